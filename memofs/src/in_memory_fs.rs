@@ -108,6 +108,34 @@ impl InMemoryFsInner {
         Ok(())
     }
 
+    /// Builds a snapshot of the entry at the given path and its descendants.
+    fn to_snapshot(&self, path: &Path) -> VfsSnapshot {
+        match self.entries.get(path) {
+            Some(Entry::File { contents }) => VfsSnapshot::File {
+                contents: contents.clone(),
+            },
+            Some(Entry::Dir { children }) => VfsSnapshot::Dir {
+                children: children
+                    .iter()
+                    .filter_map(|child| {
+                        let name = child.file_name()?.to_str()?.to_owned();
+                        Some((name, self.to_snapshot(child)))
+                    })
+                    .collect(),
+            },
+            None => VfsSnapshot::empty_dir(),
+        }
+    }
+
+    /// Removes the entry at the given path from its parent's children.
+    fn unlink(&mut self, path: &Path) {
+        if let Some(parent_path) = path.parent() {
+            if let Some(Entry::Dir { children }) = self.entries.get_mut(parent_path) {
+                children.remove(path);
+            }
+        }
+    }
+
     fn remove(&mut self, root_path: PathBuf) {
         self.orphans.remove(&root_path);
 
@@ -170,6 +198,46 @@ impl VfsBackend for InMemoryFs {
         }
     }
 
+    fn create_dir(&mut self, path: &Path) -> io::Result<()> {
+        let mut inner = self.inner.lock().unwrap();
+
+        if inner.entries.contains_key(path) {
+            return already_exists(path);
+        }
+
+        inner.load_snapshot(path.to_path_buf(), VfsSnapshot::empty_dir())
+    }
+
+    fn rename(&mut self, from: &Path, to: &Path) -> io::Result<()> {
+        let mut inner = self.inner.lock().unwrap();
+
+        if !inner.entries.contains_key(from) {
+            return not_found(from);
+        }
+
+        if to.starts_with(from) && to != from {
+            return Err(io::Error::new(
+                io::ErrorKind::Other,
+                format!(
+                    "cannot move {} into itself at {}",
+                    from.display(),
+                    to.display()
+                ),
+            ));
+        }
+
+        let snapshot = inner.to_snapshot(from);
+        inner.unlink(from);
+        inner.remove(from.to_owned());
+
+        if inner.entries.contains_key(to) {
+            inner.unlink(to);
+            inner.remove(to.to_owned());
+        }
+
+        inner.load_snapshot(to.to_path_buf(), snapshot)
+    }
+
     fn remove_file(&mut self, path: &Path) -> io::Result<()> {
         let mut inner = self.inner.lock().unwrap();
 
@@ -247,3 +315,10 @@ fn not_found<T>(path: &Path) -> io::Result<T> {
         format!("path {} not found", path.display()),
     ))
 }
+
+fn already_exists<T>(path: &Path) -> io::Result<T> {
+    Err(io::Error::new(
+        io::ErrorKind::AlreadyExists,
+        format!("path {} already exists", path.display()),
+    ))
+}
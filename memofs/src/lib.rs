@@ -71,6 +71,8 @@ pub trait VfsBackend: sealed::Sealed + Send + 'static {
     fn read(&mut self, path: &Path) -> io::Result<Vec<u8>>;
     fn write(&mut self, path: &Path, data: &[u8]) -> io::Result<()>;
     fn read_dir(&mut self, path: &Path) -> io::Result<ReadDir>;
+    fn create_dir(&mut self, path: &Path) -> io::Result<()>;
+    fn rename(&mut self, from: &Path, to: &Path) -> io::Result<()>;
     fn metadata(&mut self, path: &Path) -> io::Result<Metadata>;
     fn remove_file(&mut self, path: &Path) -> io::Result<()>;
     fn remove_dir_all(&mut self, path: &Path) -> io::Result<()>;
@@ -172,6 +174,18 @@ impl VfsInner {
         Ok(dir)
     }
 
+    fn create_dir<P: AsRef<Path>>(&mut self, path: P) -> io::Result<()> {
+        let path = path.as_ref();
+        self.backend.create_dir(path)
+    }
+
+    fn rename<P: AsRef<Path>, Q: AsRef<Path>>(&mut self, from: P, to: Q) -> io::Result<()> {
+        let from = from.as_ref();
+        let to = to.as_ref();
+        let _ = self.backend.unwatch(from);
+        self.backend.rename(from, to)
+    }
+
     fn remove_file<P: AsRef<Path>>(&mut self, path: P) -> io::Result<()> {
         let path = path.as_ref();
         let _ = self.backend.unwatch(path);
@@ -284,6 +298,29 @@ impl Vfs {
         self.inner.lock().unwrap().read_dir(path)
     }
 
+    /// Create a directory.
+    ///
+    /// Roughly equivalent to [`std::fs::create_dir`][std::fs::create_dir].
+    ///
+    /// [std::fs::create_dir]: https://doc.rust-lang.org/stable/std/fs/fn.create_dir.html
+    #[inline]
+    pub fn create_dir<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        let path = path.as_ref();
+        self.inner.lock().unwrap().create_dir(path)
+    }
+
+    /// Rename a file or directory, moving it if needed.
+    ///
+    /// Roughly equivalent to [`std::fs::rename`][std::fs::rename].
+    ///
+    /// [std::fs::rename]: https://doc.rust-lang.org/stable/std/fs/fn.rename.html
+    #[inline]
+    pub fn rename<P: AsRef<Path>, Q: AsRef<Path>>(&self, from: P, to: Q) -> io::Result<()> {
+        let from = from.as_ref();
+        let to = to.as_ref();
+        self.inner.lock().unwrap().rename(from, to)
+    }
+
     /// Remove a file.
     ///
     /// Roughly equivalent to [`std::fs::remove_file`][std::fs::remove_file].
@@ -386,6 +423,29 @@ impl VfsLock<'_> {
         self.inner.read_dir(path)
     }
 
+    /// Create a directory.
+    ///
+    /// Roughly equivalent to [`std::fs::create_dir`][std::fs::create_dir].
+    ///
+    /// [std::fs::create_dir]: https://doc.rust-lang.org/stable/std/fs/fn.create_dir.html
+    #[inline]
+    pub fn create_dir<P: AsRef<Path>>(&mut self, path: P) -> io::Result<()> {
+        let path = path.as_ref();
+        self.inner.create_dir(path)
+    }
+
+    /// Rename a file or directory, moving it if needed.
+    ///
+    /// Roughly equivalent to [`std::fs::rename`][std::fs::rename].
+    ///
+    /// [std::fs::rename]: https://doc.rust-lang.org/stable/std/fs/fn.rename.html
+    #[inline]
+    pub fn rename<P: AsRef<Path>, Q: AsRef<Path>>(&mut self, from: P, to: Q) -> io::Result<()> {
+        let from = from.as_ref();
+        let to = to.as_ref();
+        self.inner.rename(from, to)
+    }
+
     /// Remove a file.
     ///
     /// Roughly equivalent to [`std::fs::remove_file`][std::fs::remove_file].
@@ -76,6 +76,14 @@ impl VfsBackend for StdBackend {
         })
     }
 
+    fn create_dir(&mut self, path: &Path) -> io::Result<()> {
+        fs_err::create_dir(path)
+    }
+
+    fn rename(&mut self, from: &Path, to: &Path) -> io::Result<()> {
+        fs_err::rename(from, to)
+    }
+
     fn remove_file(&mut self, path: &Path) -> io::Result<()> {
         fs_err::remove_file(path)
     }
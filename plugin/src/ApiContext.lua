@@ -218,6 +218,17 @@ function ApiContext:retrieveMessages()
 	end
 
 	return sendRequest()
+		:andThen(function(response)
+			-- The server no longer has the changes after our cursor.
+			if response.code == 410 then
+				return Promise.reject(
+					"Rojo missed too many changes from the server to keep syncing." ..
+					"\nReconnect to sync the whole project again."
+				)
+			end
+
+			return response
+		end)
 		:andThen(rejectFailedRequests)
 		:andThen(Http.Response.json)
 		:andThen(function(body)
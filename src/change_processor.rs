@@ -1,8 +1,11 @@
 use std::{
-    fs,
+    collections::HashMap,
+    path::{Path, PathBuf},
     sync::{Arc, Mutex},
+    time::{Duration, Instant},
 };
 
+use anyhow::{bail, format_err};
 use crossbeam_channel::{select, Receiver, RecvError, Sender};
 use jod_thread::JoinHandle;
 use memofs::{IoResultExt, Vfs, VfsEvent};
@@ -10,12 +13,49 @@ use rbx_dom_weak::types::{Ref, Variant};
 
 use crate::{
     message_queue::MessageQueue,
+    project::{Project, ProjectNode},
+    resolution::UnresolvedValue,
     snapshot::{
-        apply_patch_set, compute_patch_set, AppliedPatchSet, InstigatingSource, PatchSet, RojoTree,
+        apply_patch_set, compute_patch_set, AppliedPatchSet, InstanceSnapshot, InstigatingSource,
+        PatchAdd, PatchSet, PatchUpdate, RojoTree,
     },
-    snapshot_middleware::{snapshot_from_vfs, snapshot_project_node},
+    snapshot_middleware::{
+        snapshot_from_vfs, snapshot_project_node, AdjacentMetadata, DirectoryMetadata,
+        INIT_SCRIPT_NAMES,
+    },
+    syncback,
 };
 
+/// How long after writing a file on behalf of a client we'll ignore filesystem
+/// events for it. Events for our own writes usually arrive within the VFS
+/// debounce window, and some never arrive at all.
+const SUPPRESS_WINDOW: Duration = Duration::from_secs(2);
+
+/// How many projects deep we'll follow `extends` when writing changes back to
+/// project files.
+const MAX_EXTENDS_DEPTH: usize = 64;
+
+/// A problem that kept an instance from being updated after the files it comes
+/// from changed. The instance keeps its last good state until the problem is
+/// fixed.
+#[derive(Debug, Clone)]
+pub struct SnapshotError {
+    /// The file or project that the failing instance comes from.
+    pub path: PathBuf,
+
+    /// A description of what went wrong, including its causes.
+    pub message: String,
+}
+
+impl SnapshotError {
+    fn new(path: &Path, error: anyhow::Error) -> Self {
+        Self {
+            path: path.to_path_buf(),
+            message: format!("{:?}", error),
+        }
+    }
+}
+
 /// Processes file change events, updates the DOM, and sends those updates
 /// through a channel for other stuff to consume.
 ///
@@ -45,11 +85,13 @@ pub struct ChangeProcessor {
 
 impl ChangeProcessor {
     /// Spin up the ChangeProcessor, connecting it to the given tree, VFS, and
-    /// outbound message queue.
+    /// outbound message queue. Errors from snapshotting changed files are
+    /// recorded in `snapshot_errors`.
     pub fn start(
         tree: Arc<Mutex<RojoTree>>,
         vfs: Arc<Vfs>,
         message_queue: Arc<MessageQueue<AppliedPatchSet>>,
+        snapshot_errors: Arc<Mutex<HashMap<Ref, SnapshotError>>>,
         tree_mutation_receiver: Receiver<PatchSet>,
     ) -> Self {
         let (shutdown_sender, shutdown_receiver) = crossbeam_channel::bounded(1);
@@ -58,6 +100,8 @@ impl ChangeProcessor {
             tree,
             vfs,
             message_queue,
+            snapshot_errors,
+            own_writes: Mutex::new(HashMap::new()),
         };
 
         let job_thread = jod_thread::Builder::new()
@@ -111,6 +155,23 @@ struct JobThreadContext {
     /// Whenever changes are applied to the DOM, we should push those changes
     /// into this message queue to inform any connected clients.
     message_queue: Arc<MessageQueue<AppliedPatchSet>>,
+
+    /// The most recent error for each instance that could not be updated. An
+    /// instance's entry is removed once it updates successfully again or is
+    /// removed from the tree.
+    snapshot_errors: Arc<Mutex<HashMap<Ref, SnapshotError>>>,
+
+    /// Files that we wrote on behalf of a client, with their contents and when
+    /// they were written. Events for these files are ignored while they're
+    /// recent and still have the same contents, since the tree already
+    /// reflects them.
+    own_writes: Mutex<HashMap<PathBuf, OwnWrite>>,
+}
+
+/// A file that we wrote on behalf of a client.
+struct OwnWrite {
+    contents: Arc<Vec<u8>>,
+    written_at: Instant,
 }
 
 impl JobThreadContext {
@@ -118,9 +179,20 @@ impl JobThreadContext {
         log::trace!("Vfs event: {:?}", event);
 
         // Update the VFS immediately with the event.
-        self.vfs
-            .commit_event(&event)
-            .expect("Error applying VFS change");
+        if let Err(err) = self.vfs.commit_event(&event) {
+            log::error!("Error applying VFS change {:?}: {}", event, err);
+            return;
+        }
+
+        if let VfsEvent::Create(path) | VfsEvent::Write(path) = &event {
+            if self.is_own_write(path) {
+                log::trace!(
+                    "Ignoring event for path written by Rojo: {}",
+                    path.display()
+                );
+                return;
+            }
+        }
 
         // For a given VFS event, we might have many changes to different parts
         // of the tree. Calculate and apply all of these changes.
@@ -152,11 +224,29 @@ impl JobThreadContext {
                         }
                     };
 
+                    let mut snapshot_errors = self.snapshot_errors.lock().unwrap();
+
                     for id in affected_ids {
-                        if let Some(patch) = compute_and_apply_changes(&mut tree, &self.vfs, id) {
-                            applied_patches.push(patch);
+                        match compute_and_apply_changes(&mut tree, &self.vfs, id) {
+                            Ok(Some(patch)) => {
+                                snapshot_errors.remove(&id);
+                                applied_patches.push(patch);
+                            }
+                            Ok(None) => {}
+                            Err(err) => {
+                                log::error!(
+                                    "Could not update instance from {}: {}",
+                                    err.path.display(),
+                                    err.message
+                                );
+                                snapshot_errors.insert(id, err);
+                            }
                         }
                     }
+
+                    // Errors belonging to instances that no longer exist can
+                    // never be fixed, so we forget about them.
+                    snapshot_errors.retain(|&id, _| tree.get_instance(id).is_some());
                 }
                 _ => log::warn!("Unhandled VFS event: {:?}", event),
             }
@@ -169,7 +259,7 @@ impl JobThreadContext {
         self.message_queue.push_messages(&applied_patches);
     }
 
-    fn handle_tree_event(&self, patch_set: PatchSet) {
+    fn handle_tree_event(&self, mut patch_set: PatchSet) {
         log::trace!("Applying PatchSet from client: {:#?}", patch_set);
 
         let applied_patch = {
@@ -179,7 +269,15 @@ impl JobThreadContext {
                 if let Some(instance) = tree.get_instance(id) {
                     if let Some(instigating_source) = &instance.metadata().instigating_source {
                         match instigating_source {
-                            InstigatingSource::Path(path) => fs::remove_file(path).unwrap(),
+                            InstigatingSource::Path(path) => {
+                                if let Err(err) = remove_instance_path(&self.vfs, path) {
+                                    log::error!(
+                                        "Could not remove instance {:?} from the filesystem: {:?}",
+                                        id,
+                                        err
+                                    );
+                                }
+                            }
                             InstigatingSource::ProjectNode(_, _, _, _) => {
                                 log::warn!(
                                     "Cannot remove instance {:?}, it's from a project file",
@@ -203,53 +301,57 @@ impl JobThreadContext {
                 let id = update.id;
 
                 if let Some(instance) = tree.get_instance(id) {
-                    if update.changed_name.is_some() {
-                        log::warn!("Cannot rename instances yet.");
-                    }
-
-                    if update.changed_class_name.is_some() {
-                        log::warn!("Cannot change ClassName yet.");
-                    }
-
                     if update.changed_metadata.is_some() {
                         log::warn!("Cannot change metadata yet.");
                     }
 
-                    for (key, changed_value) in &update.changed_properties {
-                        if key == "Source" {
-                            if let Some(instigating_source) =
-                                &instance.metadata().instigating_source
-                            {
-                                match instigating_source {
-                                    InstigatingSource::Path(path) => {
-                                        if let Some(Variant::String(value)) = changed_value {
-                                            fs::write(path, value).unwrap();
-                                        } else {
-                                            log::warn!("Cannot change Source to non-string value.");
-                                        }
-                                    }
-                                    InstigatingSource::ProjectNode(_, _, _, _) => {
-                                        log::warn!(
-                                            "Cannot remove instance {:?}, it's from a project file",
-                                            id
-                                        );
-                                    }
-                                }
-                            } else {
-                                log::warn!(
-                                    "Cannot update instance {:?}, it is not an instigating source.",
-                                    id
-                                );
-                            }
-                        } else {
-                            log::warn!("Cannot change properties besides BaseScript.Source.");
+                    if let Some(instigating_source) = &instance.metadata().instigating_source {
+                        let result = write_instance_update(
+                            &self.vfs,
+                            instance.name(),
+                            instigating_source,
+                            &project_node_names(&tree, id),
+                            instance.metadata().context.profile.as_deref(),
+                            update,
+                        );
+
+                        if let Err(err) = result {
+                            log::error!(
+                                "Could not write changes to instance {:?} back to the filesystem: {:?}",
+                                id,
+                                err
+                            );
                         }
+                    } else {
+                        log::warn!(
+                            "Cannot update instance {:?}, it is not an instigating source.",
+                            id
+                        );
                     }
                 } else {
                     log::warn!("Cannot update instance {:?}, it does not exist.", id);
                 }
             }
 
+            // Instances added by the client are replaced with snapshots of the
+            // files we wrote for them, which carry the metadata needed to keep
+            // them in sync.
+            let added_instances = std::mem::take(&mut patch_set.added_instances);
+            for patch_add in added_instances {
+                match self.write_added_instance(&tree, &patch_add) {
+                    Ok(instance) => patch_set.added_instances.push(PatchAdd {
+                        parent_id: patch_add.parent_id,
+                        instance,
+                    }),
+                    Err(err) => log::error!(
+                        "Could not create instance {} under {:?} on the filesystem: {:?}",
+                        patch_add.instance.name,
+                        patch_add.parent_id,
+                        err
+                    ),
+                }
+            }
+
             apply_patch_set(&mut tree, patch_set)
         };
 
@@ -257,7 +359,122 @@ impl JobThreadContext {
     }
 }
 
-fn compute_and_apply_changes(tree: &mut RojoTree, vfs: &Vfs, id: Ref) -> Option<AppliedPatchSet> {
+impl JobThreadContext {
+    /// Tells whether the file at the given path still looks exactly like we
+    /// wrote it moments ago. Entries are forgotten once they're checked or too
+    /// old, so that a user's own edit to the file is never ignored.
+    fn is_own_write(&self, path: &Path) -> bool {
+        let mut own_writes = self.own_writes.lock().unwrap();
+        own_writes.retain(|_, own_write| own_write.written_at.elapsed() < SUPPRESS_WINDOW);
+
+        match own_writes.remove(path) {
+            Some(own_write) => match self.vfs.read(path) {
+                Ok(contents) => contents == own_write.contents,
+                Err(_) => false,
+            },
+            None => false,
+        }
+    }
+
+    fn write_added_instance(
+        &self,
+        tree: &RojoTree,
+        patch_add: &PatchAdd,
+    ) -> anyhow::Result<InstanceSnapshot> {
+        let parent = tree.get_instance(patch_add.parent_id).ok_or_else(|| {
+            format_err!("Parent instance {:?} does not exist", patch_add.parent_id)
+        })?;
+
+        let instigating_source =
+            parent
+                .metadata()
+                .instigating_source
+                .as_ref()
+                .ok_or_else(|| {
+                    format_err!(
+                        "Parent instance {:?} is not an instigating source",
+                        patch_add.parent_id
+                    )
+                })?;
+
+        let parent_dir = instance_directory(&self.vfs, instigating_source)?;
+        let written = syncback::write_instance(&self.vfs, &parent_dir, &patch_add.instance)?;
+
+        {
+            let mut own_writes = self.own_writes.lock().unwrap();
+            let written_at = Instant::now();
+
+            for path in &written {
+                if let Ok(contents) = self.vfs.read(path) {
+                    own_writes.insert(
+                        path.clone(),
+                        OwnWrite {
+                            contents,
+                            written_at,
+                        },
+                    );
+                }
+            }
+        }
+
+        snapshot_from_vfs(&parent.metadata().context, &self.vfs, &written[0])?.ok_or_else(|| {
+            format_err!(
+                "Files written for instance {} did not turn back into an instance",
+                patch_add.instance.name
+            )
+        })
+    }
+}
+
+/// Finds the directory that children of the instance with the given
+/// instigating source should be written into.
+fn instance_directory(
+    vfs: &Vfs,
+    instigating_source: &InstigatingSource,
+) -> anyhow::Result<PathBuf> {
+    let path = match instigating_source {
+        InstigatingSource::Path(path) if Project::is_project_file(path) => {
+            let project = Project::load_from_slice(&vfs.read(path)?, path)?;
+
+            match &project.tree.path {
+                Some(tree_path) => project.folder_location().join(tree_path),
+                None => bail!(
+                    "Cannot add children to the root of project {}, it has no $path.",
+                    path.display()
+                ),
+            }
+        }
+        InstigatingSource::Path(path) => path.clone(),
+        InstigatingSource::ProjectNode(project_path, node_name, node, _) => match &node.path {
+            Some(node_path) => project_path.parent().unwrap().join(node_path),
+            None => bail!(
+                "Cannot add children to project node {} in {}, it has no $path.",
+                node_name,
+                project_path.display()
+            ),
+        },
+    };
+
+    if !vfs.metadata(&path)?.is_dir() {
+        bail!(
+            "Cannot add children to the instance from {}, it is not a directory.",
+            path.display()
+        );
+    }
+
+    Ok(path)
+}
+
+/// Snapshots the files that the given instance came from again and applies the
+/// difference to the tree.
+///
+/// If snapshotting fails, the tree is left untouched so that the instance
+/// keeps its last good state.
+fn compute_and_apply_changes(
+    tree: &mut RojoTree,
+    vfs: &Vfs,
+    id: Ref,
+) -> Result<Option<AppliedPatchSet>, SnapshotError> {
     let metadata = tree
         .get_metadata(id)
         .expect("metadata missing for instance present in tree");
@@ -270,7 +487,7 @@ fn compute_and_apply_changes(tree: &mut RojoTree, vfs: &Vfs, id: Ref) -> Option<
                 id
             );
             log::error!("This is a bug. Please file an issue!");
-            return None;
+            return Ok(None);
         }
     };
 
@@ -291,12 +508,9 @@ fn compute_and_apply_changes(tree: &mut RojoTree, vfs: &Vfs, id: Ref) -> Option<
                             path.display()
                         );
                         log::error!("This may be a bug!");
-                        return None;
-                    }
-                    Err(err) => {
-                        log::error!("Snapshot error: {:?}", err);
-                        return None;
+                        return Ok(None);
                     }
+                    Err(err) => return Err(SnapshotError::new(path, err)),
                 };
 
                 let patch_set = compute_patch_set(&snapshot, &tree, id);
@@ -314,10 +528,7 @@ fn compute_and_apply_changes(tree: &mut RojoTree, vfs: &Vfs, id: Ref) -> Option<
 
                 apply_patch_set(tree, patch_set)
             }
-            Err(err) => {
-                log::error!("Error processing filesystem change: {:?}", err);
-                return None;
-            }
+            Err(err) => return Err(SnapshotError::new(path, err.into())),
         },
 
         InstigatingSource::ProjectNode(project_path, instance_name, project_node, parent_class) => {
@@ -339,12 +550,9 @@ fn compute_and_apply_changes(tree: &mut RojoTree, vfs: &Vfs, id: Ref) -> Option<
                 Ok(None) => {
                     log::error!("Snapshot did not return an instance from a project node.");
                     log::error!("This is a bug!");
-                    return None;
-                }
-                Err(err) => {
-                    log::error!("{:?}", err);
-                    return None;
+                    return Ok(None);
                 }
+                Err(err) => return Err(SnapshotError::new(project_path, err)),
             };
 
             let patch_set = compute_patch_set(&snapshot, &tree, id);
@@ -352,5 +560,1133 @@ fn compute_and_apply_changes(tree: &mut RojoTree, vfs: &Vfs, id: Ref) -> Option<
         }
     };
 
-    Some(applied_patch_set)
+    Ok(Some(applied_patch_set))
+}
+
+/// Removes the file or directory that an instance came from.
+fn remove_instance_path(vfs: &Vfs, path: &Path) -> anyhow::Result<()> {
+    if vfs.metadata(path)?.is_dir() {
+        vfs.remove_dir_all(path)?;
+    } else {
+        vfs.remove_file(path)?;
+    }
+
+    Ok(())
+}
+
+/// Persists a change made to an instance by a client, like the Roblox Studio
+/// plugin, into the files that the instance came from.
+///
+/// Property changes are written into the instance's script, adjacent
+/// `.meta.json` file, or project node. ClassName changes are expressed by
+/// changing file extensions or `className` fields, and renames move the
+/// backing files.
+///
+/// `node_names` are the names of the project nodes leading to the instance
+/// from the root of its project, if it comes from a project node, and
+/// `profile` is the project profile that the instance was built with.
+fn write_instance_update(
+    vfs: &Vfs,
+    instance_name: &str,
+    instigating_source: &InstigatingSource,
+    node_names: &[String],
+    profile: Option<&str>,
+    update: &PatchUpdate,
+) -> anyhow::Result<()> {
+    match instigating_source {
+        // The root instance of a project is attributed to the project file
+        // itself instead of to its root project node.
+        InstigatingSource::Path(path) if Project::is_project_file(path) => {
+            write_project_node_update(vfs, path, None, &[], profile, update)
+        }
+        InstigatingSource::Path(path) => write_path_update(vfs, path, instance_name, update),
+        InstigatingSource::ProjectNode(project_path, _, node, _) => {
+            write_project_node_update(vfs, project_path, Some(node), node_names, profile, update)
+        }
+    }
+}
+
+fn write_path_update(
+    vfs: &Vfs,
+    path: &Path,
+    instance_name: &str,
+    update: &PatchUpdate,
+) -> anyhow::Result<()> {
+    let is_dir = vfs.metadata(path)?.is_dir();
+    let mut path = path.to_path_buf();
+
+    let mut changed_properties = Vec::new();
+    for (key, value) in &update.changed_properties {
+        match key.as_str() {
+            "Source" => write_source(vfs, &path, value.as_ref())?,
+
+            // Meta files are applied after the file's contents, so these
+            // properties can't go into one without shadowing the file for good.
+            "Value" if !is_dir && file_name_ends_with(&path, ".txt") => {
+                write_text(vfs, &path, value.as_ref())?
+            }
+            "Contents" if is_dir || file_name_ends_with(&path, ".csv") => bail!(
+                "Cannot change Contents of the LocalizationTable from {}, \
+                 edit its CSV files instead.",
+                path.display()
+            ),
+
+            _ => changed_properties.push((key, value)),
+        }
+    }
+
+    if is_dir {
+        let meta_path = path.join("init.meta.json");
+        let mut meta = match vfs.read(&meta_path).with_not_found()? {
+            Some(contents) => DirectoryMetadata::from_slice(&contents, meta_path.clone())?,
+            None => DirectoryMetadata::default(),
+        };
+        let mut meta_changed = !changed_properties.is_empty();
+
+        apply_property_changes(&mut meta.properties, changed_properties);
+
+        if let Some(class_name) = &update.changed_class_name {
+            // Directories with init scripts take on the class of that script,
+            // so changing class means renaming the script instead.
+            match find_init_script(vfs, &path)? {
+                Some(init_path) => {
+                    rename_script_for_class(vfs, &init_path, class_name)?;
+                }
+                None => {
+                    meta.class_name = if class_name == "Folder" {
+                        None
+                    } else {
+                        Some(class_name.clone())
+                    };
+                    meta_changed = true;
+                }
+            }
+        }
+
+        if meta_changed {
+            vfs.write(&meta_path, serde_json::to_string_pretty(&meta)?)?;
+        }
+    } else {
+        if !changed_properties.is_empty() {
+            if !supports_adjacent_meta(&path) {
+                bail!(
+                    "Cannot change properties of the instance from {}, \
+                     this kind of file does not support .meta.json files.",
+                    path.display()
+                );
+            }
+
+            let meta_path = path.with_file_name(format!("{}.meta.json", instance_name));
+            let mut meta = match vfs.read(&meta_path).with_not_found()? {
+                Some(contents) => AdjacentMetadata::from_slice(&contents, meta_path.clone())?,
+                None => AdjacentMetadata::default(),
+            };
+
+            apply_property_changes(&mut meta.properties, changed_properties);
+
+            vfs.write(&meta_path, serde_json::to_string_pretty(&meta)?)?;
+        }
+
+        if let Some(class_name) = &update.changed_class_name {
+            path = rename_script_for_class(vfs, &path, class_name)?;
+        }
+    }
+
+    if let Some(new_name) = &update.changed_name {
+        rename_instance_path(vfs, &path, is_dir, instance_name, new_name)?;
+    }
+
+    Ok(())
+}
+
+fn write_project_node_update(
+    vfs: &Vfs,
+    project_path: &Path,
+    built_node: Option<&ProjectNode>,
+    node_names: &[String],
+    profile: Option<&str>,
+    update: &PatchUpdate,
+) -> anyhow::Result<()> {
+    let node_display = if node_names.is_empty() {
+        "at the root".to_owned()
+    } else {
+        node_names.join("/")
+    };
+
+    // The instance was built from this project merged with every project it
+    // extends, so the node might be defined in any of them.
+    let mut projects = load_extends_chain(vfs, project_path)?;
+
+    if let Some(profile) = profile {
+        for project in &projects {
+            let profile_node = project
+                .profiles
+                .get(profile)
+                .and_then(|profile| profile.tree.as_ref())
+                .and_then(|tree| find_project_node(tree, node_names));
+
+            if let Some(profile_node) = profile_node {
+                if update.changed_name.is_some() || changes_node(profile_node) {
+                    bail!(
+                        "Cannot change project node {}, it is changed by profile '{}'.\n\
+                         Project path: {}",
+                        node_display,
+                        profile,
+                        project.file_location.display()
+                    );
+                }
+            }
+        }
+    }
+
+    let defined_in: Vec<usize> = projects
+        .iter()
+        .enumerate()
+        .filter(|(_, project)| find_project_node(&project.tree, node_names).is_some())
+        .map(|(index, _)| index)
+        .collect();
+
+    // Changes are written to the first project that defines the node, since
+    // it wins over the projects that it extends.
+    let index = match defined_in.first() {
+        Some(&index) => index,
+        None => bail!(
+            "Could not find project node {} in project file {} or any project it extends",
+            node_display,
+            project_path.display()
+        ),
+    };
+
+    if update.changed_name.is_some() && defined_in.len() > 1 {
+        bail!(
+            "Cannot rename project node {}, it is defined in more than one project file.\n\
+             Project path: {}",
+            node_display,
+            project_path.display()
+        );
+    }
+
+    for (key, value) in &update.changed_properties {
+        if value.is_some() {
+            continue;
+        }
+
+        for &base_index in &defined_in[1..] {
+            let base = &projects[base_index];
+            let base_node = find_project_node(&base.tree, node_names).unwrap();
+
+            if base_node.properties.contains_key(key) {
+                bail!(
+                    "Cannot remove property {} from project node {}, it is set by {}.",
+                    key,
+                    node_display,
+                    base.file_location.display()
+                );
+            }
+        }
+    }
+
+    let mut project = projects.swap_remove(index);
+    let project_file = project.file_location.clone();
+
+    // Where a script pointed to by $path lives. The node the instance was
+    // built from knows this even if another project set $path.
+    let script_path = match built_node {
+        Some(built_node) => built_node
+            .path
+            .as_ref()
+            .map(|path| project_path.parent().unwrap().join(path)),
+        None => project
+            .tree
+            .path
+            .as_ref()
+            .map(|path| project.folder_location().join(path)),
+    };
+
+    let node = find_project_node_mut(&mut project.tree, node_names).unwrap();
+    let mut project_changed = false;
+
+    for (key, value) in &update.changed_properties {
+        // Scripts pointed to by $path own their Source.
+        if let (Some(path), "Source") = (&script_path, key.as_str()) {
+            write_source(vfs, path, value.as_ref())?;
+        } else {
+            apply_property_changes(&mut node.properties, Some((key, value)));
+            project_changed = true;
+        }
+    }
+
+    if let Some(class_name) = &update.changed_class_name {
+        if script_path.is_some() {
+            bail!(
+                "Cannot change ClassName of an instance whose project node has $path set.\n\
+                 Project path: {}",
+                project_file.display()
+            );
+        }
+
+        node.class_name = Some(class_name.clone());
+        project_changed = true;
+    }
+
+    if let Some(new_name) = &update.changed_name {
+        match node_names.split_last() {
+            Some((old_name, parent_names)) => {
+                let parent = find_project_node_mut(&mut project.tree, parent_names).unwrap();
+
+                if parent.children.contains_key(new_name) {
+                    bail!(
+                        "Cannot rename project node {} to {}, a node with that name already exists.\n\
+                         Project path: {}",
+                        old_name,
+                        new_name,
+                        project_file.display()
+                    );
+                }
+
+                let node = parent.children.remove(old_name).unwrap();
+                parent.children.insert(new_name.clone(), node);
+            }
+            None => project.name = new_name.clone(),
+        }
+
+        project_changed = true;
+    }
+
+    if project_changed {
+        vfs.write(&project_file, serde_json::to_string_pretty(&project)?)?;
+    }
+
+    Ok(())
+}
+
+/// Loads a project file exactly as it's written, followed by every project it
+/// extends, in order.
+fn load_extends_chain(vfs: &Vfs, project_path: &Path) -> anyhow::Result<Vec<Project>> {
+    let mut projects: Vec<Project> = Vec::new();
+    let mut next_path = Some(project_path.to_path_buf());
+
+    while let Some(path) = next_path {
+        // Cycles are reported when the project is built, so we only need to
+        // make sure not to loop forever here.
+        if projects.len() > MAX_EXTENDS_DEPTH {
+            bail!(
+                "Project {} extends too many other projects",
+                project_path.display()
+            );
+        }
+
+        let project = Project::load_from_slice(&vfs.read(&path)?, &path)?;
+        next_path = project
+            .extends
+            .as_ref()
+            .map(|extends| project.folder_location().join(extends));
+        projects.push(project);
+    }
+
+    Ok(projects)
+}
+
+/// Tells whether a node from a profile changes the node that it's merged with,
+/// instead of only leading to one of its children.
+fn changes_node(node: &ProjectNode) -> bool {
+    node.class_name.is_some()
+        || node.path.is_some()
+        || node.ignore_unknown_instances.is_some()
+        || !node.properties.is_empty()
+        || !node.attributes.is_empty()
+}
+
+fn find_project_node<'a>(root: &'a ProjectNode, names: &[String]) -> Option<&'a ProjectNode> {
+    names
+        .iter()
+        .try_fold(root, |node, name| node.children.get(name))
+}
+
+fn find_project_node_mut<'a>(
+    root: &'a mut ProjectNode,
+    names: &[String],
+) -> Option<&'a mut ProjectNode> {
+    names
+        .iter()
+        .try_fold(root, |node, name| node.children.get_mut(name))
+}
+
+/// Finds the names of the project nodes leading from the root of a project to
+/// the instance with the given ID. Empty if the instance doesn't come from a
+/// project node.
+fn project_node_names(tree: &RojoTree, id: Ref) -> Vec<String> {
+    let mut names = Vec::new();
+    let mut project_path = None;
+    let mut current = tree.get_instance(id);
+
+    while let Some(instance) = current {
+        match &instance.metadata().instigating_source {
+            // Nodes from nested projects have a different project path than
+            // the nodes they're nested under.
+            Some(InstigatingSource::ProjectNode(path, name, _, _))
+                if project_path.is_none() || project_path == Some(path) =>
+            {
+                project_path = Some(path);
+                names.push(name.clone());
+                current = tree.get_instance(instance.parent());
+            }
+            _ => break,
+        }
+    }
+
+    names.reverse();
+    names
+}
+
+fn apply_property_changes<'a>(
+    properties: &mut HashMap<String, UnresolvedValue>,
+    changes: impl IntoIterator<Item = (&'a String, &'a Option<Variant>)>,
+) {
+    for (key, value) in changes {
+        match value {
+            Some(value) => {
+                properties.insert(key.clone(), UnresolvedValue::FullyQualified(value.clone()));
+            }
+            None => {
+                properties.remove(key);
+            }
+        }
+    }
+}
+
+fn write_source(vfs: &Vfs, path: &Path, value: Option<&Variant>) -> anyhow::Result<()> {
+    let source = match value {
+        Some(Variant::String(value)) => value,
+        _ => bail!("Cannot change Source to non-string value."),
+    };
+
+    let script_path = if vfs.metadata(path)?.is_dir() {
+        find_init_script(vfs, path)?.ok_or_else(|| {
+            format_err!(
+                "Cannot change Source, directory {} has no init script.",
+                path.display()
+            )
+        })?
+    } else {
+        path.to_path_buf()
+    };
+
+    // Other files that turn into scripts, like JSON files, hold data instead
+    // of Lua, so writing Source into them would break them.
+    if !is_lua_file(&script_path) {
+        bail!(
+            "Cannot change Source of the instance from {}, it is not a Lua script.",
+            script_path.display()
+        );
+    }
+
+    vfs.write(script_path, source)?;
+
+    Ok(())
+}
+
+fn write_text(vfs: &Vfs, path: &Path, value: Option<&Variant>) -> anyhow::Result<()> {
+    let value = match value {
+        Some(Variant::String(value)) => value,
+        _ => bail!("Cannot change Value to non-string value."),
+    };
+
+    vfs.write(path, value)?;
+
+    Ok(())
+}
+
+fn is_lua_file(path: &Path) -> bool {
+    file_name_ends_with(path, ".lua") || file_name_ends_with(path, ".luau")
+}
+
+fn file_name_ends_with(path: &Path, suffix: &str) -> bool {
+    path.file_name()
+        .and_then(|name| name.to_str())
+        .map(|name| name.ends_with(suffix))
+        .unwrap_or(false)
+}
+
+fn find_init_script(vfs: &Vfs, dir: &Path) -> anyhow::Result<Option<PathBuf>> {
+    for init_name in INIT_SCRIPT_NAMES {
+        let init_path = dir.join(init_name);
+        if vfs.metadata(&init_path).with_not_found()?.is_some() {
+            return Ok(Some(init_path));
+        }
+    }
+
+    Ok(None)
+}
+
+/// Tells whether the middleware that handles the given file reads an adjacent
+/// `.meta.json` file.
+fn supports_adjacent_meta(path: &Path) -> bool {
+    let file_name = match path.file_name().and_then(|name| name.to_str()) {
+        Some(name) => name,
+        None => return false,
+    };
+
+    if file_name.ends_with(".model.json")
+        || file_name.ends_with(".project.json")
+        || file_name.ends_with(".meta.json")
+    {
+        return false;
+    }
+
+    [
+        ".lua", ".luau", ".txt", ".csv", ".json", ".toml", ".yaml", ".yml", ".rbxm", ".rbxmx",
+    ]
+    .iter()
+    .any(|extension| file_name.ends_with(extension))
+}
+
+/// Renames the script at the given path so that it turns into an instance of
+/// the given class, returning the script's new path.
+fn rename_script_for_class(vfs: &Vfs, path: &Path, class_name: &str) -> anyhow::Result<PathBuf> {
+    let new_file_name = path
+        .file_name()
+        .and_then(|name| name.to_str())
+        .and_then(|name| script_file_name_for_class(name, class_name))
+        .ok_or_else(|| {
+            format_err!(
+                "Cannot change ClassName of the instance from {} to {}.\n\
+                 Only ModuleScript, Script, and LocalScript instances from Lua \
+                 files can change ClassName.",
+                path.display(),
+                class_name
+            )
+        })?;
+
+    let new_path = path.with_file_name(new_file_name);
+    if new_path != path {
+        move_path(vfs, path, &new_path)?;
+    }
+
+    Ok(new_path)
+}
+
+/// Computes the name a Lua file should have to turn into an instance of the
+/// given class, like `foo.server.lua` for a Script named `foo`.
+fn script_file_name_for_class(file_name: &str, class_name: &str) -> Option<String> {
+    let class_suffix = match class_name {
+        "ModuleScript" => "",
+        "Script" => ".server",
+        "LocalScript" => ".client",
+        _ => return None,
+    };
+
+    let (stem, extension) = if let Some(stem) = file_name.strip_suffix(".lua") {
+        (stem, "lua")
+    } else if let Some(stem) = file_name.strip_suffix(".luau") {
+        (stem, "luau")
+    } else {
+        return None;
+    };
+
+    let base = stem
+        .strip_suffix(".server")
+        .or_else(|| stem.strip_suffix(".client"))
+        .unwrap_or(stem);
+
+    Some(format!("{}{}.{}", base, class_suffix, extension))
+}
+
+/// Moves the file or directory that an instance came from so that it produces
+/// an instance with the new name. Adjacent `.meta.json` files are moved along
+/// with it.
+fn rename_instance_path(
+    vfs: &Vfs,
+    path: &Path,
+    is_dir: bool,
+    old_name: &str,
+    new_name: &str,
+) -> anyhow::Result<()> {
+    if new_name.is_empty() || new_name.contains(['/', '\\']) {
+        bail!(
+            "Cannot rename instance to {:?}, it is not a valid file name.",
+            new_name
+        );
+    }
+
+    let new_path = if is_dir {
+        path.with_file_name(new_name)
+    } else {
+        // Keep whatever follows the name in the file name, like
+        // `.server.lua`, so that the instance keeps its class.
+        let suffix = path
+            .file_name()
+            .and_then(|name| name.to_str())
+            .and_then(|name| name.strip_prefix(old_name))
+            .ok_or_else(|| {
+                format_err!(
+                    "Cannot rename instance {}, its file name does not match its name: {}",
+                    old_name,
+                    path.display()
+                )
+            })?;
+
+        path.with_file_name(format!("{}{}", new_name, suffix))
+    };
+
+    move_path(vfs, path, &new_path)?;
+
+    if !is_dir {
+        let meta_path = path.with_file_name(format!("{}.meta.json", old_name));
+
+        if vfs.metadata(&meta_path).with_not_found()?.is_some() {
+            let new_meta_path = path.with_file_name(format!("{}.meta.json", new_name));
+            move_path(vfs, &meta_path, &new_meta_path)?;
+        }
+    }
+
+    Ok(())
+}
+
+fn move_path(vfs: &Vfs, from: &Path, to: &Path) -> anyhow::Result<()> {
+    if vfs.metadata(to).with_not_found()?.is_some() {
+        bail!(
+            "Cannot move {} to {}, a file already exists there.",
+            from.display(),
+            to.display()
+        );
+    }
+
+    vfs.rename(from, to)?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use maplit::hashmap;
+    use memofs::{InMemoryFs, VfsSnapshot};
+
+    use crate::serve_session::ServeSession;
+
+    fn update_with(changes: impl FnOnce(&mut PatchUpdate)) -> PatchUpdate {
+        let mut update = PatchUpdate {
+            id: Ref::new(),
+            changed_name: None,
+            changed_class_name: None,
+            changed_properties: HashMap::new(),
+            changed_metadata: None,
+        };
+
+        changes(&mut update);
+        update
+    }
+
+    #[test]
+    fn script_file_names() {
+        assert_eq!(
+            script_file_name_for_class("foo.lua", "Script"),
+            Some("foo.server.lua".to_owned())
+        );
+        assert_eq!(
+            script_file_name_for_class("foo.server.luau", "LocalScript"),
+            Some("foo.client.luau".to_owned())
+        );
+        assert_eq!(
+            script_file_name_for_class("init.client.lua", "ModuleScript"),
+            Some("init.lua".to_owned())
+        );
+        assert_eq!(script_file_name_for_class("foo.lua", "Folder"), None);
+        assert_eq!(script_file_name_for_class("foo.txt", "Script"), None);
+    }
+
+    #[test]
+    fn property_into_adjacent_meta() {
+        let mut imfs = InMemoryFs::new();
+        imfs.load_snapshot(
+            "/root",
+            VfsSnapshot::dir(hashmap! {
+                "foo.server.lua" => VfsSnapshot::file("print('hello')"),
+            }),
+        )
+        .unwrap();
+
+        let vfs = Vfs::new(imfs);
+
+        let update = update_with(|update| {
+            update
+                .changed_properties
+                .insert("Disabled".to_owned(), Some(Variant::Bool(true)));
+        });
+
+        write_instance_update(
+            &vfs,
+            "foo",
+            &InstigatingSource::Path(PathBuf::from("/root/foo.server.lua")),
+            &[],
+            None,
+            &update,
+        )
+        .unwrap();
+
+        let meta_path = PathBuf::from("/root/foo.meta.json");
+        let meta = AdjacentMetadata::from_slice(&vfs.read(&meta_path).unwrap(), meta_path).unwrap();
+
+        assert_eq!(
+            meta.properties.get("Disabled"),
+            Some(&UnresolvedValue::FullyQualified(Variant::Bool(true)))
+        );
+    }
+
+    #[test]
+    fn source_not_written_into_data_files() {
+        let mut imfs = InMemoryFs::new();
+        imfs.load_snapshot(
+            "/root",
+            VfsSnapshot::dir(hashmap! {
+                "data.json" => VfsSnapshot::file(r#"{"hello": "world"}"#),
+            }),
+        )
+        .unwrap();
+
+        let vfs = Vfs::new(imfs);
+
+        let update = update_with(|update| {
+            update.changed_properties.insert(
+                "Source".to_owned(),
+                Some(Variant::String("return 5".to_owned())),
+            );
+        });
+
+        let result = write_instance_update(
+            &vfs,
+            "data",
+            &InstigatingSource::Path(PathBuf::from("/root/data.json")),
+            &[],
+            None,
+            &update,
+        );
+
+        assert!(result.is_err());
+        assert_eq!(
+            vfs.read("/root/data.json").unwrap().as_slice(),
+            br#"{"hello": "world"}"#
+        );
+    }
+
+    #[test]
+    fn file_contents_not_written_into_meta() {
+        let mut imfs = InMemoryFs::new();
+        imfs.load_snapshot(
+            "/root",
+            VfsSnapshot::dir(hashmap! {
+                "message.txt" => VfsSnapshot::file("Hello"),
+                "strings.csv" => VfsSnapshot::file("Key,Source\nhello,Hello"),
+            }),
+        )
+        .unwrap();
+
+        let vfs = Vfs::new(imfs);
+
+        let update = update_with(|update| {
+            update.changed_properties.insert(
+                "Value".to_owned(),
+                Some(Variant::String("Goodbye".to_owned())),
+            );
+        });
+
+        write_instance_update(
+            &vfs,
+            "message",
+            &InstigatingSource::Path(PathBuf::from("/root/message.txt")),
+            &[],
+            None,
+            &update,
+        )
+        .unwrap();
+
+        assert_eq!(
+            vfs.read("/root/message.txt").unwrap().as_slice(),
+            b"Goodbye"
+        );
+        assert!(vfs
+            .metadata("/root/message.meta.json")
+            .with_not_found()
+            .unwrap()
+            .is_none());
+
+        let update = update_with(|update| {
+            update.changed_properties.insert(
+                "Contents".to_owned(),
+                Some(Variant::String("[]".to_owned())),
+            );
+        });
+
+        let result = write_instance_update(
+            &vfs,
+            "strings",
+            &InstigatingSource::Path(PathBuf::from("/root/strings.csv")),
+            &[],
+            None,
+            &update,
+        );
+
+        assert!(result.is_err());
+        assert!(vfs
+            .metadata("/root/strings.meta.json")
+            .with_not_found()
+            .unwrap()
+            .is_none());
+    }
+
+    #[test]
+    fn rename_file() {
+        let mut imfs = InMemoryFs::new();
+        imfs.load_snapshot(
+            "/root",
+            VfsSnapshot::dir(hashmap! {
+                "foo.server.lua" => VfsSnapshot::file("print('hello')"),
+                "foo.meta.json" => VfsSnapshot::file(r#"{"properties": {"Disabled": true}}"#),
+            }),
+        )
+        .unwrap();
+
+        let vfs = Vfs::new(imfs);
+
+        let update = update_with(|update| {
+            update.changed_name = Some("bar".to_owned());
+        });
+
+        write_instance_update(
+            &vfs,
+            "foo",
+            &InstigatingSource::Path(PathBuf::from("/root/foo.server.lua")),
+            &[],
+            None,
+            &update,
+        )
+        .unwrap();
+
+        assert_eq!(
+            vfs.read("/root/bar.server.lua").unwrap().as_slice(),
+            b"print('hello')"
+        );
+        assert!(vfs.read("/root/bar.meta.json").is_ok());
+
+        let mut children: Vec<_> = vfs
+            .read_dir("/root")
+            .unwrap()
+            .map(|entry| entry.unwrap().path().to_owned())
+            .collect();
+        children.sort();
+
+        assert_eq!(
+            children,
+            vec![
+                PathBuf::from("/root/bar.meta.json"),
+                PathBuf::from("/root/bar.server.lua"),
+            ]
+        );
+    }
+
+    #[test]
+    fn rename_directory() {
+        let mut imfs = InMemoryFs::new();
+        imfs.load_snapshot(
+            "/root",
+            VfsSnapshot::dir(hashmap! {
+                "foo" => VfsSnapshot::dir(hashmap! {
+                    "init.lua" => VfsSnapshot::file("return nil"),
+                    "child.lua" => VfsSnapshot::file("return 1"),
+                }),
+            }),
+        )
+        .unwrap();
+
+        let vfs = Vfs::new(imfs);
+
+        let update = update_with(|update| {
+            update.changed_name = Some("bar".to_owned());
+        });
+
+        write_instance_update(
+            &vfs,
+            "foo",
+            &InstigatingSource::Path(PathBuf::from("/root/foo")),
+            &[],
+            None,
+            &update,
+        )
+        .unwrap();
+
+        assert!(vfs
+            .metadata("/root/foo")
+            .with_not_found()
+            .unwrap()
+            .is_none());
+        assert!(vfs.metadata("/root/bar").unwrap().is_dir());
+        assert_eq!(
+            vfs.read("/root/bar/init.lua").unwrap().as_slice(),
+            b"return nil"
+        );
+        assert_eq!(
+            vfs.read("/root/bar/child.lua").unwrap().as_slice(),
+            b"return 1"
+        );
+    }
+
+    #[test]
+    fn remove_directory() {
+        let mut imfs = InMemoryFs::new();
+        imfs.load_snapshot(
+            "/root",
+            VfsSnapshot::dir(hashmap! {
+                "folder" => VfsSnapshot::dir(hashmap! {
+                    "child.lua" => VfsSnapshot::file("return 1"),
+                }),
+                "script.lua" => VfsSnapshot::file("return 2"),
+            }),
+        )
+        .unwrap();
+
+        let vfs = Vfs::new(imfs);
+
+        remove_instance_path(&vfs, Path::new("/root/folder")).unwrap();
+        remove_instance_path(&vfs, Path::new("/root/script.lua")).unwrap();
+
+        assert!(vfs
+            .metadata("/root/folder")
+            .with_not_found()
+            .unwrap()
+            .is_none());
+        assert!(vfs
+            .metadata("/root/folder/child.lua")
+            .with_not_found()
+            .unwrap()
+            .is_none());
+        assert!(vfs
+            .metadata("/root/script.lua")
+            .with_not_found()
+            .unwrap()
+            .is_none());
+    }
+
+    #[test]
+    fn class_name_into_init_meta() {
+        let mut imfs = InMemoryFs::new();
+        imfs.load_snapshot(
+            "/root",
+            VfsSnapshot::dir(hashmap! {
+                "settings" => VfsSnapshot::empty_dir(),
+            }),
+        )
+        .unwrap();
+
+        let vfs = Vfs::new(imfs);
+
+        let update = update_with(|update| {
+            update.changed_class_name = Some("Configuration".to_owned());
+        });
+
+        write_instance_update(
+            &vfs,
+            "settings",
+            &InstigatingSource::Path(PathBuf::from("/root/settings")),
+            &[],
+            None,
+            &update,
+        )
+        .unwrap();
+
+        let meta_path = PathBuf::from("/root/settings/init.meta.json");
+        let meta =
+            DirectoryMetadata::from_slice(&vfs.read(&meta_path).unwrap(), meta_path).unwrap();
+
+        assert_eq!(meta.class_name.as_deref(), Some("Configuration"));
+    }
+
+    #[test]
+    fn project_node_properties_and_rename() {
+        let project_path = PathBuf::from("/root/default.project.json");
+
+        let mut imfs = InMemoryFs::new();
+        imfs.load_snapshot(
+            "/root",
+            VfsSnapshot::dir(hashmap! {
+                "default.project.json" => VfsSnapshot::file(r#"
+                    {
+                        "name": "project-node-update",
+                        "tree": {
+                            "$className": "DataModel",
+                            "ReplicatedStorage": {
+                                "Settings": {
+                                    "$className": "Configuration"
+                                }
+                            }
+                        }
+                    }
+                "#),
+            }),
+        )
+        .unwrap();
+
+        let vfs = Vfs::new(imfs);
+
+        let project =
+            Project::load_from_slice(&vfs.read(&project_path).unwrap(), &project_path).unwrap();
+        let node = project.tree.children["ReplicatedStorage"].children["Settings"].clone();
+
+        let update = update_with(|update| {
+            update.changed_name = Some("Config".to_owned());
+            update.changed_class_name = Some("Folder".to_owned());
+            update
+                .changed_properties
+                .insert("Archivable".to_owned(), Some(Variant::Bool(false)));
+        });
+
+        write_instance_update(
+            &vfs,
+            "Settings",
+            &InstigatingSource::ProjectNode(
+                project_path.clone(),
+                "Settings".to_owned(),
+                Box::new(node),
+                Some("ReplicatedStorage".to_owned()),
+            ),
+            &["ReplicatedStorage".to_owned(), "Settings".to_owned()],
+            None,
+            &update,
+        )
+        .unwrap();
+
+        let project =
+            Project::load_from_slice(&vfs.read(&project_path).unwrap(), &project_path).unwrap();
+        let storage = &project.tree.children["ReplicatedStorage"];
+
+        assert!(!storage.children.contains_key("Settings"));
+
+        let node = &storage.children["Config"];
+        assert_eq!(node.class_name.as_deref(), Some("Folder"));
+        assert_eq!(
+            node.properties.get("Archivable"),
+            Some(&UnresolvedValue::FullyQualified(Variant::Bool(false)))
+        );
+    }
+
+    #[test]
+    fn project_node_from_extended_project() {
+        let mut imfs = InMemoryFs::new();
+        imfs.load_snapshot(
+            "/root",
+            VfsSnapshot::dir(hashmap! {
+                "base.project.json" => VfsSnapshot::file(r#"
+                    {
+                        "name": "base",
+                        "tree": {
+                            "$className": "DataModel",
+                            "ReplicatedStorage": {
+                                "Shared": {
+                                    "$className": "Folder"
+                                },
+                                "Settings": {
+                                    "$className": "Configuration"
+                                }
+                            }
+                        },
+                        "profiles": {
+                            "dev": {
+                                "tree": {
+                                    "ReplicatedStorage": {
+                                        "Debug": {
+                                            "$className": "Folder"
+                                        }
+                                    }
+                                }
+                            }
+                        }
+                    }
+                "#),
+                "default.project.json" => VfsSnapshot::file(r#"
+                    {
+                        "name": "game",
+                        "extends": "base.project.json",
+                        "tree": {
+                            "ReplicatedStorage": {
+                                "Settings": {
+                                    "$properties": {
+                                        "Archivable": true
+                                    }
+                                }
+                            }
+                        }
+                    }
+                "#),
+            }),
+        )
+        .unwrap();
+
+        let session = ServeSession::with_profile(Vfs::new(imfs), "/root", Some("dev")).unwrap();
+        let vfs = session.vfs();
+        let tree = session.tree();
+
+        let write = |name: &str, update: &PatchUpdate| {
+            let storage = tree.get_instance(tree.get_root_id()).unwrap().children()[0];
+            let storage = tree.get_instance(storage).unwrap();
+            assert_eq!(storage.name(), "ReplicatedStorage");
+
+            let id = storage
+                .children()
+                .iter()
+                .copied()
+                .find(|&id| tree.get_instance(id).unwrap().name() == name)
+                .unwrap();
+            let instance = tree.get_instance(id).unwrap();
+
+            write_instance_update(
+                vfs,
+                instance.name(),
+                instance.metadata().instigating_source.as_ref().unwrap(),
+                &project_node_names(&tree, id),
+                instance.metadata().context.profile.as_deref(),
+                update,
+            )
+        };
+
+        let archivable = update_with(|update| {
+            update
+                .changed_properties
+                .insert("Archivable".to_owned(), Some(Variant::Bool(false)));
+        });
+
+        // Nodes only defined by the base project are changed there.
+        write("Shared", &archivable).unwrap();
+        let base_path = Path::new("/root/base.project.json");
+        let base = Project::load_from_slice(&vfs.read(base_path).unwrap(), base_path).unwrap();
+        assert_eq!(
+            base.tree.children["ReplicatedStorage"].children["Shared"]
+                .properties
+                .get("Archivable"),
+            Some(&UnresolvedValue::FullyQualified(Variant::Bool(false)))
+        );
+
+        // Nodes defined by both projects are changed in the one that wins.
+        write("Settings", &archivable).unwrap();
+        let project_path = Path::new("/root/default.project.json");
+        let project =
+            Project::load_from_slice(&vfs.read(project_path).unwrap(), project_path).unwrap();
+        assert_eq!(
+            project.tree.children["ReplicatedStorage"].children["Settings"]
+                .properties
+                .get("Archivable"),
+            Some(&UnresolvedValue::FullyQualified(Variant::Bool(false)))
+        );
+
+        let rename = update_with(|update| update.changed_name = Some("Config".to_owned()));
+        let err = write("Settings", &rename).unwrap_err();
+        assert!(err
+            .to_string()
+            .starts_with("Cannot rename project node ReplicatedStorage/Settings"));
+
+        let err = write("Debug", &archivable).unwrap_err();
+        assert!(err
+            .to_string()
+            .starts_with("Cannot change project node ReplicatedStorage/Debug"));
+    }
 }
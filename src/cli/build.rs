@@ -1,6 +1,7 @@
 use std::{
     io::{BufWriter, Write},
     path::{Path, PathBuf},
+    time::Duration,
 };
 
 use anyhow::Context;
@@ -9,13 +10,18 @@ use memofs::Vfs;
 use structopt::StructOpt;
 use tokio::runtime::Runtime;
 
-use crate::serve_session::ServeSession;
+use crate::{serve_session::ServeSession, snapshot::AppliedPatchSet};
 
 use super::resolve_path;
 
 const UNKNOWN_OUTPUT_KIND_ERR: &str = "Could not detect what kind of file to build. \
                                        Expected output file to end in .rbxl, .rbxlx, .rbxm, or .rbxmx.";
 
+/// How long to wait for more changes before rebuilding in watch mode. Saving
+/// several files at once, or a tool rewriting a file in a few steps, produces a
+/// burst of changes that should only cause one rebuild.
+const DEBOUNCE_WINDOW: Duration = Duration::from_millis(200);
+
 /// Generates a model or place file from the Rojo project.
 #[derive(Debug, StructOpt)]
 pub struct BuildCommand {
@@ -32,6 +38,10 @@ pub struct BuildCommand {
     /// Whether to automatically rebuild when any input files change.
     #[structopt(long)]
     pub watch: bool,
+
+    /// The project profile to build with, from the project's `profiles`.
+    #[structopt(long)]
+    pub profile: Option<String>,
 }
 
 impl BuildCommand {
@@ -44,21 +54,13 @@ impl BuildCommand {
         let vfs = Vfs::new_default();
         vfs.set_watch_enabled(self.watch);
 
-        let session = ServeSession::new(vfs, &project_path)?;
-        let mut cursor = session.message_queue().cursor();
+        let session = ServeSession::with_profile(vfs, &project_path, self.profile.as_deref())?;
+        let cursor = session.message_queue().cursor();
 
         write_model(&session, &self.output, output_kind)?;
 
         if self.watch {
-            let rt = Runtime::new().unwrap();
-
-            loop {
-                let receiver = session.message_queue().subscribe(cursor);
-                let (new_cursor, _patch_set) = rt.block_on(receiver).unwrap();
-                cursor = new_cursor;
-
-                write_model(&session, &self.output, output_kind)?;
-            }
+            watch(&session, cursor, &self.output, output_kind);
         }
 
         Ok(())
@@ -67,7 +69,7 @@ impl BuildCommand {
 
 /// The different kinds of output that Rojo can build to.
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
-enum OutputKind {
+pub(super) enum OutputKind {
     /// An XML model file.
     Rbxmx,
 
@@ -93,17 +95,129 @@ fn detect_output_kind(output: &Path) -> Option<OutputKind> {
     }
 }
 
+/// Describes how many instances were added, removed, and updated by the given
+/// patches, or returns `None` if they didn't change anything.
+fn describe_changes(patch_sets: &[AppliedPatchSet]) -> Option<String> {
+    let mut added = 0;
+    let mut removed = 0;
+    let mut updated = 0;
+
+    for patch_set in patch_sets {
+        added += patch_set.added.len();
+        removed += patch_set.removed.len();
+        updated += patch_set.updated.len();
+    }
+
+    if added + removed + updated == 0 {
+        return None;
+    }
+
+    Some(format!(
+        "{} added, {} removed, {} updated",
+        added, removed, updated
+    ))
+}
+
 fn xml_encode_config() -> rbx_xml::EncodeOptions {
     rbx_xml::EncodeOptions::new().property_behavior(rbx_xml::EncodePropertyBehavior::WriteUnknown)
 }
 
-fn write_model(
+/// Rebuilds the project every time its files change after the given message
+/// queue cursor. Never returns.
+pub(super) fn watch(
+    session: &ServeSession,
+    mut cursor: u32,
+    output: &Path,
+    output_kind: OutputKind,
+) {
+    let rt = Runtime::new().unwrap();
+
+    loop {
+        let receiver = session.message_queue().subscribe(cursor);
+        let mut patch_sets = match rt.block_on(receiver).unwrap() {
+            Ok((new_cursor, patch_sets)) => {
+                cursor = new_cursor;
+                patch_sets
+            }
+            Err(_) => {
+                // So many changes happened while we were rebuilding that some
+                // were dropped before we saw them. We can't summarize them,
+                // but we can still rebuild.
+                cursor = session.message_queue().cursor();
+
+                println!("Detected changes");
+                rebuild(session, output, output_kind);
+                continue;
+            }
+        };
+
+        // Keep collecting changes until none have arrived for a whole window.
+        loop {
+            let receiver = session.message_queue().subscribe(cursor);
+
+            let next = rt.block_on(async { tokio::time::timeout(DEBOUNCE_WINDOW, receiver).await });
+
+            match next {
+                Ok(Ok(Ok((new_cursor, more_patch_sets)))) => {
+                    cursor = new_cursor;
+                    patch_sets.extend(more_patch_sets);
+                }
+                _ => break,
+            }
+        }
+
+        let summary = match describe_changes(&patch_sets) {
+            Some(summary) => summary,
+            None => continue,
+        };
+
+        println!("Detected changes: {}", summary);
+        rebuild(session, output, output_kind);
+    }
+}
+
+/// Rebuilds the project while watching. Errors while rebuilding shouldn't stop
+/// us from watching, since the next change will probably fix them.
+fn rebuild(session: &ServeSession, output: &Path, output_kind: OutputKind) {
+    if let Err(err) = write_model(session, output, output_kind) {
+        log::error!("Could not build project: {:?}", err);
+    }
+}
+
+pub(super) fn write_model(
     session: &ServeSession,
     output: &Path,
     output_kind: OutputKind,
 ) -> anyhow::Result<()> {
     println!("Building project '{}'", session.project_name());
 
+    // The model is written to a temporary file first and then moved into
+    // place, so that nothing watching the output ever sees half of a file.
+    let file_name = output
+        .file_name()
+        .and_then(|name| name.to_str())
+        .unwrap_or("<invalid utf-8>");
+    let temp_path = output.with_file_name(format!(".{}.tmp", file_name));
+
+    let result = write_model_to(session, &temp_path, output_kind)
+        .and_then(|_| fs_err::rename(&temp_path, output).map_err(Into::into));
+
+    if result.is_err() {
+        let _ = fs_err::remove_file(&temp_path);
+    }
+
+    result?;
+
+    println!("Built project to {}", file_name);
+
+    Ok(())
+}
+
+fn write_model_to(
+    session: &ServeSession,
+    output: &Path,
+    output_kind: OutputKind,
+) -> anyhow::Result<()> {
     let tree = session.tree();
     let root_id = tree.get_root_id();
 
@@ -139,11 +253,36 @@ fn write_model(
 
     file.flush()?;
 
-    let filename = output
-        .file_name()
-        .and_then(|name| name.to_str())
-        .unwrap_or("<invalid utf-8>");
-    println!("Built project to {}", filename);
-
     Ok(())
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use rbx_dom_weak::types::Ref;
+
+    use crate::snapshot::AppliedPatchUpdate;
+
+    #[test]
+    fn describe_empty_changes() {
+        assert_eq!(describe_changes(&[]), None);
+        assert_eq!(describe_changes(&[AppliedPatchSet::new()]), None);
+    }
+
+    #[test]
+    fn describe_coalesced_changes() {
+        let mut first = AppliedPatchSet::new();
+        first.added.push(Ref::new());
+        first.updated.push(AppliedPatchUpdate::new(Ref::new()));
+
+        let mut second = AppliedPatchSet::new();
+        second.removed.push(Ref::new());
+        second.updated.push(AppliedPatchUpdate::new(Ref::new()));
+
+        assert_eq!(
+            describe_changes(&[first, second]).as_deref(),
+            Some("1 added, 1 removed, 2 updated")
+        );
+    }
+}
@@ -0,0 +1,551 @@
+use std::{
+    collections::BTreeMap,
+    io::{self, Write},
+    path::PathBuf,
+    process,
+    str::FromStr,
+};
+
+use anyhow::{format_err, Context};
+use memofs::Vfs;
+use rbx_dom_weak::{
+    types::{Color3uint8, Ref, Variant},
+    WeakDom,
+};
+use rbx_reflection::{PropertyDescriptor, PropertyKind, PropertySerialization};
+use serde::Serialize;
+use structopt::StructOpt;
+
+use crate::{
+    resolution::find_descriptor,
+    serve_session::ServeSession,
+    snapshot::{compute_patch_set, InstanceSnapshot, RojoTree},
+    syncback::remove_default_properties,
+};
+
+use super::{
+    resolve_path,
+    syncback::{detect_input_kind, read_dom, InputKind},
+};
+
+const UNKNOWN_INPUT_KIND_ERR: &str = "Could not detect what kind of file to compare against. \
+                                      Expected file to end in .rbxl, .rbxlx, .rbxm, or .rbxmx.";
+
+/// The exit status used when the project and file are different. Errors exit
+/// with status 1, so this needs to be something else for CI to tell them
+/// apart.
+const DIFFERENCES_EXIT_CODE: i32 = 2;
+
+/// Shows what would change if a place or model file were replaced with the
+/// built project.
+///
+/// Exits with status 2 if there are any differences, and 1 if something went
+/// wrong.
+#[derive(Debug, StructOpt)]
+pub struct DiffCommand {
+    /// Path to the project to compare. Defaults to the current directory.
+    #[structopt(default_value = "")]
+    pub project: PathBuf,
+
+    /// The place or model file to compare the project against.
+    ///
+    /// Should end in .rbxl, .rbxlx, .rbxm, or .rbxmx.
+    #[structopt(long, short)]
+    pub file: PathBuf,
+
+    /// How to print the differences. Valid values are text, json, and none.
+    /// With none, only the exit status tells whether anything is different:
+    /// 0 if nothing is, 2 if something is, and 1 if there was an error.
+    #[structopt(long, default_value = "text")]
+    pub format: DiffFormat,
+}
+
+impl DiffCommand {
+    pub fn run(self) -> anyhow::Result<()> {
+        let project_path = resolve_path(&self.project);
+        let file_path = resolve_path(&self.file);
+
+        let kind = detect_input_kind(&file_path).context(UNKNOWN_INPUT_KIND_ERR)?;
+        let file_dom = read_dom(&file_path)?;
+
+        let vfs = Vfs::new_default();
+        vfs.set_watch_enabled(false);
+
+        let session = ServeSession::new(vfs, &project_path)?;
+        let diff = compute_diff(session.tree().inner(), &file_dom, kind);
+
+        let stdout = io::stdout();
+        let mut output = stdout.lock();
+
+        match self.format {
+            DiffFormat::Text => write_text(&mut output, &diff)?,
+            DiffFormat::Json => {
+                serde_json::to_writer_pretty(&mut output, &diff)?;
+                writeln!(output)?;
+            }
+            DiffFormat::None => {}
+        }
+
+        output.flush()?;
+
+        if !diff.is_empty() {
+            process::exit(DIFFERENCES_EXIT_CODE);
+        }
+
+        Ok(())
+    }
+}
+
+/// The ways that `rojo diff` can print differences.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DiffFormat {
+    /// A human-readable list of changes.
+    Text,
+
+    /// A JSON document, for other tools to consume.
+    Json,
+
+    /// Nothing at all. Only the exit status is meaningful.
+    None,
+}
+
+impl FromStr for DiffFormat {
+    type Err = anyhow::Error;
+
+    fn from_str(source: &str) -> Result<Self, Self::Err> {
+        match source {
+            "text" => Ok(DiffFormat::Text),
+            "json" => Ok(DiffFormat::Json),
+            "none" => Ok(DiffFormat::None),
+            attempted => Err(format_err!(
+                "Invalid diff format '{}'. Valid formats are: text, json, none",
+                attempted
+            )),
+        }
+    }
+}
+
+/// Everything that would change if the file were replaced with the project.
+#[derive(Debug, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct Diff {
+    added: Vec<InstanceEntry>,
+    removed: Vec<InstanceEntry>,
+    changed: Vec<ChangedInstance>,
+}
+
+impl Diff {
+    fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty() && self.changed.is_empty()
+    }
+}
+
+/// An instance that was added or removed, along with all of its descendants.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct InstanceEntry {
+    path: String,
+    class_name: String,
+    descendants: usize,
+}
+
+/// An instance that exists in both trees, but has different properties.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct ChangedInstance {
+    path: String,
+    class_name: String,
+    properties: BTreeMap<String, PropertyChange>,
+}
+
+/// The value of a property in the file and in the project. A value of `None`
+/// means that the property isn't set, so it has its default value.
+#[derive(Debug, Serialize)]
+struct PropertyChange {
+    old: Option<PropertyValue>,
+    new: Option<PropertyValue>,
+}
+
+/// A property value in a diff. Refs are shown as the path of the instance they
+/// point to, since referents are different in every tree.
+#[derive(Debug, PartialEq, Serialize)]
+#[serde(untagged)]
+enum PropertyValue {
+    Ref {
+        #[serde(rename = "Ref")]
+        target: Option<String>,
+    },
+    Value(Variant),
+}
+
+impl PropertyValue {
+    fn new(value: &Variant, find_target: impl Fn(Ref) -> Option<String>) -> Self {
+        match value {
+            Variant::Ref(id) => PropertyValue::Ref {
+                target: find_target(*id),
+            },
+            other => PropertyValue::Value(other.clone()),
+        }
+    }
+}
+
+/// Compares the tree built from a project with the tree from a place or model
+/// file. Instances are matched by their name and ClassName, the same way that
+/// live sync matches them.
+fn compute_diff(project_dom: &WeakDom, file_dom: &WeakDom, kind: InputKind) -> Diff {
+    let project_root = InstanceSnapshot::from_tree(project_dom, project_dom.root_ref());
+    let mut old = InstanceSnapshot::from_tree(file_dom, file_dom.root_ref());
+
+    // Places contain the children of the project's root instance, while
+    // models contain the root instance itself. This matches `rojo build`.
+    let mut new = match kind {
+        InputKind::Place => project_root,
+        InputKind::Model => InstanceSnapshot::new().children(vec![project_root]),
+    };
+    new.name = old.name.clone();
+    new.class_name = old.class_name.clone();
+
+    // Properties with default values can be written or left out of a file
+    // without changing anything, so we leave them out of both sides.
+    remove_default_properties(&mut old);
+    remove_default_properties(&mut new);
+
+    // Files can use the serialized names of properties, like
+    // AttributesSerialize instead of Attributes.
+    canonicalize_property_names(&mut old);
+
+    let old_tree = RojoTree::new(old);
+    let root_id = old_tree.get_root_id();
+    let patch_set = compute_patch_set(&new, &old_tree, root_id);
+
+    let mut diff = Diff::default();
+
+    for patch_add in patch_set.added_instances {
+        let parent_path = dom_path(old_tree.inner(), patch_add.parent_id, false).unwrap();
+
+        diff.added.push(InstanceEntry {
+            path: join_path(&parent_path, &patch_add.instance.name),
+            class_name: patch_add.instance.class_name.to_string(),
+            descendants: count_snapshot_descendants(&patch_add.instance),
+        });
+    }
+
+    for id in patch_set.removed_instances {
+        let instance = old_tree.get_instance(id).unwrap();
+
+        diff.removed.push(InstanceEntry {
+            path: dom_path(old_tree.inner(), id, false).unwrap(),
+            class_name: instance.class_name().to_owned(),
+            descendants: count_descendants(old_tree.inner(), id),
+        });
+    }
+
+    // The trees don't share referents, so Refs are compared by the paths of
+    // the instances they point to. Refs to instances that exist in both trees
+    // have already been rewritten to point into the old tree.
+    let old_target = |id| dom_path(file_dom, id, false);
+    let new_target = |id| match old_tree.get_instance(id) {
+        Some(_) => dom_path(old_tree.inner(), id, false),
+        None => dom_path(project_dom, id, kind == InputKind::Model),
+    };
+
+    for update in patch_set.updated_instances {
+        let instance = old_tree.get_instance(update.id).unwrap();
+
+        let properties: BTreeMap<_, _> = update
+            .changed_properties
+            .into_iter()
+            .map(|(key, new)| {
+                let old = instance
+                    .properties()
+                    .get(&key)
+                    .map(|value| PropertyValue::new(value, old_target));
+                let new = new.map(|value| PropertyValue::new(&value, new_target));
+
+                (key, PropertyChange { old, new })
+            })
+            .filter(|(_key, change)| !values_equivalent(&change.old, &change.new))
+            .collect();
+
+        if properties.is_empty() {
+            continue;
+        }
+
+        diff.changed.push(ChangedInstance {
+            path: dom_path(old_tree.inner(), update.id, false).unwrap(),
+            class_name: instance.class_name().to_owned(),
+            properties,
+        });
+    }
+
+    diff.added.sort_by(|a, b| a.path.cmp(&b.path));
+    diff.removed.sort_by(|a, b| a.path.cmp(&b.path));
+    diff.changed.sort_by(|a, b| a.path.cmp(&b.path));
+
+    diff
+}
+
+/// Tells whether two property values would behave the same in Roblox, even if
+/// they aren't stored the same way.
+fn values_equivalent(old: &Option<PropertyValue>, new: &Option<PropertyValue>) -> bool {
+    match (old, new) {
+        // Colors are often stored in files as Color3uint8 even when they're
+        // written as Color3 in a project.
+        (
+            Some(PropertyValue::Value(Variant::Color3uint8(a))),
+            Some(PropertyValue::Value(Variant::Color3(b))),
+        )
+        | (
+            Some(PropertyValue::Value(Variant::Color3(b))),
+            Some(PropertyValue::Value(Variant::Color3uint8(a))),
+        ) => *a == Color3uint8::from(*b),
+        _ => old == new,
+    }
+}
+
+fn canonicalize_property_names(snapshot: &mut InstanceSnapshot) {
+    let class_name = snapshot.class_name.clone();
+
+    snapshot.properties = snapshot
+        .properties
+        .drain()
+        .map(
+            |(key, value)| match canonical_property_name(&class_name, &key) {
+                Some(canonical) => (canonical, value),
+                None => (key, value),
+            },
+        )
+        .collect();
+
+    for child in &mut snapshot.children {
+        canonicalize_property_names(child);
+    }
+}
+
+/// Finds the canonical name of a property if the given name is an alias or
+/// the name it serializes as.
+fn canonical_property_name(class_name: &str, prop_name: &str) -> Option<String> {
+    if let Some(PropertyDescriptor {
+        kind: PropertyKind::Alias { alias_for },
+        ..
+    }) = find_descriptor(class_name, prop_name)
+    {
+        return Some(alias_for.to_string());
+    }
+
+    let database = rbx_reflection_database::get();
+    let mut current_class_name = class_name;
+
+    loop {
+        let class = database.classes.get(current_class_name)?;
+
+        for (name, descriptor) in &class.properties {
+            if let PropertyKind::Canonical {
+                serialization: PropertySerialization::SerializesAs(serialized_name),
+            } = &descriptor.kind
+            {
+                if serialized_name == prop_name {
+                    return Some(name.to_string());
+                }
+            }
+        }
+
+        current_class_name = class.superclass.as_deref()?;
+    }
+}
+
+/// Describes where an instance is by the names of its ancestors. The root of
+/// the tree is only included if `include_root` is set.
+pub(super) fn dom_path(dom: &WeakDom, id: Ref, include_root: bool) -> Option<String> {
+    let root_id = dom.root_ref();
+    let mut names = Vec::new();
+    let mut current = dom.get_by_ref(id)?;
+
+    loop {
+        if current.referent() == root_id {
+            if include_root {
+                names.push(current.name.as_str());
+            }
+            break;
+        }
+
+        names.push(current.name.as_str());
+        current = dom.get_by_ref(current.parent())?;
+    }
+
+    names.reverse();
+    Some(names.join("/"))
+}
+
+fn join_path(parent: &str, name: &str) -> String {
+    if parent.is_empty() {
+        name.to_owned()
+    } else {
+        format!("{}/{}", parent, name)
+    }
+}
+
+fn count_descendants(dom: &WeakDom, id: Ref) -> usize {
+    let instance = dom.get_by_ref(id).unwrap();
+
+    instance
+        .children()
+        .iter()
+        .map(|&child| 1 + count_descendants(dom, child))
+        .sum()
+}
+
+fn count_snapshot_descendants(snapshot: &InstanceSnapshot) -> usize {
+    snapshot
+        .children
+        .iter()
+        .map(|child| 1 + count_snapshot_descendants(child))
+        .sum()
+}
+
+fn write_text<W: Write>(output: &mut W, diff: &Diff) -> io::Result<()> {
+    if diff.is_empty() {
+        return writeln!(output, "No differences found.");
+    }
+
+    for entry in &diff.added {
+        writeln!(output, "+ {}", describe_entry(entry))?;
+    }
+
+    for entry in &diff.removed {
+        writeln!(output, "- {}", describe_entry(entry))?;
+    }
+
+    for changed in &diff.changed {
+        writeln!(output, "~ {} ({})", changed.path, changed.class_name)?;
+
+        for (key, change) in &changed.properties {
+            writeln!(
+                output,
+                "    {}: {} -> {}",
+                key,
+                display_value(change.old.as_ref()),
+                display_value(change.new.as_ref())
+            )?;
+        }
+    }
+
+    writeln!(
+        output,
+        "{} added, {} removed, {} changed",
+        diff.added.len(),
+        diff.removed.len(),
+        diff.changed.len()
+    )
+}
+
+fn describe_entry(entry: &InstanceEntry) -> String {
+    match entry.descendants {
+        0 => format!("{} ({})", entry.path, entry.class_name),
+        1 => format!("{} ({}, 1 descendant)", entry.path, entry.class_name),
+        count => format!(
+            "{} ({}, {} descendants)",
+            entry.path, entry.class_name, count
+        ),
+    }
+}
+
+fn display_value(value: Option<&PropertyValue>) -> String {
+    let value = match value {
+        None => return "(default)".to_owned(),
+        Some(PropertyValue::Ref { target: None }) => return "nil".to_owned(),
+        Some(PropertyValue::Ref {
+            target: Some(target),
+        }) => return format!("-> {}", target),
+        Some(PropertyValue::Value(value)) => Some(value),
+    };
+
+    match value {
+        None => "(default)".to_owned(),
+
+        // Sources are usually far too long to print, so we only summarize
+        // long or multi-line strings.
+        Some(Variant::String(value)) if value.contains('\n') || value.len() > 60 => {
+            format!("<{} lines>", value.lines().count())
+        }
+        Some(Variant::String(value)) => format!("{:?}", value),
+        Some(Variant::Bool(value)) => value.to_string(),
+        Some(Variant::Float32(value)) => value.to_string(),
+        Some(Variant::Float64(value)) => value.to_string(),
+        Some(Variant::Int32(value)) => value.to_string(),
+        Some(Variant::Int64(value)) => value.to_string(),
+        Some(value) => format!("{:?}", value),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use rbx_dom_weak::InstanceBuilder;
+
+    #[test]
+    fn place_changes() {
+        let project = WeakDom::new(
+            InstanceBuilder::new("DataModel")
+                .with_name("my-game")
+                .with_child(
+                    InstanceBuilder::new("ReplicatedStorage")
+                        .with_child(
+                            InstanceBuilder::new("ModuleScript")
+                                .with_name("Util")
+                                .with_property("Source", "return 2"),
+                        )
+                        .with_child(InstanceBuilder::new("Folder").with_name("New")),
+                )
+                .with_child(InstanceBuilder::new("Lighting").with_property("Brightness", 5.0f32)),
+        );
+
+        let file = WeakDom::new(
+            InstanceBuilder::new("DataModel")
+                .with_child(
+                    InstanceBuilder::new("ReplicatedStorage")
+                        .with_child(
+                            InstanceBuilder::new("ModuleScript")
+                                .with_name("Util")
+                                .with_property("Source", "return 1"),
+                        )
+                        .with_child(
+                            InstanceBuilder::new("Folder")
+                                .with_name("Old")
+                                .with_child(InstanceBuilder::new("Folder")),
+                        ),
+                )
+                .with_child(InstanceBuilder::new("Lighting").with_property("Brightness", 5.0f32)),
+        );
+
+        let diff = compute_diff(&project, &file, InputKind::Place);
+
+        let mut text = Vec::new();
+        write_text(&mut text, &diff).unwrap();
+
+        assert_eq!(
+            String::from_utf8(text).unwrap(),
+            "+ ReplicatedStorage/New (Folder)\n\
+             - ReplicatedStorage/Old (Folder, 1 descendant)\n\
+             ~ ReplicatedStorage/Util (ModuleScript)\n    \
+             Source: \"return 1\" -> \"return 2\"\n\
+             1 added, 1 removed, 1 changed\n"
+        );
+    }
+
+    #[test]
+    fn identical_model() {
+        let model = || {
+            InstanceBuilder::new("Model")
+                .with_name("Tree")
+                .with_child(InstanceBuilder::new("Part").with_name("Trunk"))
+        };
+
+        let project = WeakDom::new(model());
+        let file = WeakDom::new(InstanceBuilder::new("DataModel").with_child(model()));
+
+        let diff = compute_diff(&project, &file, InputKind::Model);
+        assert!(diff.is_empty());
+    }
+}
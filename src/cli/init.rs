@@ -21,6 +21,12 @@ static PLACE_PROJECT: &str =
 static PLACE_README: &str = include_str!("../../assets/default-place-project/README.md");
 static PLACE_GIT_IGNORE: &str = include_str!("../../assets/default-place-project/gitignore.txt");
 
+static PLUGIN_PROJECT: &str =
+    include_str!("../../assets/default-plugin-project/default.project.json");
+static PLUGIN_README: &str = include_str!("../../assets/default-plugin-project/README.md");
+static PLUGIN_INIT: &str = include_str!("../../assets/default-plugin-project/src-init.server.lua");
+static PLUGIN_GIT_IGNORE: &str = include_str!("../../assets/default-plugin-project/gitignore.txt");
+
 /// Initializes a new Rojo project.
 #[derive(Debug, StructOpt)]
 pub struct InitCommand {
@@ -28,9 +34,16 @@ pub struct InitCommand {
     #[structopt(default_value = "")]
     pub path: PathBuf,
 
-    /// The kind of project to create, 'place' or 'model'. Defaults to place.
-    #[structopt(long, default_value = "place")]
-    pub kind: InitKind,
+    /// The kind of project to create, 'place', 'model', or 'plugin'. Defaults
+    /// to place.
+    #[structopt(long)]
+    pub kind: Option<InitKind>,
+
+    /// A directory to copy the new project from instead of one of the built-in
+    /// kinds. Variables like {{project_name}} in file names and contents are
+    /// replaced.
+    #[structopt(long, conflicts_with = "kind")]
+    pub template: Option<PathBuf>,
 }
 
 impl InitCommand {
@@ -48,9 +61,15 @@ impl InitCommand {
             name: project_name.to_owned(),
         };
 
-        match self.kind {
-            InitKind::Place => init_place(&base_path, project_params)?,
-            InitKind::Model => init_model(&base_path, project_params)?,
+        if let Some(template) = &self.template {
+            let template_path = resolve_path(template);
+            init_from_template(&base_path, &template_path, project_params)?;
+        } else {
+            match self.kind.unwrap_or(InitKind::Place) {
+                InitKind::Place => init_place(&base_path, project_params)?,
+                InitKind::Model => init_model(&base_path, project_params)?,
+                InitKind::Plugin => init_plugin(&base_path, project_params)?,
+            }
         }
 
         println!("Created project successfully.");
@@ -65,8 +84,11 @@ pub enum InitKind {
     /// A place that contains a baseplate.
     Place,
 
-    /// An empty model, suitable for a library or plugin.
+    /// An empty model, suitable for a library.
     Model,
+
+    /// A Studio plugin, whose root is a Script that runs with plugin security.
+    Plugin,
 }
 
 impl FromStr for InitKind {
@@ -76,8 +98,9 @@ impl FromStr for InitKind {
         match source {
             "place" => Ok(InitKind::Place),
             "model" => Ok(InitKind::Model),
+            "plugin" => Ok(InitKind::Plugin),
             _ => Err(format_err!(
-                "Invalid init kind '{}'. Valid kinds are: place, model",
+                "Invalid init kind '{}'. Valid kinds are: place, model, plugin",
                 source
             )),
         }
@@ -147,6 +170,113 @@ fn init_model(base_path: &Path, project_params: ProjectParams) -> anyhow::Result
     Ok(())
 }
 
+fn init_plugin(base_path: &Path, project_params: ProjectParams) -> anyhow::Result<()> {
+    println!("Creating new plugin project '{}'", project_params.name);
+
+    let project_file = project_params.render_template(PLUGIN_PROJECT);
+    try_create_project(base_path, &project_file)?;
+
+    let readme = project_params.render_template(PLUGIN_README);
+    write_if_not_exists(&base_path.join("README.md"), &readme)?;
+
+    let src = base_path.join("src");
+    fs::create_dir_all(&src)?;
+
+    let init = project_params.render_template(PLUGIN_INIT);
+    write_if_not_exists(&src.join("init.server.lua"), &init)?;
+
+    let git_ignore = project_params.render_template(PLUGIN_GIT_IGNORE);
+    try_git_init(base_path, &git_ignore)?;
+
+    Ok(())
+}
+
+/// Copies a template directory into a new project, rendering every file name
+/// and every text file along the way.
+fn init_from_template(
+    base_path: &Path,
+    template_path: &Path,
+    project_params: ProjectParams,
+) -> anyhow::Result<()> {
+    println!(
+        "Creating new project '{}' from template {}",
+        project_params.name,
+        template_path.display()
+    );
+
+    if !template_path.is_dir() {
+        bail!("Template is not a directory: {}", template_path.display());
+    }
+
+    let files = template_files(template_path)?;
+
+    // Check for existing project files before writing anything so that we
+    // don't leave a half-created project behind.
+    for relative_path in &files {
+        let output_path = base_path.join(project_params.render_path(relative_path));
+
+        if is_project_file(&output_path) && output_path.exists() {
+            bail!("Project file already exists: {}", output_path.display());
+        }
+    }
+
+    for relative_path in &files {
+        let output_path = base_path.join(project_params.render_path(relative_path));
+
+        if let Some(parent) = output_path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+
+        let contents = fs::read(template_path.join(relative_path))?;
+
+        // Binary files, like images or models, are copied without changes.
+        match String::from_utf8(contents) {
+            Ok(text) => write_if_not_exists(&output_path, project_params.render_template(&text))?,
+            Err(err) => write_if_not_exists(&output_path, err.into_bytes())?,
+        }
+    }
+
+    git_init_if_needed(base_path)?;
+
+    Ok(())
+}
+
+/// Lists every file in a template directory relative to it, in a stable order.
+/// The template's own Git repository, if it has one, is skipped.
+fn template_files(template_path: &Path) -> anyhow::Result<Vec<PathBuf>> {
+    let mut files = Vec::new();
+    let mut to_visit = vec![PathBuf::new()];
+
+    while let Some(relative_dir) = to_visit.pop() {
+        for entry in fs::read_dir(template_path.join(&relative_dir))? {
+            let entry = entry?;
+
+            if entry.file_name() == ".git" {
+                continue;
+            }
+
+            let relative_path = relative_dir.join(entry.file_name());
+
+            if entry.file_type()?.is_dir() {
+                to_visit.push(relative_path);
+            } else {
+                files.push(relative_path);
+            }
+        }
+    }
+
+    files.sort();
+
+    Ok(files)
+}
+
+fn is_project_file(path: &Path) -> bool {
+    path.file_name()
+        .and_then(|name| name.to_str())
+        .map(|name| name.ends_with(".project.json"))
+        .unwrap_or(false)
+}
+
 /// Contains parameters used in templates to create a project.
 struct ProjectParams {
     name: String,
@@ -154,15 +284,42 @@ struct ProjectParams {
 
 impl ProjectParams {
     /// Render a template by replacing variables with project parameters.
+    /// Variables can be written as either {{name}} or {name}.
     fn render_template(&self, template: &str) -> String {
-        template
-            .replace("{project_name}", &self.name)
-            .replace("{rojo_version}", env!("CARGO_PKG_VERSION"))
+        let variables = [
+            ("project_name", self.name.as_str()),
+            ("rojo_version", env!("CARGO_PKG_VERSION")),
+        ];
+
+        let mut rendered = template.to_owned();
+
+        for (name, value) in &variables {
+            rendered = rendered
+                .replace(&format!("{{{{{}}}}}", name), value)
+                .replace(&format!("{{{}}}", name), value);
+        }
+
+        rendered
+    }
+
+    /// Render each component of a path in a template directory.
+    fn render_path(&self, path: &Path) -> PathBuf {
+        path.iter()
+            .map(|component| self.render_template(&component.to_string_lossy()))
+            .collect()
     }
 }
 
 /// Attempt to initialize a Git repository if necessary, and create .gitignore.
 fn try_git_init(path: &Path, git_ignore: &str) -> Result<(), anyhow::Error> {
+    git_init_if_needed(path)?;
+    write_if_not_exists(&path.join(".gitignore"), git_ignore)?;
+
+    Ok(())
+}
+
+/// Initialize a Git repository in the given path if it isn't in one already.
+fn git_init_if_needed(path: &Path) -> Result<(), anyhow::Error> {
     if should_git_init(path) {
         log::debug!("Initializing Git repository...");
 
@@ -173,8 +330,6 @@ fn try_git_init(path: &Path, git_ignore: &str) -> Result<(), anyhow::Error> {
         }
     }
 
-    write_if_not_exists(&path.join(".gitignore"), git_ignore)?;
-
     Ok(())
 }
 
@@ -201,7 +356,7 @@ fn should_git_init(path: &Path) -> bool {
 }
 
 /// Write a file if it does not exist yet, otherwise, leave it alone.
-fn write_if_not_exists(path: &Path, contents: &str) -> Result<(), anyhow::Error> {
+fn write_if_not_exists(path: &Path, contents: impl AsRef<[u8]>) -> Result<(), anyhow::Error> {
     let file_res = OpenOptions::new().write(true).create_new(true).open(path);
 
     let mut file = match file_res {
@@ -214,7 +369,7 @@ fn write_if_not_exists(path: &Path, contents: &str) -> Result<(), anyhow::Error>
         }
     };
 
-    file.write_all(contents.as_bytes())?;
+    file.write_all(contents.as_ref())?;
 
     Ok(())
 }
@@ -244,3 +399,88 @@ fn try_create_project(base_path: &Path, contents: &str) -> Result<(), anyhow::Er
 
     Ok(())
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use memofs::Vfs;
+
+    use crate::serve_session::ServeSession;
+
+    fn params() -> ProjectParams {
+        ProjectParams {
+            name: "my-project".to_owned(),
+        }
+    }
+
+    #[test]
+    fn render_variables() {
+        let params = params();
+
+        assert_eq!(
+            params.render_template("{{project_name}} and {project_name}"),
+            "my-project and my-project"
+        );
+        assert_eq!(
+            params.render_template("{{rojo_version}}"),
+            env!("CARGO_PKG_VERSION")
+        );
+        assert_eq!(params.render_template("{{unknown}}"), "{{unknown}}");
+        assert_eq!(
+            params.render_path(Path::new("src/{{project_name}}.lua")),
+            Path::new("src/my-project.lua")
+        );
+    }
+
+    #[test]
+    fn plugin_root_is_script() {
+        let dir = tempfile::tempdir().unwrap();
+        init_plugin(dir.path(), params()).unwrap();
+
+        let session = ServeSession::new(Vfs::new_default(), dir.path()).unwrap();
+        let tree = session.tree();
+        let root = tree.get_instance(tree.get_root_id()).unwrap();
+
+        assert_eq!(root.class_name(), "Script");
+    }
+
+    #[test]
+    fn from_template() {
+        let template = tempfile::tempdir().unwrap();
+        let template_path = template.path();
+
+        fs::write(
+            template_path.join("default.project.json"),
+            r#"{"name": "{{project_name}}", "tree": {"$path": "src"}}"#,
+        )
+        .unwrap();
+        fs::create_dir(template_path.join("src")).unwrap();
+        fs::write(
+            template_path.join("src").join("{{project_name}}.lua"),
+            "return \"{{project_name}}\"",
+        )
+        .unwrap();
+        fs::write(template_path.join("icon.png"), [0x89, 0x50, 0xff, 0xfe]).unwrap();
+
+        let dir = tempfile::tempdir().unwrap();
+        let base_path = dir.path();
+        init_from_template(base_path, template_path, params()).unwrap();
+
+        assert_eq!(
+            fs::read_to_string(base_path.join("default.project.json")).unwrap(),
+            r#"{"name": "my-project", "tree": {"$path": "src"}}"#
+        );
+        assert_eq!(
+            fs::read_to_string(base_path.join("src").join("my-project.lua")).unwrap(),
+            "return \"my-project\""
+        );
+        assert_eq!(
+            fs::read(base_path.join("icon.png")).unwrap(),
+            [0x89, 0x50, 0xff, 0xfe]
+        );
+
+        let err = init_from_template(base_path, template_path, params()).unwrap_err();
+        assert!(err.to_string().starts_with("Project file already exists"));
+    }
+}
@@ -0,0 +1,589 @@
+use std::{
+    env,
+    io::{self, Write},
+    path::{Path, PathBuf},
+    process,
+    str::FromStr,
+};
+
+use anyhow::{bail, format_err, Context};
+use fs_err as fs;
+use rbx_dom_weak::{
+    types::{Ref, Variant},
+    WeakDom,
+};
+use serde::Serialize;
+use structopt::StructOpt;
+
+use crate::snapshot_middleware::{
+    check_localization_csv, check_merged_localization, merge_localization_tables,
+    read_localization_contents, read_localization_csv_flexible, write_localization_csv,
+    DirectoryMetadata, LocalizationIssue,
+};
+
+use super::{
+    diff::dom_path,
+    resolve_path,
+    syncback::{detect_input_kind, read_dom},
+};
+
+/// The exit status used when `rojo localization check` finds problems. Errors
+/// exit with status 1, so this needs to be something else for CI to tell them
+/// apart.
+const ISSUES_EXIT_CODE: i32 = 2;
+
+const UNKNOWN_INPUT_KIND_ERR: &str = "Could not detect what kind of file to export from. \
+                                      Expected file to end in .rbxl, .rbxlx, .rbxm, or .rbxmx.";
+
+/// Works with LocalizationTables and the CSV files they're made from.
+#[derive(Debug, StructOpt)]
+pub struct LocalizationCommand {
+    #[structopt(subcommand)]
+    subcommand: LocalizationSubcommand,
+}
+
+/// Manages localization CSV files.
+#[derive(Debug, StructOpt)]
+pub enum LocalizationSubcommand {
+    /// Writes a LocalizationTable from a place or model file to a CSV file
+    /// that Rojo can sync back into the table.
+    Export {
+        /// The place or model file to export from.
+        ///
+        /// Should end in .rbxl, .rbxlx, .rbxm, or .rbxmx.
+        input: PathBuf,
+
+        /// Which LocalizationTable to export, as its name or a path of names
+        /// separated by slashes, like `ReplicatedStorage/Strings`. Required if
+        /// the file has more than one LocalizationTable.
+        #[structopt(long)]
+        table: Option<String>,
+
+        /// Where to write the CSV file.
+        #[structopt(long, short)]
+        output: PathBuf,
+    },
+
+    /// Checks localization CSV files for duplicate keys, unknown locale
+    /// columns, missing translations, and malformed rows.
+    ///
+    /// Exits with status 2 if there are any problems, and 1 if something went
+    /// wrong.
+    Check {
+        /// CSV files, or directories to search for CSV files. Defaults to the
+        /// current directory.
+        paths: Vec<PathBuf>,
+
+        /// A locale that every entry should have a translation for, like `es`.
+        /// Can be given more than once. Defaults to every locale that each
+        /// table has a column for.
+        #[structopt(long = "locale")]
+        locales: Vec<String>,
+
+        /// How to print problems. Valid values are text and json. Either way,
+        /// the exit status is 2 if there are problems and 1 if there was an
+        /// error.
+        #[structopt(long, default_value = "text")]
+        format: CheckFormat,
+    },
+}
+
+/// The ways that `rojo localization check` can print problems.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CheckFormat {
+    /// One line for each problem, with the file and line it's on.
+    Text,
+
+    /// A JSON document, for other tools to consume.
+    Json,
+}
+
+impl FromStr for CheckFormat {
+    type Err = anyhow::Error;
+
+    fn from_str(source: &str) -> Result<Self, Self::Err> {
+        match source {
+            "text" => Ok(CheckFormat::Text),
+            "json" => Ok(CheckFormat::Json),
+            attempted => Err(format_err!(
+                "Invalid check format '{}'. Valid formats are: text, json",
+                attempted
+            )),
+        }
+    }
+}
+
+impl LocalizationCommand {
+    pub fn run(self) -> anyhow::Result<()> {
+        self.subcommand.run()
+    }
+}
+
+impl LocalizationSubcommand {
+    pub fn run(self) -> anyhow::Result<()> {
+        match self {
+            LocalizationSubcommand::Export {
+                input,
+                table,
+                output,
+            } => export(
+                &resolve_path(&input),
+                table.as_deref(),
+                &resolve_path(&output),
+            ),
+            LocalizationSubcommand::Check {
+                paths,
+                locales,
+                format,
+            } => run_check(&paths, &locales, format),
+        }
+    }
+}
+
+fn export(input: &Path, table: Option<&str>, output: &Path) -> anyhow::Result<()> {
+    detect_input_kind(input).context(UNKNOWN_INPUT_KIND_ERR)?;
+    let dom = read_dom(input)?;
+
+    let table_id = find_table(&dom, table)?;
+    let instance = dom.get_by_ref(table_id).unwrap();
+    let table_path = dom_path(&dom, table_id, false).unwrap();
+
+    let entries = match instance.properties.get("Contents") {
+        Some(Variant::String(contents)) => read_localization_contents(contents)
+            .with_context(|| format!("LocalizationTable {} has malformed contents", table_path))?,
+        Some(_) => bail!(
+            "The Contents property of LocalizationTable {} is not a string",
+            table_path
+        ),
+        None => Vec::new(),
+    };
+
+    fs::write(output, write_localization_csv(&entries)?)?;
+
+    println!(
+        "Exported {} entries from {} to {}",
+        entries.len(),
+        table_path,
+        output.display()
+    );
+
+    Ok(())
+}
+
+/// Finds the LocalizationTable to export. Without a name or path to look for,
+/// the file must have exactly one LocalizationTable.
+fn find_table(dom: &WeakDom, table: Option<&str>) -> anyhow::Result<Ref> {
+    let mut tables = Vec::new();
+    let mut to_visit = dom.root().children().to_vec();
+
+    while let Some(id) = to_visit.pop() {
+        let instance = dom.get_by_ref(id).unwrap();
+
+        if instance.class == "LocalizationTable" {
+            tables.push((id, dom_path(dom, id, false).unwrap()));
+        }
+
+        to_visit.extend_from_slice(instance.children());
+    }
+
+    tables.sort_by(|(_, a), (_, b)| a.cmp(b));
+
+    let matches: Vec<&(Ref, String)> = match table {
+        Some(table) => tables
+            .iter()
+            .filter(|(id, path)| path == table || dom.get_by_ref(*id).unwrap().name == table)
+            .collect(),
+        None => tables.iter().collect(),
+    };
+
+    match matches.as_slice() {
+        [(id, _)] => Ok(*id),
+        [] => match table {
+            Some(table) => Err(format_err!(
+                "Could not find a LocalizationTable named {}",
+                table
+            )),
+            None => Err(format_err!(
+                "The file does not contain any LocalizationTables"
+            )),
+        },
+        _ => {
+            let paths: Vec<&str> = matches.iter().map(|(_, path)| path.as_str()).collect();
+
+            Err(format_err!(
+                "The file contains more than one matching LocalizationTable. \
+                 Use --table to choose one of: {}",
+                paths.join(", ")
+            ))
+        }
+    }
+}
+
+fn run_check(paths: &[PathBuf], locales: &[String], format: CheckFormat) -> anyhow::Result<()> {
+    let paths: Vec<PathBuf> = if paths.is_empty() {
+        vec![env::current_dir()?]
+    } else {
+        paths
+            .iter()
+            .map(|path| resolve_path(path).into_owned())
+            .collect()
+    };
+
+    let target_locales = if locales.is_empty() {
+        None
+    } else {
+        Some(locales)
+    };
+
+    let report = check(&paths, target_locales)?;
+
+    let stdout = io::stdout();
+    let mut output = stdout.lock();
+
+    match format {
+        CheckFormat::Text => write_text(&mut output, &report)?,
+        CheckFormat::Json => {
+            serde_json::to_writer_pretty(&mut output, &report)?;
+            writeln!(output)?;
+        }
+    }
+
+    output.flush()?;
+
+    if !report.issues.is_empty() {
+        process::exit(ISSUES_EXIT_CODE);
+    }
+
+    Ok(())
+}
+
+/// Every problem found by `rojo localization check`.
+#[derive(Debug, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct CheckReport {
+    files_checked: usize,
+    issues: Vec<ReportedIssue>,
+}
+
+impl CheckReport {
+    fn add(&mut self, path: &Path, issues: Vec<LocalizationIssue>) {
+        self.issues
+            .extend(issues.into_iter().map(|issue| ReportedIssue {
+                path: path.to_path_buf(),
+                issue,
+            }));
+    }
+}
+
+#[derive(Debug, Serialize)]
+struct ReportedIssue {
+    path: PathBuf,
+
+    #[serde(flatten)]
+    issue: LocalizationIssue,
+}
+
+/// The CSV files that make up one LocalizationTable.
+#[derive(Debug, PartialEq)]
+enum CheckTarget {
+    File(PathBuf),
+
+    /// A directory whose CSV files are merged into one table, usually with
+    /// one file per locale.
+    Merged(PathBuf, Vec<PathBuf>),
+}
+
+fn check(paths: &[PathBuf], target_locales: Option<&[String]>) -> anyhow::Result<CheckReport> {
+    let mut targets = Vec::new();
+    for path in paths {
+        find_check_targets(path, &mut targets)?;
+    }
+
+    let mut report = CheckReport::default();
+
+    for target in targets {
+        match target {
+            CheckTarget::File(path) => {
+                let contents = fs::read(&path)?;
+                let issues = check_localization_csv(&contents, target_locales)
+                    .with_context(|| format!("Could not read CSV file {}", path.display()))?;
+
+                report.files_checked += 1;
+                report.add(&path, issues);
+            }
+            CheckTarget::Merged(dir_path, csv_paths) => {
+                let mut tables = Vec::new();
+
+                for path in &csv_paths {
+                    let contents = fs::read(path)?;
+
+                    // Missing translations can only be found once every file
+                    // in the directory has been merged.
+                    let issues = check_localization_csv(&contents, Some(&[]))
+                        .with_context(|| format!("Could not read CSV file {}", path.display()))?;
+
+                    report.files_checked += 1;
+                    report.add(path, issues);
+
+                    // Malformed rows have already been reported, but the rest
+                    // of the file still counts towards the merged table.
+                    let entries = read_localization_csv_flexible(&contents)?;
+                    tables.push((path.as_path(), entries));
+                }
+
+                let entries = merge_localization_tables(&tables);
+                report.add(
+                    &dir_path,
+                    check_merged_localization(&entries, target_locales),
+                );
+            }
+        }
+    }
+
+    Ok(report)
+}
+
+/// Finds the CSV files to check at the given path. Directories are searched
+/// for CSV files, except for directories that turn into one LocalizationTable,
+/// which are checked as one table.
+fn find_check_targets(path: &Path, targets: &mut Vec<CheckTarget>) -> anyhow::Result<()> {
+    if !fs::metadata(path)?.is_dir() {
+        targets.push(CheckTarget::File(path.to_path_buf()));
+        return Ok(());
+    }
+
+    let meta_path = path.join("init.meta.json");
+    let is_table = match fs::read(&meta_path) {
+        Ok(contents) => {
+            let metadata = DirectoryMetadata::from_slice(&contents, meta_path)?;
+            metadata.class_name.as_deref() == Some("LocalizationTable")
+        }
+        Err(err) if err.kind() == io::ErrorKind::NotFound => false,
+        Err(err) => return Err(err.into()),
+    };
+
+    let mut children = Vec::new();
+    for entry in fs::read_dir(path)? {
+        children.push(entry?.path());
+    }
+    children.sort();
+
+    if is_table {
+        let csv_paths = children
+            .iter()
+            .filter(|child| is_csv(child))
+            .cloned()
+            .collect();
+        targets.push(CheckTarget::Merged(path.to_path_buf(), csv_paths));
+    }
+
+    // Directories in a LocalizationTable directory still turn into its
+    // children, so they can have tables of their own.
+    for child in children {
+        if child.is_dir() || (!is_table && is_csv(&child)) {
+            find_check_targets(&child, targets)?;
+        }
+    }
+
+    Ok(())
+}
+
+fn is_csv(path: &Path) -> bool {
+    path.is_file() && path.extension().and_then(|ext| ext.to_str()) == Some("csv")
+}
+
+fn write_text<W: Write>(output: &mut W, report: &CheckReport) -> io::Result<()> {
+    for reported in &report.issues {
+        writeln!(
+            output,
+            "{}: {}",
+            reported.issue.location(&reported.path),
+            reported.issue.message
+        )?;
+    }
+
+    writeln!(
+        output,
+        "Checked {} CSV files and found {} problems",
+        report.files_checked,
+        report.issues.len()
+    )
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use rbx_dom_weak::InstanceBuilder;
+
+    #[test]
+    fn export_table() {
+        let contents = r#"[{"key":"Ack","examples":"An exclamation of despair","source":"Ack!","values":{"es":"¡Ay!"}}]"#;
+
+        let place = WeakDom::new(
+            InstanceBuilder::new("DataModel").with_child(
+                InstanceBuilder::new("ReplicatedStorage").with_child(
+                    InstanceBuilder::new("LocalizationTable")
+                        .with_name("Strings")
+                        .with_property("Contents", contents),
+                ),
+            ),
+        );
+
+        let dir = tempfile::tempdir().unwrap();
+        let input = dir.path().join("place.rbxlx");
+        let output = dir.path().join("Strings.csv");
+
+        let file = fs::File::create(&input).unwrap();
+        rbx_xml::to_writer_default(file, &place, place.root().children()).unwrap();
+
+        export(&input, None, &output).unwrap();
+
+        assert_eq!(
+            fs::read_to_string(&output).unwrap(),
+            "Key,Source,Context,Example,es\n\
+             Ack,Ack!,,An exclamation of despair,¡Ay!\n"
+        );
+    }
+
+    #[test]
+    fn choose_table() {
+        let dom = WeakDom::new(
+            InstanceBuilder::new("DataModel")
+                .with_child(
+                    InstanceBuilder::new("ReplicatedStorage")
+                        .with_child(InstanceBuilder::new("LocalizationTable").with_name("Strings")),
+                )
+                .with_child(
+                    InstanceBuilder::new("LocalizationService")
+                        .with_child(InstanceBuilder::new("LocalizationTable").with_name("Strings"))
+                        .with_child(InstanceBuilder::new("LocalizationTable").with_name("Menus")),
+                ),
+        );
+
+        let path_of = |id| dom_path(&dom, id, false).unwrap();
+
+        assert_eq!(
+            path_of(find_table(&dom, Some("Menus")).unwrap()),
+            "LocalizationService/Menus"
+        );
+        assert_eq!(
+            path_of(find_table(&dom, Some("ReplicatedStorage/Strings")).unwrap()),
+            "ReplicatedStorage/Strings"
+        );
+
+        let err = find_table(&dom, Some("Strings")).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "The file contains more than one matching LocalizationTable. \
+             Use --table to choose one of: LocalizationService/Strings, ReplicatedStorage/Strings"
+        );
+
+        assert!(find_table(&dom, None).is_err());
+        assert!(find_table(&dom, Some("Missing")).is_err());
+    }
+
+    #[test]
+    fn check_files_and_merged_tables() {
+        let dir = tempfile::tempdir().unwrap();
+
+        let strings = dir.path().join("Strings");
+        fs::create_dir(&strings).unwrap();
+        fs::write(
+            strings.join("init.meta.json"),
+            r#"{"className": "LocalizationTable"}"#,
+        )
+        .unwrap();
+        fs::write(
+            strings.join("es.csv"),
+            "Key,Source,es\nAck,Ack!,¡Ay!\nBye,Goodbye!,¡Adiós!\n",
+        )
+        .unwrap();
+        fs::write(strings.join("fr.csv"), "Key,Source,fr\nAck,Ack!,Zut !\n").unwrap();
+
+        let extra = strings.join("Extra");
+        fs::create_dir(&extra).unwrap();
+        fs::write(extra.join("Hints.csv"), "Key,Source\nTip,Tip\nTip,Tip\n").unwrap();
+
+        let menus = dir.path().join("Menus.csv");
+        fs::write(&menus, "Key,Source,es\nPlay,Play,Jugar\nPlay,Play,Jugar\n").unwrap();
+
+        let report = check(&[dir.path().to_path_buf()], None).unwrap();
+        assert_eq!(report.files_checked, 4);
+
+        let issues: Vec<_> = report
+            .issues
+            .iter()
+            .map(|reported| {
+                (
+                    reported.issue.location(&reported.path),
+                    reported.issue.message.as_str(),
+                )
+            })
+            .collect();
+
+        assert_eq!(
+            issues,
+            vec![
+                (
+                    format!("{}:3", menus.display()),
+                    "Localization key 'Play' was already defined on line 2"
+                ),
+                (
+                    strings.display().to_string(),
+                    "Localization key 'Bye' is missing translations for: fr"
+                ),
+                (
+                    format!("{}:3", extra.join("Hints.csv").display()),
+                    "Localization key 'Tip' was already defined on line 2"
+                ),
+            ]
+        );
+
+        let json = serde_json::to_value(&report.issues[0]).unwrap();
+        assert_eq!(
+            json,
+            serde_json::json!({
+                "path": menus,
+                "line": 3,
+                "kind": "duplicateKey",
+                "message": "Localization key 'Play' was already defined on line 2",
+            })
+        );
+    }
+
+    #[test]
+    fn check_merged_table_with_malformed_row() {
+        let dir = tempfile::tempdir().unwrap();
+
+        fs::write(
+            dir.path().join("init.meta.json"),
+            r#"{"className": "LocalizationTable"}"#,
+        )
+        .unwrap();
+        fs::write(
+            dir.path().join("es.csv"),
+            "Key,Source,es\nAck,Ack!,¡Ay!\nBye,Goodbye!,¡Adiós!\n",
+        )
+        .unwrap();
+
+        let fr = dir.path().join("fr.csv");
+        fs::write(
+            &fr,
+            "Key,Source,fr\nAck,Ack!,Zut !\nBye,Goodbye!,Au revoir !,\n",
+        )
+        .unwrap();
+
+        let locales = ["es".to_owned(), "fr".to_owned()];
+        let report = check(&[dir.path().to_path_buf()], Some(&locales)).unwrap();
+
+        let issues: Vec<_> = report
+            .issues
+            .iter()
+            .map(|reported| reported.issue.location(&reported.path))
+            .collect();
+
+        // The rest of fr.csv still counts, so no translations are missing.
+        assert_eq!(issues, vec![format!("{}:3", fr.display())]);
+    }
+}
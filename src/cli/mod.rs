@@ -1,11 +1,15 @@
 //! Defines Rojo's CLI through structopt types.
 
 mod build;
+mod diff;
 mod doc;
 mod fmt_project;
 mod init;
+mod localization;
 mod plugin;
 mod serve;
+mod sourcemap;
+mod syncback;
 mod upload;
 
 use std::{borrow::Cow, env, path::Path, str::FromStr};
@@ -14,12 +18,16 @@ use structopt::StructOpt;
 use thiserror::Error;
 
 pub use self::build::BuildCommand;
+pub use self::diff::{DiffCommand, DiffFormat};
 pub use self::doc::DocCommand;
 pub use self::fmt_project::FmtProjectCommand;
 pub use self::init::{InitCommand, InitKind};
+pub use self::localization::{CheckFormat, LocalizationCommand, LocalizationSubcommand};
 pub use self::plugin::{PluginCommand, PluginSubcommand};
 pub use self::serve::ServeCommand;
-pub use self::upload::UploadCommand;
+pub use self::sourcemap::SourcemapCommand;
+pub use self::syncback::SyncbackCommand;
+pub use self::upload::{UploadCommand, UploadKind, VersionType};
 
 /// Command line options that Rojo accepts, defined using the structopt crate.
 #[derive(Debug, StructOpt)]
@@ -43,6 +51,10 @@ impl Options {
             Subcommand::FmtProject(subcommand) => subcommand.run(),
             Subcommand::Doc(subcommand) => subcommand.run(),
             Subcommand::Plugin(subcommand) => subcommand.run(),
+            Subcommand::Sourcemap(subcommand) => subcommand.run(),
+            Subcommand::Syncback(subcommand) => subcommand.run(),
+            Subcommand::Diff(subcommand) => subcommand.run(),
+            Subcommand::Localization(subcommand) => subcommand.run(),
         }
     }
 }
@@ -115,6 +127,10 @@ pub enum Subcommand {
     FmtProject(FmtProjectCommand),
     Doc(DocCommand),
     Plugin(PluginCommand),
+    Sourcemap(SourcemapCommand),
+    Syncback(SyncbackCommand),
+    Diff(DiffCommand),
+    Localization(LocalizationCommand),
 }
 
 pub(super) fn resolve_path(path: &Path) -> Cow<'_, Path> {
@@ -1,34 +1,68 @@
 use std::{
     fs::{self, File},
     io::BufWriter,
+    path::{Path, PathBuf},
 };
 
+use anyhow::{bail, format_err};
 use memofs::{InMemoryFs, Vfs, VfsSnapshot};
 use roblox_install::RobloxStudio;
 use structopt::StructOpt;
 
-use crate::serve_session::ServeSession;
+use crate::{project::Project, serve_session::ServeSession};
+
+use super::{
+    build::{watch, write_model, OutputKind},
+    resolve_path,
+};
 
 static PLUGIN_BINCODE: &[u8] = include_bytes!(concat!(env!("OUT_DIR"), "/plugin.bincode"));
 static PLUGIN_FILE_NAME: &str = "RojoManagedPlugin.rbxm";
 
-/// Install Rojo's plugin.
+/// Install Rojo's plugin, or a plugin built from a Rojo project.
 #[derive(Debug, StructOpt)]
 pub struct PluginCommand {
     #[structopt(subcommand)]
     subcommand: PluginSubcommand,
 }
 
-/// Manages Rojo's Roblox Studio plugin.
+/// Manages Roblox Studio plugins, including Rojo's own.
 #[derive(Debug, StructOpt)]
 pub enum PluginSubcommand {
-    /// Install the plugin in Roblox Studio's plugins folder. If the plugin is
+    /// Install a plugin in Roblox Studio's plugins folder. If the plugin is
     /// already installed, installing it again will overwrite the current plugin
     /// file.
-    Install,
+    Install {
+        /// Path to a project to build and install as a plugin. Defaults to
+        /// Rojo's own plugin.
+        project: Option<PathBuf>,
+
+        /// Whether to reinstall the plugin when any of the project's files
+        /// change.
+        #[structopt(long, requires = "project")]
+        watch: bool,
+
+        /// The project profile to build with, from the project's `profiles`.
+        #[structopt(long, requires = "project")]
+        profile: Option<String>,
+
+        /// The folder to install the plugin into. Defaults to Roblox Studio's
+        /// plugins folder.
+        #[structopt(long)]
+        plugins_dir: Option<PathBuf>,
+    },
 
     /// Removes the plugin if it is installed.
-    Uninstall,
+    Uninstall {
+        /// Path to the project whose plugin should be removed. Defaults to
+        /// Rojo's own plugin.
+        project: Option<PathBuf>,
+
+        /// The folder to remove the plugin from. Defaults to Roblox Studio's
+        /// plugins folder.
+        #[structopt(long)]
+        plugins_dir: Option<PathBuf>,
+    },
 }
 
 impl PluginCommand {
@@ -40,25 +74,95 @@ impl PluginCommand {
 impl PluginSubcommand {
     pub fn run(self) -> anyhow::Result<()> {
         match self {
-            PluginSubcommand::Install => install_plugin(),
-            PluginSubcommand::Uninstall => uninstall_plugin(),
+            PluginSubcommand::Install {
+                project,
+                watch,
+                profile,
+                plugins_dir,
+            } => {
+                let plugins_folder_path = plugins_folder(plugins_dir.as_deref())?;
+
+                if !plugins_folder_path.exists() {
+                    log::debug!(
+                        "Creating plugins folder at {}",
+                        plugins_folder_path.display()
+                    );
+                    fs::create_dir_all(&plugins_folder_path)?;
+                }
+
+                match project {
+                    Some(project) => install_project(
+                        &resolve_path(&project),
+                        profile.as_deref(),
+                        watch,
+                        &plugins_folder_path,
+                    ),
+                    None => install_plugin(&plugins_folder_path),
+                }
+            }
+            PluginSubcommand::Uninstall {
+                project,
+                plugins_dir,
+            } => {
+                let plugins_folder_path = plugins_folder(plugins_dir.as_deref())?;
+
+                let file_name = match project {
+                    Some(project) => {
+                        let project_path = resolve_path(&project);
+                        let project = Project::load_fuzzy(&project_path)?.ok_or_else(|| {
+                            format_err!("No project file found in {}", project_path.display())
+                        })?;
+
+                        plugin_file_name(&project.name)?
+                    }
+                    None => PLUGIN_FILE_NAME.to_owned(),
+                };
+
+                uninstall_plugin(&plugins_folder_path.join(file_name))
+            }
         }
     }
 }
 
-fn install_plugin() -> anyhow::Result<()> {
-    let plugin_snapshot: VfsSnapshot = bincode::deserialize(PLUGIN_BINCODE)
-        .expect("Rojo's plugin was not properly packed into Rojo's binary");
+/// Finds the folder that plugins are installed in. Roblox Studio's plugins
+/// folder is only looked for if no folder was given, since it can't be found on
+/// every platform.
+fn plugins_folder(plugins_dir: Option<&Path>) -> anyhow::Result<PathBuf> {
+    match plugins_dir {
+        Some(path) => Ok(resolve_path(path).into_owned()),
+        None => Ok(RobloxStudio::locate()?.plugins_path().to_owned()),
+    }
+}
 
-    let studio = RobloxStudio::locate()?;
+/// The name of the file that a project is installed to as a plugin. Names that
+/// would put the plugin outside of the plugins folder or replace Rojo's own
+/// plugin are rejected.
+fn plugin_file_name(project_name: &str) -> anyhow::Result<String> {
+    if project_name.is_empty() || project_name.contains(['/', '\\']) || project_name.contains("..")
+    {
+        bail!(
+            "Project name {:?} cannot be used as a plugin name, it is not a valid file name.",
+            project_name
+        );
+    }
 
-    let plugins_folder_path = studio.plugins_path();
+    let file_name = format!("{}.rbxm", project_name);
 
-    if !plugins_folder_path.exists() {
-        log::debug!("Creating Roblox Studio plugins folder");
-        fs::create_dir(plugins_folder_path)?;
+    // File names are case-insensitive on Windows and macOS.
+    if file_name.eq_ignore_ascii_case(PLUGIN_FILE_NAME) {
+        bail!(
+            "Project name {:?} cannot be used as a plugin name, it is used by Rojo's own plugin.",
+            project_name
+        );
     }
 
+    Ok(file_name)
+}
+
+fn install_plugin(plugins_folder_path: &Path) -> anyhow::Result<()> {
+    let plugin_snapshot: VfsSnapshot = bincode::deserialize(PLUGIN_BINCODE)
+        .expect("Rojo's plugin was not properly packed into Rojo's binary");
+
     let mut in_memory_fs = InMemoryFs::new();
     in_memory_fs.load_snapshot("/plugin", plugin_snapshot)?;
 
@@ -78,11 +182,32 @@ fn install_plugin() -> anyhow::Result<()> {
     Ok(())
 }
 
-fn uninstall_plugin() -> anyhow::Result<()> {
-    let studio = RobloxStudio::locate()?;
+/// Builds a project into the plugins folder, named after the project.
+fn install_project(
+    project_path: &Path,
+    profile: Option<&str>,
+    watch_project: bool,
+    plugins_folder_path: &Path,
+) -> anyhow::Result<()> {
+    let vfs = Vfs::new_default();
+    vfs.set_watch_enabled(watch_project);
 
-    let plugin_path = studio.plugins_path().join(PLUGIN_FILE_NAME);
+    let session = ServeSession::with_profile(vfs, project_path, profile)?;
+    let cursor = session.message_queue().cursor();
 
+    let plugin_path = plugins_folder_path.join(plugin_file_name(session.project_name())?);
+    log::debug!("Writing plugin to {}", plugin_path.display());
+
+    write_model(&session, &plugin_path, OutputKind::Rbxm)?;
+
+    if watch_project {
+        watch(&session, cursor, &plugin_path, OutputKind::Rbxm);
+    }
+
+    Ok(())
+}
+
+fn uninstall_plugin(plugin_path: &Path) -> anyhow::Result<()> {
     if plugin_path.exists() {
         log::debug!("Removing existing plugin from {}", plugin_path.display());
         fs::remove_file(plugin_path)?;
@@ -92,3 +217,64 @@ fn uninstall_plugin() -> anyhow::Result<()> {
 
     Ok(())
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn install_and_uninstall_project() {
+        let project = tempfile::tempdir().unwrap();
+        fs::write(
+            project.path().join("default.project.json"),
+            r#"{"name": "MyPlugin", "tree": {"$path": "src"}}"#,
+        )
+        .unwrap();
+        fs::create_dir(project.path().join("src")).unwrap();
+        fs::write(
+            project.path().join("src").join("init.server.lua"),
+            "print(\"Hello!\")",
+        )
+        .unwrap();
+
+        let plugins = tempfile::tempdir().unwrap();
+        let plugins_dir = plugins.path().join("Plugins");
+
+        PluginSubcommand::Install {
+            project: Some(project.path().to_owned()),
+            watch: false,
+            profile: None,
+            plugins_dir: Some(plugins_dir.clone()),
+        }
+        .run()
+        .unwrap();
+
+        let plugin_path = plugins_dir.join("MyPlugin.rbxm");
+        let dom = rbx_binary::from_reader(File::open(&plugin_path).unwrap()).unwrap();
+        let root = dom.get_by_ref(dom.root().children()[0]).unwrap();
+        assert_eq!(root.name, "MyPlugin");
+        assert_eq!(root.class, "Script");
+
+        PluginSubcommand::Uninstall {
+            project: Some(project.path().to_owned()),
+            plugins_dir: Some(plugins_dir),
+        }
+        .run()
+        .unwrap();
+
+        assert!(!plugin_path.exists());
+    }
+
+    #[test]
+    fn plugin_file_names() {
+        assert_eq!(plugin_file_name("MyPlugin").unwrap(), "MyPlugin.rbxm");
+
+        assert!(plugin_file_name("").is_err());
+        assert!(plugin_file_name("../x").is_err());
+        assert!(plugin_file_name("a/b").is_err());
+        assert!(plugin_file_name("a\\b").is_err());
+        assert!(plugin_file_name("..").is_err());
+        assert!(plugin_file_name("RojoManagedPlugin").is_err());
+        assert!(plugin_file_name("rojomanagedplugin").is_err());
+    }
+}
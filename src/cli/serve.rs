@@ -31,6 +31,16 @@ pub struct ServeCommand {
     /// it has none.
     #[structopt(long)]
     pub port: Option<u16>,
+
+    /// The number of changes to keep for clients that fall behind. Clients
+    /// that miss more changes than this need to sync again from scratch.
+    /// Defaults to `1000`.
+    #[structopt(long)]
+    pub history_limit: Option<usize>,
+
+    /// The project profile to serve with, from the project's `profiles`.
+    #[structopt(long)]
+    pub profile: Option<String>,
 }
 
 impl ServeCommand {
@@ -39,7 +49,15 @@ impl ServeCommand {
 
         let vfs = Vfs::new_default();
 
-        let session = Arc::new(ServeSession::new(vfs, &project_path)?);
+        let session = Arc::new(ServeSession::with_profile(
+            vfs,
+            &project_path,
+            self.profile.as_deref(),
+        )?);
+
+        if let Some(limit) = self.history_limit {
+            session.message_queue().set_history_limit(limit);
+        }
 
         let ip = self.address.unwrap_or(DEFAULT_BIND_ADDRESS.into());
 
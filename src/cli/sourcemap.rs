@@ -0,0 +1,265 @@
+use std::{
+    io::{BufWriter, Write},
+    path::{Path, PathBuf},
+};
+
+use fs_err::File;
+use memofs::Vfs;
+use rbx_dom_weak::types::Ref;
+use serde::Serialize;
+use structopt::StructOpt;
+use tokio::runtime::Runtime;
+
+use crate::{
+    path_serializer,
+    serve_session::ServeSession,
+    snapshot::{InstanceWithMeta, RojoTree},
+};
+
+use super::resolve_path;
+
+/// Representation of a node in the generated sourcemap tree.
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct SourcemapNode {
+    name: String,
+    class_name: String,
+
+    #[serde(
+        skip_serializing_if = "Vec::is_empty",
+        serialize_with = "path_serializer::serialize_vec_absolute"
+    )]
+    file_paths: Vec<PathBuf>,
+
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    children: Vec<SourcemapNode>,
+}
+
+/// Generates a sourcemap file from the Rojo project.
+#[derive(Debug, StructOpt)]
+pub struct SourcemapCommand {
+    /// Path to the project to use for the sourcemap. Defaults to the current
+    /// directory.
+    #[structopt(default_value = "")]
+    pub project: PathBuf,
+
+    /// Where to output the sourcemap. Omit this to use stdout instead of
+    /// writing to a file.
+    ///
+    /// Should end in .json.
+    #[structopt(long, short)]
+    pub output: Option<PathBuf>,
+
+    /// If non-script instances should be included in the sourcemap. Defaults
+    /// to false.
+    #[structopt(long)]
+    pub include_non_scripts: bool,
+
+    /// Whether to automatically regenerate the sourcemap when any input files
+    /// change.
+    #[structopt(long)]
+    pub watch: bool,
+}
+
+impl SourcemapCommand {
+    pub fn run(self) -> anyhow::Result<()> {
+        let project_path = resolve_path(&self.project);
+
+        log::trace!("Constructing in-memory filesystem");
+        let vfs = Vfs::new_default();
+        vfs.set_watch_enabled(self.watch);
+
+        let session = ServeSession::new(vfs, &project_path)?;
+        let mut cursor = session.message_queue().cursor();
+
+        let filter = if self.include_non_scripts {
+            filter_nothing
+        } else {
+            filter_non_scripts
+        };
+
+        write_sourcemap(&session, self.output.as_deref(), filter)?;
+
+        if self.watch {
+            let rt = Runtime::new().unwrap();
+
+            loop {
+                let receiver = session.message_queue().subscribe(cursor);
+                cursor = match rt.block_on(receiver).unwrap() {
+                    Ok((new_cursor, _patch_sets)) => new_cursor,
+
+                    // Some changes were dropped before we saw them, but we
+                    // regenerate the whole sourcemap either way.
+                    Err(_) => session.message_queue().cursor(),
+                };
+
+                write_sourcemap(&session, self.output.as_deref(), filter)?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+fn filter_nothing(_instance: &InstanceWithMeta) -> bool {
+    true
+}
+
+fn filter_non_scripts(instance: &InstanceWithMeta) -> bool {
+    matches!(
+        instance.class_name(),
+        "Script" | "LocalScript" | "ModuleScript"
+    )
+}
+
+fn recurse_create_node(
+    session: &ServeSession,
+    tree: &RojoTree,
+    id: Ref,
+    project_dir: &Path,
+    filter: fn(&InstanceWithMeta) -> bool,
+) -> Option<SourcemapNode> {
+    let instance = tree.get_instance(id).expect("instance did not exist");
+
+    let children: Vec<_> = instance
+        .children()
+        .iter()
+        .filter_map(|&child_id| recurse_create_node(session, tree, child_id, project_dir, filter))
+        .collect();
+
+    // If this instance doesn't pass the filter and none of its descendants
+    // did either, it doesn't contain anything we're looking for.
+    if children.is_empty() && !filter(&instance) {
+        return None;
+    }
+
+    let file_paths = instance
+        .metadata()
+        .relevant_paths
+        .iter()
+        // Not all paths listed as relevant are guaranteed to exist.
+        .filter(|path| {
+            session
+                .vfs()
+                .metadata(path)
+                .map(|meta| meta.is_file())
+                .unwrap_or(false)
+        })
+        // Paths outside of the project's folder are left absolute.
+        .map(|path| path.strip_prefix(project_dir).unwrap_or(path).to_path_buf())
+        .collect();
+
+    Some(SourcemapNode {
+        name: instance.name().to_owned(),
+        class_name: instance.class_name().to_owned(),
+        file_paths,
+        children,
+    })
+}
+
+fn write_sourcemap(
+    session: &ServeSession,
+    output: Option<&Path>,
+    filter: fn(&InstanceWithMeta) -> bool,
+) -> anyhow::Result<()> {
+    let tree = session.tree();
+    let project_dir = session.root_project().folder_location();
+
+    let root_node = recurse_create_node(session, &tree, tree.get_root_id(), project_dir, filter);
+
+    if let Some(output_path) = output {
+        log::trace!("Opening sourcemap file for write");
+        let mut file = BufWriter::new(File::create(output_path)?);
+        serde_json::to_writer(&mut file, &root_node)?;
+        file.flush()?;
+
+        println!("Created sourcemap at {}", output_path.display());
+    } else {
+        let output = serde_json::to_string(&root_node)?;
+        println!("{}", output);
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use maplit::hashmap;
+    use memofs::{InMemoryFs, VfsSnapshot};
+
+    fn sourcemap_for(include_non_scripts: bool) -> serde_json::Value {
+        let mut imfs = InMemoryFs::new();
+        imfs.load_snapshot(
+            "/foo",
+            VfsSnapshot::dir(hashmap! {
+                "default.project.json" => VfsSnapshot::file(r#"
+                    {
+                        "name": "sourcemap-project",
+                        "tree": {
+                            "$path": "src"
+                        }
+                    }
+                "#),
+                "src" => VfsSnapshot::dir(hashmap! {
+                    "hello.lua" => VfsSnapshot::file("return 'hello'"),
+                    "notes.txt" => VfsSnapshot::file("Some notes"),
+                    "Empty" => VfsSnapshot::empty_dir(),
+                }),
+            }),
+        )
+        .unwrap();
+
+        let session = ServeSession::new(Vfs::new(imfs), "/foo").unwrap();
+        let tree = session.tree();
+
+        let filter = if include_non_scripts {
+            filter_nothing
+        } else {
+            filter_non_scripts
+        };
+
+        let root_node = recurse_create_node(
+            &session,
+            &tree,
+            tree.get_root_id(),
+            Path::new("/foo"),
+            filter,
+        );
+
+        serde_json::to_value(&root_node).unwrap()
+    }
+
+    #[test]
+    fn scripts_only() {
+        let sourcemap = sourcemap_for(false);
+
+        assert_eq!(sourcemap["name"], "sourcemap-project");
+        assert_eq!(sourcemap["className"], "Folder");
+
+        let children = sourcemap["children"].as_array().unwrap();
+        assert_eq!(children.len(), 1);
+        assert_eq!(children[0]["name"], "hello");
+        assert_eq!(children[0]["className"], "ModuleScript");
+        assert_eq!(
+            children[0]["filePaths"],
+            serde_json::json!(["src/hello.lua"])
+        );
+    }
+
+    #[test]
+    fn include_non_scripts() {
+        let sourcemap = sourcemap_for(true);
+
+        let mut names: Vec<_> = sourcemap["children"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|child| child["name"].as_str().unwrap().to_owned())
+            .collect();
+        names.sort();
+
+        assert_eq!(names, vec!["Empty", "hello", "notes"]);
+    }
+}
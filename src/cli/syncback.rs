@@ -0,0 +1,399 @@
+use std::{
+    collections::BTreeMap,
+    io::BufReader,
+    path::{Path, PathBuf},
+};
+
+use anyhow::{bail, Context};
+use fs_err::File;
+use memofs::Vfs;
+use rbx_dom_weak::{types::Variant, WeakDom};
+use rbx_reflection::ClassTag;
+use structopt::StructOpt;
+
+use crate::{
+    project::{Project, ProjectNode},
+    resolution::{take_attributes, UnresolvedValue},
+    snapshot::InstanceSnapshot,
+    syncback,
+};
+
+use super::resolve_path;
+
+const UNKNOWN_INPUT_KIND_ERR: &str = "Could not detect what kind of file to convert. \
+                                      Expected input file to end in .rbxl, .rbxlx, .rbxm, or .rbxmx.";
+
+/// Converts a place or model file into a new Rojo project.
+#[derive(Debug, StructOpt)]
+pub struct SyncbackCommand {
+    /// Path to the place or model file to convert.
+    ///
+    /// Should end in .rbxl, .rbxlx, .rbxm, or .rbxmx.
+    pub input: PathBuf,
+
+    /// Folder to create the project in. Must be empty or not exist yet.
+    #[structopt(long, short)]
+    pub output: PathBuf,
+}
+
+impl SyncbackCommand {
+    pub fn run(self) -> anyhow::Result<()> {
+        let input_path = resolve_path(&self.input);
+        let output_path = resolve_path(&self.output);
+
+        let input_kind = detect_input_kind(&input_path).context(UNKNOWN_INPUT_KIND_ERR)?;
+        let dom = read_dom(&input_path)?;
+
+        if output_path.exists() && fs_err::read_dir(output_path.as_ref())?.next().is_some() {
+            bail!(
+                "Cannot create a project in {}, the folder is not empty.",
+                output_path.display()
+            );
+        }
+
+        fs_err::create_dir_all(&output_path)?;
+
+        let vfs = Vfs::new_default();
+        vfs.set_watch_enabled(false);
+
+        let src_path = output_path.join("src");
+        vfs.create_dir(&src_path)?;
+
+        let tree = match input_kind {
+            InputKind::Place => place_node(&vfs, &output_path, &src_path, &dom)?,
+            InputKind::Model => model_node(&vfs, &output_path, &src_path, &dom)?,
+        };
+
+        let name = input_path
+            .file_stem()
+            .and_then(|stem| stem.to_str())
+            .unwrap_or("syncback")
+            .to_owned();
+
+        let project = Project {
+            name,
+            extends: None,
+            tree,
+            serve_port: None,
+            serve_place_ids: None,
+            place_id: None,
+            game_id: None,
+            glob_ignore_paths: Vec::new(),
+            sync_rules: Vec::new(),
+            typed_data_modules: None,
+            profiles: BTreeMap::new(),
+            file_location: output_path.join("default.project.json"),
+            extended_ignore_rules: Vec::new(),
+            extended_files: Vec::new(),
+        };
+
+        let serialized = serde_json::to_string_pretty(&project)
+            .context("could not encode project file as JSON")?;
+        vfs.write(&project.file_location, serialized)?;
+
+        println!("Created project at {}", output_path.display());
+
+        Ok(())
+    }
+}
+
+/// The different kinds of input that Rojo can convert into a project.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(super) enum InputKind {
+    /// A place file, whose top-level instances are services.
+    Place,
+
+    /// A model file, which must contain exactly one top-level instance.
+    Model,
+}
+
+pub(super) fn detect_input_kind(input: &Path) -> Option<InputKind> {
+    match input.extension()?.to_str()? {
+        "rbxl" | "rbxlx" => Some(InputKind::Place),
+        "rbxm" | "rbxmx" => Some(InputKind::Model),
+        _ => None,
+    }
+}
+
+pub(super) fn read_dom(input: &Path) -> anyhow::Result<WeakDom> {
+    let reader = BufReader::new(File::open(input)?);
+
+    let dom = match input.extension().and_then(|ext| ext.to_str()) {
+        Some("rbxlx") | Some("rbxmx") => rbx_xml::from_reader_default(reader)
+            .with_context(|| format!("Malformed XML file: {}", input.display()))?,
+        _ => rbx_binary::from_reader(reader)
+            .with_context(|| format!("Malformed binary file: {}", input.display()))?,
+    };
+
+    Ok(dom)
+}
+
+/// Creates the project node for a place, writing each service's contents into
+/// its own folder.
+fn place_node(
+    vfs: &Vfs,
+    project_dir: &Path,
+    src_path: &Path,
+    dom: &WeakDom,
+) -> anyhow::Result<ProjectNode> {
+    let mut root = ProjectNode {
+        class_name: Some("DataModel".to_owned()),
+        ..Default::default()
+    };
+
+    for &child_id in dom.root().children() {
+        let mut snapshot = InstanceSnapshot::from_tree(dom, child_id);
+        syncback::remove_default_properties(&mut snapshot);
+
+        let name = snapshot.name.to_string();
+        if root.children.contains_key(&name) {
+            log::warn!(
+                "Skipping top-level instance {}, another instance already has that name",
+                name
+            );
+            continue;
+        }
+
+        let node = if is_service(&snapshot.class_name) {
+            service_node(vfs, project_dir, src_path, &snapshot)?
+        } else {
+            path_node(vfs, project_dir, src_path, &snapshot)?
+        };
+
+        root.children.insert(name, node);
+    }
+
+    Ok(root)
+}
+
+fn model_node(
+    vfs: &Vfs,
+    project_dir: &Path,
+    src_path: &Path,
+    dom: &WeakDom,
+) -> anyhow::Result<ProjectNode> {
+    let children = dom.root().children();
+
+    if children.len() != 1 {
+        bail!(
+            "Rojo can only convert model files with one top-level instance, \
+             but this model has {}.",
+            children.len()
+        );
+    }
+
+    let mut snapshot = InstanceSnapshot::from_tree(dom, children[0]);
+    syncback::remove_default_properties(&mut snapshot);
+
+    path_node(vfs, project_dir, src_path, &snapshot)
+}
+
+/// Services are described by the project file itself, which keeps their
+/// properties in `$properties` and points `$path` at a folder holding their
+/// children.
+fn service_node(
+    vfs: &Vfs,
+    project_dir: &Path,
+    src_path: &Path,
+    snapshot: &InstanceSnapshot,
+) -> anyhow::Result<ProjectNode> {
+    // If the children of this service can't all become distinct files, the
+    // whole service is written out as a model file instead.
+    if !syncback::children_fit_in_directory(snapshot) {
+        return path_node(vfs, project_dir, src_path, snapshot);
+    }
+
+    let mut node = ProjectNode::default();
+
+    // Services are inferred from their names, so only services with unusual
+    // names need their class spelled out.
+    if snapshot.name != snapshot.class_name {
+        node.class_name = Some(snapshot.class_name.to_string());
+    }
+
+    let mut properties = snapshot.properties.clone();
+    node.attributes = take_attributes(&mut properties);
+
+    for (key, value) in properties {
+        if let Variant::Ref(_) | Variant::SharedString(_) = value {
+            log::warn!(
+                "Property {}.{} cannot be written to a project file, skipping it",
+                snapshot.name,
+                key
+            );
+            continue;
+        }
+
+        node.properties
+            .insert(key, UnresolvedValue::FullyQualified(value));
+    }
+
+    if !snapshot.children.is_empty() {
+        let dir_path = src_path.join(snapshot.name.as_ref());
+        vfs.create_dir(&dir_path)?;
+
+        for child in &snapshot.children {
+            syncback::write_instance(vfs, &dir_path, child)?;
+        }
+
+        node.path = Some(relative_path(project_dir, &dir_path));
+    }
+
+    Ok(node)
+}
+
+/// Writes the instance using the same rules as two-way sync and points a
+/// project node at the result.
+fn path_node(
+    vfs: &Vfs,
+    project_dir: &Path,
+    src_path: &Path,
+    snapshot: &InstanceSnapshot,
+) -> anyhow::Result<ProjectNode> {
+    let written = syncback::write_instance(vfs, src_path, snapshot)?;
+
+    Ok(ProjectNode {
+        path: Some(relative_path(project_dir, &written[0])),
+        ..Default::default()
+    })
+}
+
+fn relative_path(base: &Path, path: &Path) -> PathBuf {
+    path.strip_prefix(base).unwrap_or(path).to_path_buf()
+}
+
+fn is_service(class_name: &str) -> bool {
+    let database = rbx_reflection_database::get();
+
+    database
+        .classes
+        .get(class_name)
+        .map(|descriptor| descriptor.tags.contains(&ClassTag::Service))
+        .unwrap_or(false)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use rbx_dom_weak::{types::Ref, InstanceBuilder};
+
+    use crate::serve_session::ServeSession;
+
+    /// Describes the shape of a tree as text, with children sorted by name so
+    /// that trees can be compared regardless of ordering.
+    fn describe(dom: &WeakDom, id: Ref, depth: usize, output: &mut String) {
+        let instance = dom.get_by_ref(id).unwrap();
+
+        output.push_str(&"  ".repeat(depth));
+        output.push_str(&format!("{} {}", instance.class, instance.name));
+        if let Some(Variant::String(source)) = instance.properties.get("Source") {
+            output.push_str(&format!(" {:?}", source));
+        }
+        output.push('\n');
+
+        let mut children = instance.children().to_vec();
+        children.sort_by_key(|&child| dom.get_by_ref(child).unwrap().name.clone());
+
+        for child in children {
+            describe(dom, child, depth + 1, output);
+        }
+    }
+
+    #[test]
+    fn place_round_trip() {
+        let place = WeakDom::new(
+            InstanceBuilder::new("DataModel")
+                .with_child(
+                    InstanceBuilder::new("ReplicatedStorage").with_child(
+                        InstanceBuilder::new("Folder")
+                            .with_name("Shared")
+                            .with_child(
+                                InstanceBuilder::new("ModuleScript")
+                                    .with_name("Util")
+                                    .with_property("Source", "return {}"),
+                            ),
+                    ),
+                )
+                .with_child(
+                    InstanceBuilder::new("ServerScriptService").with_child(
+                        InstanceBuilder::new("Script")
+                            .with_name("Main")
+                            .with_property("Source", "print('hello')")
+                            .with_child(
+                                InstanceBuilder::new("ModuleScript")
+                                    .with_name("Config")
+                                    .with_property("Source", "return 5"),
+                            ),
+                    ),
+                )
+                .with_child(
+                    InstanceBuilder::new("StarterPlayer").with_child(
+                        InstanceBuilder::new("StarterPlayerScripts").with_child(
+                            InstanceBuilder::new("LocalScript")
+                                .with_name("Client")
+                                .with_property("Source", "print('client')"),
+                        ),
+                    ),
+                )
+                .with_child(
+                    InstanceBuilder::new("Workspace")
+                        .with_child(InstanceBuilder::new("Part").with_name("Part"))
+                        .with_child(InstanceBuilder::new("Part").with_name("Part")),
+                )
+                .with_child(InstanceBuilder::new("Lighting").with_property("Brightness", 5.0f32)),
+        );
+
+        let dir = tempfile::tempdir().unwrap();
+        let place_path = dir.path().join("game.rbxlx");
+        let project_path = dir.path().join("game");
+
+        let file = File::create(&place_path).unwrap();
+        rbx_xml::to_writer_default(file, &place, place.root().children()).unwrap();
+
+        SyncbackCommand {
+            input: place_path,
+            output: project_path.clone(),
+        }
+        .run()
+        .unwrap();
+
+        assert!(project_path.join("src/Workspace.model.json").is_file());
+        assert!(project_path
+            .join("src/ServerScriptService/Main/init.server.lua")
+            .is_file());
+        assert!(project_path
+            .join("src/StarterPlayer/StarterPlayerScripts/init.meta.json")
+            .is_file());
+
+        let vfs = Vfs::new_default();
+        vfs.set_watch_enabled(false);
+
+        let session = ServeSession::new(vfs, &project_path).unwrap();
+        let tree = session.tree();
+        let built = tree.inner();
+
+        let mut expected = String::new();
+        describe(&place, place.root_ref(), 0, &mut expected);
+
+        let mut actual = String::new();
+        describe(built, built.root_ref(), 0, &mut actual);
+
+        // The root instance is named after the project.
+        let expected = expected.replacen("DataModel DataModel", "DataModel game", 1);
+        assert_eq!(actual, expected);
+
+        let lighting = built
+            .root()
+            .children()
+            .iter()
+            .map(|&id| built.get_by_ref(id).unwrap())
+            .find(|instance| instance.class == "Lighting")
+            .unwrap();
+
+        assert_eq!(
+            lighting.properties.get("Brightness"),
+            Some(&Variant::Float32(5.0))
+        );
+    }
+}
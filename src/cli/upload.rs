@@ -1,18 +1,41 @@
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::str::FromStr;
+use std::{fmt, thread, time::Duration};
 
 use anyhow::{bail, format_err, Context};
+use fs_err as fs;
 use memofs::Vfs;
 use reqwest::{
-    header::{ACCEPT, CONTENT_TYPE, COOKIE, USER_AGENT},
-    StatusCode,
+    header::{HeaderValue, ACCEPT, CONTENT_TYPE, COOKIE, RETRY_AFTER, USER_AGENT},
+    multipart::{Form, Part},
+    Method, RequestBuilder, Response, StatusCode,
 };
+use serde::{de::DeserializeOwned, Deserialize};
 use structopt::StructOpt;
 
-use crate::{auth_cookie::get_auth_cookie, serve_session::ServeSession};
+use crate::{
+    auth_cookie::get_auth_cookie,
+    credentials::{Credentials, Secret},
+    deploy_manifest::{DeployManifest, FailurePolicy},
+    serve_session::ServeSession,
+};
 
 use super::resolve_path;
 
+const OPEN_CLOUD_URL: &str = "https://apis.roblox.com";
+
+/// How long to wait between checks on an Open Cloud operation, like processing
+/// an uploaded model.
+const OPERATION_POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+/// How many times to check on an Open Cloud operation before giving up.
+const OPERATION_POLL_ATTEMPTS: u32 = 60;
+
+/// How long to wait before the first retry of a failed request. Each retry
+/// after that waits twice as long as the last, up to `MAX_RETRY_DELAY`.
+const RETRY_BASE_DELAY: Duration = Duration::from_secs(1);
+const MAX_RETRY_DELAY: Duration = Duration::from_secs(30);
+
 /// Builds the project and uploads it to Roblox.
 #[derive(Debug, StructOpt)]
 pub struct UploadCommand {
@@ -20,73 +43,324 @@ pub struct UploadCommand {
     #[structopt(default_value = "")]
     pub project: PathBuf,
 
-    /// Authenication cookie to use. If not specified, Rojo will attempt to find one from the system automatically.
+    /// The kind of asset to upload to, 'place' or 'model'. Defaults to place.
+    #[structopt(long, default_value = "place")]
+    pub kind: UploadKind,
+
+    /// The project profile to build with, from the project's `profiles`. Places in a deploy manifest can choose their own profile instead.
+    #[structopt(long)]
+    pub profile: Option<String>,
+
+    /// Authenication cookie to use. If not specified, Rojo will read ROJO_ROBLOX_COOKIE, then the credentials file, then attempt to find one from the system automatically.
     #[structopt(long)]
-    pub cookie: Option<String>,
+    pub cookie: Option<Secret>,
 
-    /// API key obtained from create.roblox.com/credentials. Rojo will use the Open Cloud API when this is provided. Only supports uploading to a place.
+    /// API key obtained from create.roblox.com/credentials. If not specified, Rojo will read ROJO_API_KEY, then the credentials file. Rojo will use the Open Cloud API when an API key is found.
     #[structopt(long = "api_key")]
-    pub api_key: Option<String>,
+    pub api_key: Option<Secret>,
 
-    /// The Universe ID of the given place. Required when using the Open Cloud API.
+    /// Profile to read from the credentials file, ~/.rojo/credentials.toml or the path in ROJO_CREDENTIALS_FILE. Defaults to the 'default' profile.
+    #[structopt(long = "credentials-profile")]
+    pub credentials_profile: Option<String>,
+
+    /// The Universe ID of the given place. Required when uploading a place with the Open Cloud API.
     #[structopt(long = "universe_id")]
     pub universe_id: Option<u64>,
 
-    /// Asset ID to upload to.
-    #[structopt(long = "asset_id")]
-    pub asset_id: u64,
+    /// The kind of place version to create with the Open Cloud API, 'saved' or 'published'. Defaults to published.
+    #[structopt(long = "version-type", default_value = "published")]
+    pub version_type: VersionType,
+
+    /// Asset ID to upload to. Required unless --manifest is used.
+    #[structopt(long = "asset_id", required_unless = "manifest")]
+    pub asset_id: Option<u64>,
+
+    /// Deploy manifest listing the places to build and upload, like rojo-deploy.json. Requires --env.
+    #[structopt(
+        long,
+        requires = "env",
+        conflicts_with_all = &["asset_id", "universe_id", "kind", "output"]
+    )]
+    pub manifest: Option<PathBuf>,
+
+    /// Which environment's places to upload from the deploy manifest, like 'staging' or 'production'.
+    #[structopt(long, requires = "manifest")]
+    pub env: Option<String>,
+
+    /// How many times to retry requests that fail because of rate limits,
+    /// server errors, or network problems.
+    #[structopt(long, default_value = "3")]
+    pub retries: u32,
+
+    /// How many seconds to wait for each request before giving up on it.
+    #[structopt(long, default_value = "60")]
+    pub timeout: u64,
+
+    /// Build the project and report what would be uploaded without uploading
+    /// anything.
+    #[structopt(long = "dry-run")]
+    pub dry_run: bool,
+
+    /// Also write the exact bytes that are uploaded to this file.
+    #[structopt(long, short)]
+    pub output: Option<PathBuf>,
 }
 
 impl UploadCommand {
     pub fn run(self) -> Result<(), anyhow::Error> {
-        let project_path = resolve_path(&self.project);
+        // Passing secrets on the command line leaves them in shell history, so
+        // they can also come from the environment or the credentials file.
+        let cookie_flag_given = self.cookie.is_some();
+        let credentials = Credentials {
+            api_key: self.api_key.clone(),
+            cookie: self.cookie.clone(),
+        }
+        .find(self.credentials_profile.as_deref())?;
 
-        let vfs = Vfs::new_default();
+        // using open cloud. notify if cookie is provided.
+        if cookie_flag_given && credentials.api_key.is_some() {
+            log::warn!("--cookie was provided but is ignored when using Open Cloud API");
+        }
 
-        let session = ServeSession::new(vfs, project_path)?;
+        let uploader = if self.dry_run {
+            None
+        } else {
+            let client = RetryingClient::new(Duration::from_secs(self.timeout), self.retries)?;
+            Some(Uploader::new(client, credentials.clone())?)
+        };
 
-        let tree = session.tree();
-        let inner_tree = tree.inner();
-        let root = inner_tree.root();
+        if let (Some(manifest), Some(environment)) = (&self.manifest, &self.env) {
+            let manifest = DeployManifest::load(&resolve_path(manifest))?;
+            return deploy(
+                &manifest,
+                environment,
+                self.profile.as_deref(),
+                uploader.as_ref(),
+                self.version_type,
+            );
+        }
 
-        let encode_ids = match root.class.as_str() {
-            "DataModel" => root.children().to_vec(),
-            _ => vec![root.referent()],
+        let target = UploadTarget {
+            kind: self.kind,
+            asset_id: self.asset_id.unwrap(),
+            universe_id: self.universe_id,
         };
 
-        let mut buffer = Vec::new();
+        let buffer = build_payload(
+            &resolve_path(&self.project),
+            self.kind,
+            self.profile.as_deref(),
+        )?;
+
+        if let Some(output) = &self.output {
+            fs::write(resolve_path(output), &buffer)?;
+        }
 
-        log::trace!("Encoding binary model");
-        rbx_binary::to_writer(&mut buffer, tree.inner(), &encode_ids)?;
+        let uploader = match uploader {
+            Some(uploader) => uploader,
+            None => {
+                let api = match credentials.api_key {
+                    Some(_) => "the Open Cloud API",
+                    None => "legacy upload",
+                };
 
-        match (self.cookie, self.api_key, self.universe_id) {
-            (cookie, None, universe) => {
+                println!(
+                    "Dry run: would upload {} bytes to {} {} using {}",
+                    buffer.len(),
+                    target.kind.as_str(),
+                    target.asset_id,
+                    api
+                );
+
+                return Ok(());
+            }
+        };
+
+        if target.universe_id.is_some() {
+            match (&uploader, target.kind) {
                 // using legacy. notify if universe is provided.
-                if universe.is_some() {
-                    log::warn!(
-                        "--universe_id was provided but is ignored when using legacy upload"
-                    );
+                (Uploader::Legacy { .. }, _) => {
+                    log::warn!("--universe_id was provided but is ignored when using legacy upload")
+                }
+                (Uploader::OpenCloud(_), UploadKind::Model) => {
+                    log::warn!("--universe_id was provided but is ignored when uploading a model")
+                }
+                (Uploader::OpenCloud(_), UploadKind::Place) => {}
+            }
+        }
+
+        uploader.upload(buffer, &target, self.version_type)
+    }
+}
+
+/// Builds the project at the given path with an optional profile and encodes
+/// it the way Roblox expects the given kind of asset.
+fn build_payload(
+    project_path: &Path,
+    kind: UploadKind,
+    profile: Option<&str>,
+) -> anyhow::Result<Vec<u8>> {
+    let vfs = Vfs::new_default();
+    vfs.set_watch_enabled(false);
+
+    let session = ServeSession::with_profile(vfs, project_path, profile)?;
+
+    let tree = session.tree();
+    let inner_tree = tree.inner();
+    let root = inner_tree.root();
+
+    let encode_ids = match (kind, root.class.as_str()) {
+        (UploadKind::Place, "DataModel") => root.children().to_vec(),
+        (UploadKind::Model, "DataModel") => bail!(
+            "Cannot upload a project whose root is a DataModel as a model. \
+             Use --kind place to upload it to a place instead."
+        ),
+        _ => vec![root.referent()],
+    };
+
+    let mut buffer = Vec::new();
+
+    log::trace!("Encoding binary model");
+    rbx_binary::to_writer(&mut buffer, tree.inner(), &encode_ids)?;
+
+    Ok(buffer)
+}
+
+/// Builds and uploads every place in the manifest that belongs to the given
+/// environment, one at a time. If `uploader` is `None`, places are only built.
+fn deploy(
+    manifest: &DeployManifest,
+    environment: &str,
+    profile: Option<&str>,
+    uploader: Option<&Uploader>,
+    version_type: VersionType,
+) -> anyhow::Result<()> {
+    let places: Vec<_> = manifest.places_in(environment).collect();
+
+    if places.is_empty() {
+        bail!(
+            "The deploy manifest {} has no places in the '{}' environment",
+            manifest.file_location.display(),
+            environment
+        );
+    }
+
+    let mut succeeded = 0;
+    let mut failed = Vec::new();
+
+    for (index, place) in places.iter().enumerate() {
+        let name = place.display_name();
+        println!(
+            "[{}/{}] {} (place {})",
+            index + 1,
+            places.len(),
+            name,
+            place.place_id
+        );
+
+        let target = UploadTarget {
+            kind: UploadKind::Place,
+            asset_id: place.place_id,
+            universe_id: Some(place.universe_id),
+        };
+
+        let profile = place.profile.as_deref().or(profile);
+
+        let result = build_payload(&place.project, UploadKind::Place, profile).and_then(|buffer| {
+            match uploader {
+                Some(uploader) => uploader.upload(buffer, &target, version_type),
+                None => {
+                    println!("    Dry run: would upload {} bytes", buffer.len());
+                    Ok(())
                 }
+            }
+        });
 
-                let cookie = cookie.or_else(get_auth_cookie).context(
-                    "Rojo could not find your Roblox auth cookie. Please pass one via --cookie.",
-                )?;
-                do_upload(buffer, self.asset_id, &cookie)
+        match result {
+            Ok(()) => {
+                succeeded += 1;
+                println!("    Done");
             }
+            Err(err) => {
+                println!("    Failed: {:#}", err);
+                failed.push(name);
 
-            (cookie, Some(api_key), Some(universe_id)) => {
-                // using open cloud. notify if cookie is provided.
-                if cookie.is_some() {
-                    log::warn!("--cookie was provided but is ignored when using Open Cloud API");
+                if manifest.on_failure == FailurePolicy::Stop {
+                    break;
                 }
+            }
+        }
+    }
+
+    let skipped = places.len() - succeeded - failed.len();
+    println!(
+        "{} succeeded, {} failed, {} skipped",
+        succeeded,
+        failed.len(),
+        skipped
+    );
+
+    if !failed.is_empty() {
+        bail!("Could not upload {}", failed.join(", "));
+    }
+
+    Ok(())
+}
+
+/// Where to upload a built project.
+struct UploadTarget {
+    kind: UploadKind,
+    asset_id: u64,
+    universe_id: Option<u64>,
+}
+
+/// Uploads with whichever API the user has credentials for.
+enum Uploader {
+    Legacy {
+        client: RetryingClient,
+        cookie: Secret,
+    },
+    OpenCloud(OpenCloud),
+}
+
+impl Uploader {
+    fn new(client: RetryingClient, credentials: Credentials) -> anyhow::Result<Self> {
+        match credentials.api_key {
+            Some(api_key) => Ok(Uploader::OpenCloud(OpenCloud::new(client, &api_key)?)),
+            None => {
+                let cookie = credentials
+                    .cookie
+                    .or_else(|| get_auth_cookie().map(Secret::new))
+                    .context(
+                        "Rojo could not find your Roblox auth cookie. \
+                         Please set ROJO_ROBLOX_COOKIE or pass one via --cookie.",
+                    )?;
 
-                do_upload_open_cloud(buffer, universe_id, self.asset_id, &api_key)
+                Ok(Uploader::Legacy { client, cookie })
             }
+        }
+    }
 
-            (_, Some(_), None) => {
-                // API key is provided, universe id is not.
-                bail!("--universe_id must be provided to use the Open Cloud API");
+    fn upload(
+        &self,
+        buffer: Vec<u8>,
+        target: &UploadTarget,
+        version_type: VersionType,
+    ) -> anyhow::Result<()> {
+        match self {
+            Uploader::Legacy { client, cookie } => {
+                do_upload(client, buffer, target.asset_id, cookie)
             }
+            Uploader::OpenCloud(open_cloud) => match target.kind {
+                UploadKind::Place => {
+                    let universe_id = target.universe_id.context(
+                        "--universe_id must be provided to upload a place with the Open Cloud API",
+                    )?;
+
+                    open_cloud.upload_place(buffer, universe_id, target.asset_id, version_type)
+                }
+                UploadKind::Model => open_cloud.upload_model(buffer, target.asset_id),
+            },
         }
     }
 }
@@ -94,7 +368,7 @@ impl UploadCommand {
 /// The kind of asset to upload to the website. Affects what endpoints Rojo uses
 /// and changes how the asset is built.
 #[derive(Debug, Clone, Copy)]
-enum UploadKind {
+pub enum UploadKind {
     /// Upload to a place.
     Place,
 
@@ -102,6 +376,15 @@ enum UploadKind {
     Model,
 }
 
+impl UploadKind {
+    fn as_str(self) -> &'static str {
+        match self {
+            UploadKind::Place => "place",
+            UploadKind::Model => "model",
+        }
+    }
+}
+
 impl FromStr for UploadKind {
     type Err = anyhow::Error;
 
@@ -117,18 +400,58 @@ impl FromStr for UploadKind {
     }
 }
 
-fn do_upload(buffer: Vec<u8>, asset_id: u64, cookie: &str) -> anyhow::Result<()> {
+/// The kind of version to create when uploading a place with the Open Cloud
+/// API.
+#[derive(Debug, Clone, Copy)]
+pub enum VersionType {
+    /// Save the place without changing the version that players join.
+    Saved,
+
+    /// Save the place and make it the version that players join.
+    Published,
+}
+
+impl VersionType {
+    fn as_str(self) -> &'static str {
+        match self {
+            VersionType::Saved => "Saved",
+            VersionType::Published => "Published",
+        }
+    }
+}
+
+impl FromStr for VersionType {
+    type Err = anyhow::Error;
+
+    fn from_str(source: &str) -> Result<Self, Self::Err> {
+        match source {
+            "saved" => Ok(VersionType::Saved),
+            "published" => Ok(VersionType::Published),
+            attempted => Err(format_err!(
+                "Invalid version type '{}'. Valid types are: saved, published",
+                attempted
+            )),
+        }
+    }
+}
+
+fn do_upload(
+    client: &RetryingClient,
+    buffer: Vec<u8>,
+    asset_id: u64,
+    cookie: &Secret,
+) -> anyhow::Result<()> {
     let url = format!(
         "https://data.roblox.com/Data/Upload.ashx?assetid={}",
         asset_id
     );
 
-    let client = reqwest::Client::new();
+    let cookie = secret_header(&format!(".ROBLOSECURITY={}", cookie.expose()))?;
 
-    let build_request = move || {
+    let build_request = |client: &reqwest::Client| {
         client
             .post(&url)
-            .header(COOKIE, format!(".ROBLOSECURITY={}", cookie))
+            .header(COOKIE, cookie.clone())
             .header(USER_AGENT, "Roblox/WinInet")
             .header(CONTENT_TYPE, "application/xml")
             .header(ACCEPT, "application/json")
@@ -136,7 +459,7 @@ fn do_upload(buffer: Vec<u8>, asset_id: u64, cookie: &str) -> anyhow::Result<()>
     };
 
     log::debug!("Uploading to Roblox...");
-    let mut response = build_request().send()?;
+    let mut response = client.send(build_request)?;
 
     // Starting in Feburary, 2021, the upload endpoint performs CSRF challenges.
     // If we receive an HTTP 403 with a X-CSRF-Token reply, we should retry the
@@ -144,7 +467,9 @@ fn do_upload(buffer: Vec<u8>, asset_id: u64, cookie: &str) -> anyhow::Result<()>
     if response.status() == StatusCode::FORBIDDEN {
         if let Some(csrf_token) = response.headers().get("X-CSRF-Token") {
             log::debug!("Received CSRF challenge, retrying with token...");
-            response = build_request().header("X-CSRF-Token", csrf_token).send()?;
+            let csrf_token = csrf_token.clone();
+            response = client
+                .send(|client| build_request(client).header("X-CSRF-Token", csrf_token.clone()))?;
         }
     }
 
@@ -159,37 +484,731 @@ fn do_upload(buffer: Vec<u8>, asset_id: u64, cookie: &str) -> anyhow::Result<()>
     Ok(())
 }
 
-/// Implementation of do_upload that supports the new open cloud api.
+/// Uploads assets with the Open Cloud API.
 /// see https://developer.roblox.com/en-us/articles/open-cloud
-fn do_upload_open_cloud(
-    buffer: Vec<u8>,
-    universe_id: u64,
-    asset_id: u64,
-    api_key: &str,
-) -> anyhow::Result<()> {
-    let url = format!(
-        "https://apis.roblox.com/universes/v1/{}/places/{}/versions?versionType=Published",
-        universe_id, asset_id
-    );
+struct OpenCloud {
+    client: RetryingClient,
+    base_url: String,
+    api_key: HeaderValue,
+    poll_interval: Duration,
+}
+
+impl OpenCloud {
+    fn new(client: RetryingClient, api_key: &Secret) -> anyhow::Result<Self> {
+        Ok(OpenCloud {
+            client,
+            base_url: OPEN_CLOUD_URL.to_owned(),
+            api_key: secret_header(api_key.expose())?,
+            poll_interval: OPERATION_POLL_INTERVAL,
+        })
+    }
 
-    let client = reqwest::Client::new();
+    fn upload_place(
+        &self,
+        buffer: Vec<u8>,
+        universe_id: u64,
+        place_id: u64,
+        version_type: VersionType,
+    ) -> anyhow::Result<()> {
+        let url = format!(
+            "{}/universes/v1/{}/places/{}/versions?versionType={}",
+            self.base_url,
+            universe_id,
+            place_id,
+            version_type.as_str()
+        );
 
-    log::debug!("Uploading to Roblox...");
-    let mut response = client
-        .post(&url)
-        .header("x-api-key", api_key)
-        .header(CONTENT_TYPE, "application/xml")
-        .header(ACCEPT, "application/json")
-        .body(buffer)
-        .send()?;
+        log::debug!("Uploading to Roblox...");
+        let response = self.client.send(|client| {
+            client
+                .post(&url)
+                .header("x-api-key", self.api_key.clone())
+                .header(CONTENT_TYPE, "application/xml")
+                .header(ACCEPT, "application/json")
+                .body(buffer.clone())
+        })?;
+
+        let version: PlaceVersion = parse_response(response)?;
+        log::info!(
+            "Uploaded version {} of place {}",
+            version.version_number,
+            place_id
+        );
+
+        Ok(())
+    }
+
+    /// Uploads a new version of an existing model. Roblox processes models
+    /// after they're uploaded, so this waits until that's done.
+    fn upload_model(&self, buffer: Vec<u8>, asset_id: u64) -> anyhow::Result<()> {
+        let url = format!("{}/assets/v1/assets/{}", self.base_url, asset_id);
+
+        let request = serde_json::json!({ "assetId": asset_id }).to_string();
+
+        log::debug!("Uploading to Roblox...");
+        let response = self.client.send(|client| {
+            // Forms can't be reused, so every attempt needs a new one.
+            let file = Part::bytes(buffer.clone())
+                .file_name("model.rbxm")
+                .mime_str("model/x-rbxm")
+                .expect("model/x-rbxm is a valid MIME type");
+            let form = Form::new()
+                .text("request", request.clone())
+                .part("fileContent", file);
+
+            client
+                .patch(&url)
+                .header("x-api-key", self.api_key.clone())
+                .header(ACCEPT, "application/json")
+                .multipart(form)
+        })?;
+
+        let operation = self.wait_for_operation(parse_response(response)?)?;
+
+        if let Some(error) = operation.error {
+            bail!("Roblox could not process the model: {}", error.message);
+        }
+
+        if let Some(response) = operation.response {
+            log::info!(
+                "Uploaded revision {} of model {}",
+                response.revision_id.as_deref().unwrap_or("(unknown)"),
+                asset_id
+            );
+        }
+
+        Ok(())
+    }
+
+    fn wait_for_operation(&self, mut operation: Operation) -> anyhow::Result<Operation> {
+        let url = format!("{}/assets/v1/{}", self.base_url, operation.path);
+        let mut attempts = 0;
+
+        while !operation.done {
+            if attempts == OPERATION_POLL_ATTEMPTS {
+                bail!(
+                    "Timed out waiting for Roblox to process the upload ({})",
+                    operation.path
+                );
+            }
+
+            attempts += 1;
+            thread::sleep(self.poll_interval);
+
+            log::debug!("Waiting for Roblox to process the upload...");
+            let response = self.client.send(|client| {
+                client
+                    .get(&url)
+                    .header("x-api-key", self.api_key.clone())
+                    .header(ACCEPT, "application/json")
+            })?;
+
+            operation = parse_response(response)?;
+        }
+
+        Ok(operation)
+    }
+}
+
+/// Creates a header for a secret value, like an API key. Sensitive headers are
+/// left out when requests are logged.
+fn secret_header(value: &str) -> anyhow::Result<HeaderValue> {
+    let mut header = HeaderValue::from_str(value)
+        .context("Credentials can only contain visible ASCII characters")?;
+    header.set_sensitive(true);
+
+    Ok(header)
+}
+
+/// Sends requests to Roblox, retrying ones that fail because of rate limits,
+/// server errors, or network problems. Requests that change something are only
+/// retried when Roblox responds, since after a network problem we can't know
+/// whether the first one went through.
+struct RetryingClient {
+    client: reqwest::Client,
+    retries: u32,
+    base_delay: Duration,
+}
+
+impl RetryingClient {
+    fn new(timeout: Duration, retries: u32) -> anyhow::Result<Self> {
+        let client = reqwest::Client::builder()
+            .timeout(timeout)
+            .build()
+            .context("Could not create HTTP client")?;
+
+        Ok(RetryingClient {
+            client,
+            retries,
+            base_delay: RETRY_BASE_DELAY,
+        })
+    }
+
+    /// Sends the request made by `build_request`, which is called again for
+    /// each retry. If every retry fails, the last response or error is
+    /// returned.
+    fn send<F>(&self, build_request: F) -> anyhow::Result<Response>
+    where
+        F: Fn(&reqwest::Client) -> RequestBuilder,
+    {
+        let mut attempt = 0;
 
+        loop {
+            let request = build_request(&self.client).build()?;
+            let idempotent = request.method() == Method::GET;
+            let result = self.client.execute(request);
+
+            let delay = match &result {
+                Ok(response) if should_retry(response.status()) => {
+                    Some(retry_after(response).unwrap_or_else(|| self.backoff_delay(attempt)))
+                }
+                Err(err) if idempotent && (err.is_timeout() || err.is_http()) => {
+                    Some(self.backoff_delay(attempt))
+                }
+                _ => None,
+            };
+
+            match delay {
+                Some(delay) if attempt < self.retries => {
+                    let reason = match &result {
+                        Ok(response) => response.status().to_string(),
+                        Err(err) => err.to_string(),
+                    };
+
+                    attempt += 1;
+                    log::warn!(
+                        "Request to Roblox failed ({}), retrying in {:?} (attempt {} of {})",
+                        reason,
+                        delay,
+                        attempt,
+                        self.retries
+                    );
+
+                    thread::sleep(delay);
+                }
+                _ => return Ok(result?),
+            }
+        }
+    }
+
+    fn backoff_delay(&self, attempt: u32) -> Duration {
+        let delay = self.base_delay * 2u32.saturating_pow(attempt);
+        delay.min(MAX_RETRY_DELAY)
+    }
+}
+
+/// Rate limits and server errors are usually temporary, while other errors
+/// will fail the same way every time.
+fn should_retry(status: StatusCode) -> bool {
+    status == StatusCode::TOO_MANY_REQUESTS || status.is_server_error()
+}
+
+/// Reads how long the server asked us to wait before retrying, if it did.
+fn retry_after(response: &Response) -> Option<Duration> {
+    let value = response.headers().get(RETRY_AFTER)?.to_str().ok()?;
+    parse_retry_after(value)
+}
+
+/// Retry-After can also be an HTTP date, but Roblox only sends a number of
+/// seconds.
+fn parse_retry_after(value: &str) -> Option<Duration> {
+    value.trim().parse().ok().map(Duration::from_secs)
+}
+
+fn parse_response<T: DeserializeOwned>(mut response: Response) -> anyhow::Result<T> {
     let status = response.status();
+    let body = response.text()?;
+
     if !status.is_success() {
-        bail!(
-            "The Roblox API returned an unexpected error: {}",
-            response.text()?
+        return Err(ApiError::new(status, &body).into());
+    }
+
+    serde_json::from_str(&body)
+        .with_context(|| format!("The Roblox API returned an unexpected response: {}", body))
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct PlaceVersion {
+    version_number: u64,
+}
+
+/// A long-running task started by an Open Cloud API, like processing an
+/// uploaded asset.
+#[derive(Debug, Deserialize)]
+struct Operation {
+    path: String,
+
+    #[serde(default)]
+    done: bool,
+
+    error: Option<OperationError>,
+    response: Option<AssetVersion>,
+}
+
+#[derive(Debug, Deserialize)]
+struct OperationError {
+    #[serde(default)]
+    message: String,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct AssetVersion {
+    revision_id: Option<String>,
+}
+
+/// An error response from an Open Cloud API.
+#[derive(Debug)]
+struct ApiError {
+    status: StatusCode,
+    code: Option<String>,
+    message: String,
+}
+
+impl ApiError {
+    fn new(status: StatusCode, body: &str) -> Self {
+        // Different APIs name the error code field differently.
+        #[derive(Deserialize)]
+        struct ErrorBody {
+            #[serde(alias = "error")]
+            code: Option<String>,
+            message: Option<String>,
+        }
+
+        match serde_json::from_str::<ErrorBody>(body) {
+            Ok(ErrorBody {
+                code,
+                message: Some(message),
+            }) => ApiError {
+                status,
+                code,
+                message,
+            },
+            _ => ApiError {
+                status,
+                code: None,
+                message: body.to_owned(),
+            },
+        }
+    }
+}
+
+impl fmt::Display for ApiError {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            formatter,
+            "The Roblox API returned an error ({}): ",
+            self.status
+        )?;
+
+        if let Some(code) = &self.code {
+            write!(formatter, "{}: ", code)?;
+        }
+
+        write!(formatter, "{}", self.message)
+    }
+}
+
+impl std::error::Error for ApiError {}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use std::{
+        io::{BufRead, BufReader, Read, Write},
+        net::TcpListener,
+    };
+
+    use crate::deploy_manifest::DeployPlace;
+
+    /// A request received by `mock_server`, with the body decoded lossily so
+    /// that it's easy to check.
+    struct MockRequest {
+        line: String,
+        body: String,
+    }
+
+    /// Starts an HTTP server that answers one request with each of the given
+    /// responses, in order. Returns the URL of the server and a handle that
+    /// gives back the requests the server received.
+    ///
+    /// Responses with status 429 ask to be retried right away, and responses
+    /// with status 0 close the connection without answering.
+    fn mock_server(
+        responses: Vec<(u16, &'static str)>,
+    ) -> (String, thread::JoinHandle<Vec<MockRequest>>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+
+        let handle = thread::spawn(move || {
+            let mut requests = Vec::new();
+
+            for (status, body) in responses {
+                let (stream, _) = listener.accept().unwrap();
+                let mut reader = BufReader::new(stream);
+
+                let mut line = String::new();
+                reader.read_line(&mut line).unwrap();
+
+                let mut content_length = 0;
+                loop {
+                    let mut header = String::new();
+                    reader.read_line(&mut header).unwrap();
+
+                    let header = header.trim_end();
+                    if header.is_empty() {
+                        break;
+                    }
+
+                    let mut pieces = header.splitn(2, ':');
+                    let name = pieces.next().unwrap();
+                    if name.eq_ignore_ascii_case("content-length") {
+                        content_length = pieces.next().unwrap().trim().parse().unwrap();
+                    }
+                }
+
+                let mut request_body = vec![0; content_length];
+                reader.read_exact(&mut request_body).unwrap();
+
+                requests.push(MockRequest {
+                    line: line.trim_end().to_owned(),
+                    body: String::from_utf8_lossy(&request_body).into_owned(),
+                });
+
+                if status == 0 {
+                    continue;
+                }
+
+                let retry_after = if status == 429 {
+                    "Retry-After: 0\r\n"
+                } else {
+                    ""
+                };
+
+                let response = format!(
+                    "HTTP/1.1 {} Mock\r\nContent-Type: application/json\r\n{}\
+                     Content-Length: {}\r\nConnection: close\r\n\r\n{}",
+                    status,
+                    retry_after,
+                    body.len(),
+                    body
+                );
+
+                let mut stream = reader.into_inner();
+                stream.write_all(response.as_bytes()).unwrap();
+            }
+
+            requests
+        });
+
+        (url, handle)
+    }
+
+    fn open_cloud(base_url: String, retries: u32) -> OpenCloud {
+        let client = RetryingClient {
+            base_delay: Duration::from_millis(10),
+            ..RetryingClient::new(Duration::from_secs(10), retries).unwrap()
+        };
+
+        OpenCloud {
+            base_url,
+            poll_interval: Duration::from_millis(10),
+            ..OpenCloud::new(client, &Secret::new("test-key")).unwrap()
+        }
+    }
+
+    #[test]
+    fn upload_model_waits_for_operation() {
+        let (url, server) = mock_server(vec![
+            (200, r#"{"path": "operations/abc", "done": false}"#),
+            (200, r#"{"path": "operations/abc"}"#),
+            (
+                200,
+                r#"{"path": "operations/abc", "done": true, "response": {"assetId": "123", "revisionId": "4"}}"#,
+            ),
+        ]);
+
+        open_cloud(url, 0)
+            .upload_model(b"model contents".to_vec(), 123)
+            .unwrap();
+
+        let requests = server.join().unwrap();
+        let lines: Vec<_> = requests
+            .iter()
+            .map(|request| request.line.as_str())
+            .collect();
+
+        assert_eq!(
+            lines,
+            [
+                "PATCH /assets/v1/assets/123 HTTP/1.1",
+                "GET /assets/v1/operations/abc HTTP/1.1",
+                "GET /assets/v1/operations/abc HTTP/1.1",
+            ]
         );
+
+        let body = &requests[0].body;
+        assert!(body.contains(r#"{"assetId":123}"#));
+        assert!(body.contains("Content-Type: model/x-rbxm"));
+        assert!(body.contains("model contents"));
     }
 
-    Ok(())
+    #[test]
+    fn upload_model_operation_error() {
+        let (url, server) = mock_server(vec![(
+            200,
+            r#"{"path": "operations/abc", "done": true, "error": {"code": 3, "message": "Model is not valid"}}"#,
+        )]);
+
+        let err = open_cloud(url, 0)
+            .upload_model(Vec::new(), 123)
+            .unwrap_err();
+        server.join().unwrap();
+
+        assert_eq!(
+            err.to_string(),
+            "Roblox could not process the model: Model is not valid"
+        );
+    }
+
+    #[test]
+    fn upload_place_version_type() {
+        let (url, server) = mock_server(vec![(200, r#"{"versionNumber": 7}"#)]);
+
+        open_cloud(url, 0)
+            .upload_place(Vec::new(), 1, 2, VersionType::Saved)
+            .unwrap();
+
+        let requests = server.join().unwrap();
+        assert_eq!(
+            requests[0].line,
+            "POST /universes/v1/1/places/2/versions?versionType=Saved HTTP/1.1"
+        );
+    }
+
+    #[test]
+    fn structured_api_errors() {
+        let (url, server) = mock_server(vec![
+            (
+                403,
+                r#"{"code": "INSUFFICIENT_SCOPE", "message": "The API key cannot access this asset"}"#,
+            ),
+            (
+                400,
+                r#"{"error": "INVALID_ARGUMENT", "message": "Invalid place file", "errorDetails": []}"#,
+            ),
+            (500, "Something went wrong"),
+        ]);
+
+        let open_cloud = open_cloud(url, 0);
+
+        let model_err = open_cloud.upload_model(Vec::new(), 123).unwrap_err();
+        let place_err = open_cloud
+            .upload_place(Vec::new(), 1, 2, VersionType::Published)
+            .unwrap_err();
+        let unknown_err = open_cloud.upload_model(Vec::new(), 123).unwrap_err();
+        server.join().unwrap();
+
+        assert_eq!(
+            model_err.to_string(),
+            "The Roblox API returned an error (403 Forbidden): \
+             INSUFFICIENT_SCOPE: The API key cannot access this asset"
+        );
+        assert_eq!(
+            place_err.to_string(),
+            "The Roblox API returned an error (400 Bad Request): \
+             INVALID_ARGUMENT: Invalid place file"
+        );
+        assert_eq!(
+            unknown_err.to_string(),
+            "The Roblox API returned an error (500 Internal Server Error): Something went wrong"
+        );
+    }
+
+    #[test]
+    fn retries_failed_requests() {
+        let (url, server) = mock_server(vec![
+            (
+                429,
+                r#"{"code": "RESOURCE_EXHAUSTED", "message": "Too many requests"}"#,
+            ),
+            (503, "Service Unavailable"),
+            (200, r#"{"versionNumber": 7}"#),
+        ]);
+
+        open_cloud(url, 2)
+            .upload_place(Vec::new(), 1, 2, VersionType::Published)
+            .unwrap();
+
+        let requests = server.join().unwrap();
+        assert_eq!(requests.len(), 3);
+    }
+
+    #[test]
+    fn retries_run_out() {
+        let (url, server) = mock_server(vec![(500, "Something went wrong"), (502, "Bad Gateway")]);
+
+        let err = open_cloud(url, 1)
+            .upload_place(Vec::new(), 1, 2, VersionType::Published)
+            .unwrap_err();
+        server.join().unwrap();
+
+        assert_eq!(
+            err.to_string(),
+            "The Roblox API returned an error (502 Bad Gateway): Bad Gateway"
+        );
+    }
+
+    #[test]
+    fn publish_not_retried_after_network_error() {
+        let (url, server) = mock_server(vec![(0, ""), (200, r#"{"versionNumber": 7}"#)]);
+
+        open_cloud(url.clone(), 2)
+            .upload_place(Vec::new(), 1, 2, VersionType::Published)
+            .unwrap_err();
+
+        // Take the response that a retry would have gotten ourselves.
+        reqwest::get(&url).unwrap();
+
+        let requests = server.join().unwrap();
+        assert!(requests[0].line.starts_with("POST"));
+        assert!(requests[1].line.starts_with("GET / "));
+    }
+
+    #[test]
+    fn polling_retried_after_network_error() {
+        let (url, server) = mock_server(vec![
+            (200, r#"{"path": "operations/abc", "done": false}"#),
+            (0, ""),
+            (
+                200,
+                r#"{"path": "operations/abc", "done": true, "response": {"assetId": "123", "revisionId": "4"}}"#,
+            ),
+        ]);
+
+        open_cloud(url, 1).upload_model(Vec::new(), 123).unwrap();
+
+        let requests = server.join().unwrap();
+        assert_eq!(requests.len(), 3);
+    }
+
+    #[test]
+    fn retry_after() {
+        assert_eq!(parse_retry_after("5"), Some(Duration::from_secs(5)));
+        assert_eq!(parse_retry_after(" 0 "), Some(Duration::from_secs(0)));
+        assert_eq!(parse_retry_after("Wed, 21 Oct 2015 07:28:00 GMT"), None);
+    }
+
+    #[test]
+    fn dry_run_writes_output() {
+        let dir = tempfile::tempdir().unwrap();
+        let output = dir.path().join("upload.rbxm");
+
+        fs::write(
+            dir.path().join("default.project.json"),
+            r#"{"name": "model", "tree": {"$className": "Folder"}}"#,
+        )
+        .unwrap();
+
+        UploadCommand {
+            project: dir.path().to_path_buf(),
+            kind: UploadKind::Model,
+            profile: None,
+            cookie: None,
+            api_key: None,
+            credentials_profile: None,
+            universe_id: None,
+            version_type: VersionType::Published,
+            asset_id: Some(123),
+            manifest: None,
+            env: None,
+            retries: 0,
+            timeout: 1,
+            dry_run: true,
+            output: Some(output.clone()),
+        }
+        .run()
+        .unwrap();
+
+        let dom = rbx_binary::from_reader(fs::File::open(output).unwrap()).unwrap();
+        let root = dom.get_by_ref(dom.root().children()[0]).unwrap();
+        assert_eq!(root.name, "model");
+        assert_eq!(root.class, "Folder");
+    }
+
+    #[test]
+    fn deploy_failure_policy() {
+        let dir = tempfile::tempdir().unwrap();
+
+        for name in &["first", "second"] {
+            let project_dir = dir.path().join(name);
+            fs::create_dir(&project_dir).unwrap();
+            fs::write(
+                project_dir.join("default.project.json"),
+                r#"{"name": "place", "tree": {"$className": "DataModel"}}"#,
+            )
+            .unwrap();
+        }
+
+        let place = |name: &str, place_id| DeployPlace {
+            name: Some(name.to_owned()),
+            project: dir.path().join(name),
+            environment: "staging".to_owned(),
+            profile: None,
+            universe_id: 1,
+            place_id,
+        };
+
+        let mut manifest = DeployManifest {
+            on_failure: FailurePolicy::Stop,
+            places: vec![place("first", 10), place("second", 20)],
+            file_location: dir.path().join("rojo-deploy.json"),
+        };
+
+        let (url, server) = mock_server(vec![(
+            400,
+            r#"{"code": "INVALID_ARGUMENT", "message": "Bad place"}"#,
+        )]);
+        let uploader = Uploader::OpenCloud(open_cloud(url, 0));
+
+        let err = deploy(
+            &manifest,
+            "staging",
+            None,
+            Some(&uploader),
+            VersionType::Published,
+        )
+        .unwrap_err();
+        assert_eq!(err.to_string(), "Could not upload first");
+        assert_eq!(server.join().unwrap().len(), 1);
+
+        manifest.on_failure = FailurePolicy::Continue;
+
+        let (url, server) = mock_server(vec![
+            (
+                400,
+                r#"{"code": "INVALID_ARGUMENT", "message": "Bad place"}"#,
+            ),
+            (200, r#"{"versionNumber": 3}"#),
+        ]);
+        let uploader = Uploader::OpenCloud(open_cloud(url, 0));
+
+        let err = deploy(
+            &manifest,
+            "staging",
+            None,
+            Some(&uploader),
+            VersionType::Published,
+        )
+        .unwrap_err();
+        assert_eq!(err.to_string(), "Could not upload first");
+
+        let requests = server.join().unwrap();
+        assert_eq!(
+            requests[1].line,
+            "POST /universes/v1/1/places/20/versions?versionType=Published HTTP/1.1"
+        );
+    }
 }
@@ -0,0 +1,292 @@
+//! Finds the credentials Rojo uses to upload to Roblox. Credentials passed on
+//! the command line are used first, then environment variables, then the
+//! user's credentials file.
+//!
+//! The credentials file is a TOML file in `~/.rojo/credentials.toml`, or
+//! wherever `ROJO_CREDENTIALS_FILE` points. Each table in it is a named
+//! profile:
+//!
+//! ```toml
+//! [default]
+//! api_key = "..."
+//!
+//! [work]
+//! api_key = "..."
+//! cookie = "..."
+//! ```
+
+use std::{
+    collections::HashMap,
+    convert::Infallible,
+    env, fmt, io,
+    path::{Path, PathBuf},
+    str::FromStr,
+};
+
+use anyhow::{bail, Context};
+use fs_err as fs;
+use serde::Deserialize;
+
+pub const API_KEY_VAR: &str = "ROJO_API_KEY";
+pub const COOKIE_VAR: &str = "ROJO_ROBLOX_COOKIE";
+pub const CREDENTIALS_FILE_VAR: &str = "ROJO_CREDENTIALS_FILE";
+
+/// The profile that's read from the credentials file when none is chosen.
+const DEFAULT_PROFILE: &str = "default";
+
+/// A value that needs to stay out of logs and error messages, like an API key.
+/// Formatting it with `{:?}` never shows the value.
+#[derive(Clone, PartialEq, Eq, Deserialize)]
+#[serde(transparent)]
+pub struct Secret(String);
+
+impl Secret {
+    pub fn new(value: impl Into<String>) -> Self {
+        Secret(value.into())
+    }
+
+    pub fn expose(&self) -> &str {
+        &self.0
+    }
+}
+
+impl fmt::Debug for Secret {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        write!(formatter, "Secret(<redacted>)")
+    }
+}
+
+impl FromStr for Secret {
+    type Err = Infallible;
+
+    fn from_str(source: &str) -> Result<Self, Self::Err> {
+        Ok(Secret::new(source))
+    }
+}
+
+#[derive(Debug, Default, Clone, PartialEq, Eq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Credentials {
+    pub api_key: Option<Secret>,
+    pub cookie: Option<Secret>,
+}
+
+impl Credentials {
+    /// Fills in any credentials that weren't given on the command line from
+    /// environment variables and the credentials file.
+    ///
+    /// The credentials file is only read once no API key has been found, since
+    /// an API key is all that uploading needs, or when `profile` is given. If
+    /// `profile` is given, it must exist in the credentials file. Otherwise,
+    /// the default profile is used if there is one.
+    pub fn find(self, profile: Option<&str>) -> anyhow::Result<Credentials> {
+        let file_path = match env::var_os(CREDENTIALS_FILE_VAR) {
+            Some(path) => Some(PathBuf::from(path)),
+            None => dirs::home_dir().map(|home| home.join(".rojo").join("credentials.toml")),
+        };
+
+        self.find_with(profile, |name| env::var(name).ok(), file_path.as_deref())
+    }
+
+    fn find_with(
+        self,
+        profile: Option<&str>,
+        var: impl Fn(&str) -> Option<String>,
+        file_path: Option<&Path>,
+    ) -> anyhow::Result<Credentials> {
+        let secret_var = |name| var(name).filter(|value| !value.is_empty()).map(Secret);
+
+        let from_env = Credentials {
+            api_key: secret_var(API_KEY_VAR),
+            cookie: secret_var(COOKIE_VAR),
+        };
+
+        let mut credentials = self.or(from_env);
+
+        if credentials.api_key.is_none() || profile.is_some() {
+            credentials = credentials.or(read_profile(file_path, profile)?);
+        }
+
+        Ok(credentials)
+    }
+
+    fn or(self, other: Credentials) -> Credentials {
+        Credentials {
+            api_key: self.api_key.or(other.api_key),
+            cookie: self.cookie.or(other.cookie),
+        }
+    }
+}
+
+fn read_profile(file_path: Option<&Path>, profile: Option<&str>) -> anyhow::Result<Credentials> {
+    let name = profile.unwrap_or(DEFAULT_PROFILE);
+
+    let file_path = match file_path {
+        Some(path) => path,
+        None if profile.is_some() => bail!(
+            "Could not find your home directory to read credentials profile '{}'. \
+             Set {} to the path of your credentials file.",
+            name,
+            CREDENTIALS_FILE_VAR
+        ),
+        None => return Ok(Credentials::default()),
+    };
+
+    let contents = match fs::read_to_string(file_path) {
+        Ok(contents) => contents,
+        Err(err) if err.kind() == io::ErrorKind::NotFound && profile.is_none() => {
+            return Ok(Credentials::default());
+        }
+        Err(err) => return Err(err.into()),
+    };
+
+    let mut profiles: HashMap<String, Credentials> = toml::from_str(&contents)
+        .with_context(|| format!("Malformed credentials file: {}", file_path.display()))?;
+
+    match profiles.remove(name) {
+        Some(credentials) => {
+            log::debug!(
+                "Using credentials profile '{}' from {}",
+                name,
+                file_path.display()
+            );
+            Ok(credentials)
+        }
+        None if profile.is_none() => Ok(Credentials::default()),
+        None => bail!(
+            "Credentials profile '{}' was not found in {}",
+            name,
+            file_path.display()
+        ),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const CREDENTIALS_FILE: &str = r#"
+        [default]
+        api_key = "default-key"
+
+        [work]
+        api_key = "work-key"
+        cookie = "work-cookie"
+    "#;
+
+    fn no_vars(_name: &str) -> Option<String> {
+        None
+    }
+
+    fn write_credentials_file() -> (tempfile::TempDir, PathBuf) {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("credentials.toml");
+        fs::write(&path, CREDENTIALS_FILE).unwrap();
+
+        (dir, path)
+    }
+
+    #[test]
+    fn precedence() {
+        let (_dir, path) = write_credentials_file();
+
+        let vars = |name: &str| match name {
+            API_KEY_VAR => Some("env-key".to_owned()),
+            COOKIE_VAR => Some("env-cookie".to_owned()),
+            _ => None,
+        };
+
+        let from_cli = Credentials {
+            api_key: Some(Secret::new("cli-key")),
+            cookie: None,
+        };
+
+        let credentials = from_cli.find_with(None, vars, Some(&path)).unwrap();
+        assert_eq!(credentials.api_key, Some(Secret::new("cli-key")));
+        assert_eq!(credentials.cookie, Some(Secret::new("env-cookie")));
+
+        let credentials = Credentials::default()
+            .find_with(None, no_vars, Some(&path))
+            .unwrap();
+        assert_eq!(credentials.api_key, Some(Secret::new("default-key")));
+        assert_eq!(credentials.cookie, None);
+    }
+
+    #[test]
+    fn empty_vars_are_ignored() {
+        let vars = |name: &str| match name {
+            API_KEY_VAR => Some(String::new()),
+            _ => None,
+        };
+
+        let credentials = Credentials::default().find_with(None, vars, None).unwrap();
+        assert_eq!(credentials, Credentials::default());
+    }
+
+    #[test]
+    fn named_profile() {
+        let (_dir, path) = write_credentials_file();
+
+        let credentials = Credentials::default()
+            .find_with(Some("work"), no_vars, Some(&path))
+            .unwrap();
+        assert_eq!(credentials.api_key, Some(Secret::new("work-key")));
+        assert_eq!(credentials.cookie, Some(Secret::new("work-cookie")));
+
+        let err = Credentials::default()
+            .find_with(Some("home"), no_vars, Some(&path))
+            .unwrap_err();
+        assert!(err
+            .to_string()
+            .starts_with("Credentials profile 'home' was not found in"));
+    }
+
+    #[test]
+    fn missing_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("credentials.toml");
+
+        let credentials = Credentials::default()
+            .find_with(None, no_vars, Some(&path))
+            .unwrap();
+        assert_eq!(credentials, Credentials::default());
+
+        assert!(Credentials::default()
+            .find_with(Some("work"), no_vars, Some(&path))
+            .is_err());
+    }
+
+    #[test]
+    fn file_unused_with_api_key() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("credentials.toml");
+        fs::write(&path, "not a credentials file").unwrap();
+
+        let vars = |name: &str| match name {
+            API_KEY_VAR => Some("env-key".to_owned()),
+            _ => None,
+        };
+
+        let credentials = Credentials::default()
+            .find_with(None, vars, Some(&path))
+            .unwrap();
+        assert_eq!(credentials.api_key, Some(Secret::new("env-key")));
+        assert_eq!(credentials.cookie, None);
+
+        assert!(Credentials::default()
+            .find_with(None, no_vars, Some(&path))
+            .is_err());
+    }
+
+    #[test]
+    fn secrets_are_redacted() {
+        let credentials = Credentials {
+            api_key: Some(Secret::new("very-secret")),
+            cookie: None,
+        };
+
+        let formatted = format!("{:?}", credentials);
+        assert!(!formatted.contains("very-secret"));
+        assert!(formatted.contains("<redacted>"));
+    }
+}
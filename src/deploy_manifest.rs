@@ -0,0 +1,170 @@
+//! Defines deploy manifests, which list every place in an experience so that
+//! `rojo upload --manifest` can build and upload all of them at once.
+
+use std::path::{Path, PathBuf};
+
+use anyhow::Context;
+use fs_err as fs;
+use serde::Deserialize;
+
+/// Describes the places that make up an experience and where each of them
+/// should be uploaded. Deploy manifests are usually stored in
+/// `rojo-deploy.json` files.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields, rename_all = "camelCase")]
+pub struct DeployManifest {
+    /// What to do after a place fails to build or upload.
+    #[serde(default)]
+    pub on_failure: FailurePolicy,
+
+    /// Every place that can be deployed, across all environments.
+    pub places: Vec<DeployPlace>,
+
+    /// The path to the file that this manifest was loaded from.
+    #[serde(skip)]
+    pub file_location: PathBuf,
+}
+
+impl DeployManifest {
+    pub fn load(manifest_file_location: &Path) -> anyhow::Result<Self> {
+        let contents = fs::read(manifest_file_location)?;
+
+        let mut manifest: DeployManifest =
+            serde_json::from_slice(&contents).with_context(|| {
+                format!(
+                    "Error parsing deploy manifest in path {}",
+                    manifest_file_location.display()
+                )
+            })?;
+
+        // Project paths are relative to the manifest, not wherever Rojo
+        // happens to be running.
+        let folder = manifest_file_location.parent().unwrap();
+        for place in &mut manifest.places {
+            if place.name.is_none() {
+                place.name = Some(place.project.display().to_string());
+            }
+
+            place.project = folder.join(&place.project);
+        }
+
+        manifest.file_location = manifest_file_location.to_path_buf();
+
+        Ok(manifest)
+    }
+
+    /// Returns the places that belong to the given environment, in the order
+    /// they're listed in the manifest.
+    pub fn places_in<'a>(&'a self, environment: &'a str) -> impl Iterator<Item = &'a DeployPlace> {
+        self.places
+            .iter()
+            .filter(move |place| place.environment == environment)
+    }
+}
+
+/// A project that gets built and uploaded to one place.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields, rename_all = "camelCase")]
+pub struct DeployPlace {
+    /// A name to show in output. Defaults to the project path.
+    pub name: Option<String>,
+
+    /// The project to build, relative to the manifest.
+    pub project: PathBuf,
+
+    /// Which environment this place belongs to, like `staging` or
+    /// `production`.
+    pub environment: String,
+
+    /// The project profile to build this place with, if any. Overrides
+    /// `--profile`.
+    pub profile: Option<String>,
+
+    pub universe_id: u64,
+    pub place_id: u64,
+}
+
+impl DeployPlace {
+    pub fn display_name(&self) -> String {
+        match &self.name {
+            Some(name) => name.clone(),
+            None => self.project.display().to_string(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum FailurePolicy {
+    /// Don't upload any more places after one fails.
+    Stop,
+
+    /// Keep uploading the rest of the places after one fails.
+    Continue,
+}
+
+// Deriving Default for enums needs a newer Rust than our minimum version.
+#[allow(clippy::derivable_impls)]
+impl Default for FailurePolicy {
+    fn default() -> Self {
+        FailurePolicy::Stop
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn load_manifest() {
+        let dir = tempfile::tempdir().unwrap();
+        let manifest_path = dir.path().join("rojo-deploy.json");
+
+        fs::write(
+            &manifest_path,
+            r#"{
+                "onFailure": "continue",
+                "places": [
+                    {
+                        "name": "Lobby",
+                        "project": "lobby.project.json",
+                        "environment": "staging",
+                        "universeId": 1,
+                        "placeId": 10
+                    },
+                    {
+                        "project": "places/shop",
+                        "environment": "production",
+                        "universeId": 2,
+                        "placeId": 20
+                    },
+                    {
+                        "project": "places/shop",
+                        "environment": "staging",
+                        "universeId": 1,
+                        "placeId": 11
+                    }
+                ]
+            }"#,
+        )
+        .unwrap();
+
+        let manifest = DeployManifest::load(&manifest_path).unwrap();
+        assert_eq!(manifest.on_failure, FailurePolicy::Continue);
+
+        let staging: Vec<_> = manifest.places_in("staging").collect();
+        assert_eq!(staging.len(), 2);
+        assert_eq!(staging[0].display_name(), "Lobby");
+        assert_eq!(staging[0].project, dir.path().join("lobby.project.json"));
+        assert_eq!(staging[1].display_name(), "places/shop");
+        assert_eq!(staging[1].place_id, 11);
+
+        assert_eq!(manifest.places_in("testing").count(), 0);
+    }
+
+    #[test]
+    fn default_failure_policy() {
+        let manifest: DeployManifest = serde_json::from_str(r#"{"places": []}"#).unwrap();
+        assert_eq!(manifest.on_failure, FailurePolicy::Stop);
+    }
+}
@@ -9,6 +9,8 @@ mod tree_view;
 
 mod auth_cookie;
 mod change_processor;
+mod credentials;
+mod deploy_manifest;
 mod glob;
 mod lua_ast;
 mod message_queue;
@@ -20,6 +22,7 @@ mod serve_session;
 mod session_id;
 mod snapshot;
 mod snapshot_middleware;
+mod syncback;
 mod web;
 
 pub use project::*;
@@ -1,5 +1,6 @@
 //! Defines module for defining a small Lua AST for simple codegen. Rojo uses
-//! this module to convert JSON into generated Lua code.
+//! this module to convert JSON into generated Lua code, optionally with Luau
+//! type annotations.
 
 use std::{
     fmt::{self, Write},
@@ -34,8 +35,42 @@ where
     }
 }
 
+/// Generates the source of a ModuleScript that returns the given value.
+///
+/// If `typed` is set, the module is checked in strict mode and exports a type
+/// named `Data` that's inferred from the value, which is also what the module
+/// returns.
+pub(crate) fn data_module_source(value: Expression, typed: bool) -> String {
+    if !typed {
+        return Statement::Return(value).to_string();
+    }
+
+    let data_type = value.infer_type();
+
+    let statements = [
+        Statement::Directive("strict"),
+        Statement::ExportType("Data".to_owned(), data_type),
+        Statement::Return(Expression::Cast(
+            Box::new(value),
+            Type::Named("Data".to_owned()),
+        )),
+    ];
+
+    statements
+        .iter()
+        .map(|statement| statement.to_string())
+        .collect::<Vec<_>>()
+        .join("\n\n")
+}
+
 pub(crate) enum Statement {
     Return(Expression),
+
+    /// A directive for Luau's type checker, like `--!strict`.
+    Directive(&'static str),
+
+    /// Declares a type that can be used by scripts that require this one.
+    ExportType(String, Type),
 }
 
 impl FmtLua for Statement {
@@ -45,6 +80,11 @@ impl FmtLua for Statement {
                 write!(output, "return ")?;
                 literal.fmt_lua(output)
             }
+            Self::Directive(directive) => write!(output, "--!{}", directive),
+            Self::ExportType(name, ty) => {
+                write!(output, "export type {} = ", name)?;
+                ty.fmt_lua(output)
+            }
         }
     }
 }
@@ -66,12 +106,59 @@ pub(crate) enum Expression {
     /// Arrays are not technically distinct from other tables in Lua, but this
     /// representation is more convenient.
     Array(Vec<Expression>),
+
+    /// Asserts that an expression has the given type, like `value :: Type`.
+    Cast(Box<Expression>, Type),
 }
 
 impl Expression {
     pub fn table(entries: Vec<(Expression, Expression)>) -> Self {
         Self::Table(Table { entries })
     }
+
+    /// Infers the narrowest Luau type that describes this expression.
+    pub fn infer_type(&self) -> Type {
+        match self {
+            Self::Nil => Type::Nil,
+            Self::Bool(_) => Type::Boolean,
+            Self::Number(_) => Type::Number,
+            Self::String(_) => Type::String,
+            Self::Cast(_, ty) => ty.clone(),
+            Self::Array(values) => {
+                let mut types: Vec<Type> = Vec::new();
+
+                for value in values {
+                    let ty = value.infer_type();
+                    if !types.contains(&ty) {
+                        types.push(ty);
+                    }
+                }
+
+                let item_type = match types.len() {
+                    0 => Type::Any,
+                    1 => types.pop().unwrap(),
+                    _ => Type::Union(types),
+                };
+
+                Type::Array(Box::new(item_type))
+            }
+            Self::Table(table) => {
+                let mut fields = Vec::with_capacity(table.entries.len());
+
+                for (key, value) in &table.entries {
+                    match key {
+                        Self::String(key) => fields.push((key.clone(), value.infer_type())),
+
+                        // Types can only name fields with string keys, so
+                        // tables with other keys fall back to an indexer.
+                        _ => return Type::Map(Box::new(Type::Any), Box::new(Type::Any)),
+                    }
+                }
+
+                Type::Table(fields)
+            }
+        }
+    }
 }
 
 impl FmtLua for Expression {
@@ -83,6 +170,11 @@ impl FmtLua for Expression {
             Self::String(inner) => inner.fmt_lua(output),
             Self::Table(inner) => inner.fmt_lua(output),
             Self::Array(inner) => inner.fmt_lua(output),
+            Self::Cast(inner, ty) => {
+                inner.fmt_lua(output)?;
+                write!(output, " :: ")?;
+                ty.fmt_lua(output)
+            }
         }
     }
 
@@ -94,6 +186,79 @@ impl FmtLua for Expression {
             Self::String(inner) => inner.fmt_table_key(output),
             Self::Table(inner) => inner.fmt_table_key(output),
             Self::Array(inner) => inner.fmt_table_key(output),
+            Self::Cast(inner, _) => inner.fmt_table_key(output),
+        }
+    }
+}
+
+/// A Luau type annotation.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum Type {
+    Nil,
+    Boolean,
+    Number,
+    String,
+    Any,
+
+    /// Refers to a type declared elsewhere by name.
+    Named(String),
+
+    Array(Box<Type>),
+    Union(Vec<Type>),
+
+    /// A table with a known set of string keys.
+    Table(Vec<(String, Type)>),
+
+    /// A table with arbitrary keys of one type, like `{[string]: number}`.
+    Map(Box<Type>, Box<Type>),
+}
+
+impl FmtLua for Type {
+    fn fmt_lua(&self, output: &mut LuaStream<'_>) -> fmt::Result {
+        match self {
+            Self::Nil => write!(output, "nil"),
+            Self::Boolean => write!(output, "boolean"),
+            Self::Number => write!(output, "number"),
+            Self::String => write!(output, "string"),
+            Self::Any => write!(output, "any"),
+            Self::Named(name) => write!(output, "{}", name),
+            Self::Array(item) => {
+                write!(output, "{{")?;
+                item.fmt_lua(output)?;
+                write!(output, "}}")
+            }
+            Self::Union(types) => {
+                for (index, ty) in types.iter().enumerate() {
+                    if index > 0 {
+                        write!(output, " | ")?;
+                    }
+
+                    ty.fmt_lua(output)?;
+                }
+
+                Ok(())
+            }
+            Self::Table(fields) => {
+                writeln!(output, "{{")?;
+                output.indent();
+
+                for (key, ty) in fields {
+                    key.fmt_table_key(output)?;
+                    write!(output, ": ")?;
+                    ty.fmt_lua(output)?;
+                    writeln!(output, ",")?;
+                }
+
+                output.unindent();
+                write!(output, "}}")
+            }
+            Self::Map(key, value) => {
+                write!(output, "{{[")?;
+                key.fmt_lua(output)?;
+                write!(output, "]: ")?;
+                value.fmt_lua(output)?;
+                write!(output, "}}")
+            }
         }
     }
 }
@@ -324,4 +489,39 @@ mod test {
 
         assert_eq!(displayed, "\"\\\"\\r\\n\\t\\\\\"");
     }
+
+    #[test]
+    fn typed_data_module() {
+        let value = Expression::table(vec![
+            ("name".into(), "Sword".into()),
+            ("damage".into(), Expression::Number(25.0)),
+            ("end".into(), Expression::Bool(true)),
+            (
+                "tags".into(),
+                Expression::Array(vec!["a".into(), Expression::Number(1.0)]),
+            ),
+            ("empty".into(), Expression::Array(Vec::new())),
+        ]);
+
+        let expected = "\
+--!strict
+
+export type Data = {
+	name: string,
+	damage: number,
+	[\"end\"]: boolean,
+	tags: {string | number},
+	empty: {any},
+}
+
+return {
+	name = \"Sword\",
+	damage = 25,
+	[\"end\"] = true,
+	tags = {\"a\", 1},
+	empty = {},
+} :: Data";
+
+        assert_eq!(data_module_source(value, true), expected);
+    }
 }
@@ -1,53 +1,99 @@
-use std::sync::{Mutex, RwLock};
+use std::{
+    collections::VecDeque,
+    sync::{Mutex, RwLock},
+};
 
 use futures::channel::oneshot;
+use thiserror::Error;
+
+/// The number of messages a MessageQueue keeps around by default.
+pub const DEFAULT_HISTORY_LIMIT: usize = 1000;
+
+/// The result of subscribing to a MessageQueue: either the new cursor and all
+/// messages since the subscriber's cursor, or an error if some of those
+/// messages are no longer retained.
+pub type SubscribeResult<T> = Result<(u32, Vec<T>), CursorExpired>;
+
+/// Returned to subscribers whose cursor has fallen behind the history that the
+/// queue still has. They've missed messages and need to start over from the
+/// current state.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Error)]
+#[error(
+    "Message cursor {cursor} is too old, only messages from cursor {oldest_cursor} onward are kept"
+)]
+pub struct CursorExpired {
+    pub cursor: u32,
+    pub oldest_cursor: u32,
+}
 
-/// A message queue with persistent history that can be subscribed to.
+/// A message queue with bounded persistent history that can be subscribed to.
 ///
 /// Definitely non-optimal. This would ideally be a lockless mpmc queue.
-#[derive(Default)]
 pub struct MessageQueue<T> {
-    messages: RwLock<Vec<T>>,
+    history: RwLock<History<T>>,
     message_listeners: Mutex<Vec<Listener<T>>>,
 }
 
 impl<T: Clone> MessageQueue<T> {
     pub fn new() -> MessageQueue<T> {
+        Self::with_history_limit(DEFAULT_HISTORY_LIMIT)
+    }
+
+    /// Creates a MessageQueue that keeps at most `limit` messages in its
+    /// history. Cursors keep counting up as old messages are dropped.
+    pub fn with_history_limit(limit: usize) -> MessageQueue<T> {
         MessageQueue {
-            messages: RwLock::new(Vec::new()),
+            history: RwLock::new(History {
+                messages: VecDeque::new(),
+                first_cursor: 0,
+                limit,
+            }),
             message_listeners: Mutex::new(Vec::new()),
         }
     }
 
+    /// Changes how many messages are kept in the history, dropping the oldest
+    /// messages if there are too many.
+    pub fn set_history_limit(&self, limit: usize) {
+        let mut history = self.history.write().unwrap();
+        history.limit = limit;
+        history.trim();
+    }
+
     pub fn push_messages(&self, new_messages: &[T]) {
         let mut message_listeners = self.message_listeners.lock().unwrap();
-        let mut messages = self.messages.write().unwrap();
-        messages.extend_from_slice(new_messages);
+        let mut history = self.history.write().unwrap();
+        history.messages.extend(new_messages.iter().cloned());
 
         let mut remaining_listeners = Vec::new();
 
         for listener in message_listeners.drain(..) {
-            match fire_listener_if_ready(&messages, listener) {
+            match fire_listener_if_ready(&history, listener) {
                 Ok(_) => {}
                 Err(listener) => remaining_listeners.push(listener),
             }
         }
 
+        // Listeners are fired before trimming the history so that subscribers
+        // that were already waiting never miss messages, even if a single push
+        // is larger than the history limit.
+        history.trim();
+
         // Without this annotation, Rust gets confused since the first argument
         // is a MutexGuard, but the second is a Vec.
         *message_listeners = remaining_listeners;
     }
 
     /// Subscribe to any messages occurring after the given message cursor.
-    pub fn subscribe(&self, cursor: u32) -> oneshot::Receiver<(u32, Vec<T>)> {
+    pub fn subscribe(&self, cursor: u32) -> oneshot::Receiver<SubscribeResult<T>> {
         let (sender, receiver) = oneshot::channel();
 
         let listener = {
             let listener = Listener { sender, cursor };
 
-            let messages = self.messages.read().unwrap();
+            let history = self.history.read().unwrap();
 
-            match fire_listener_if_ready(&messages, listener) {
+            match fire_listener_if_ready(&history, listener) {
                 Ok(_) => return receiver,
                 Err(listener) => listener,
             }
@@ -64,36 +110,114 @@ impl<T: Clone> MessageQueue<T> {
     /// This method is only useful in tests. Non-test code should use subscribe
     /// instead.
     #[cfg(test)]
-    pub fn subscribe_any(&self) -> oneshot::Receiver<(u32, Vec<T>)> {
-        let cursor = {
-            let messages = self.messages.read().unwrap();
-            messages.len() as u32
-        };
-
-        self.subscribe(cursor)
+    pub fn subscribe_any(&self) -> oneshot::Receiver<SubscribeResult<T>> {
+        self.subscribe(self.cursor())
     }
 
     pub fn cursor(&self) -> u32 {
-        self.messages.read().unwrap().len() as u32
+        self.history.read().unwrap().cursor()
+    }
+}
+
+impl<T: Clone> Default for MessageQueue<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+struct History<T> {
+    /// The most recent messages, oldest first.
+    messages: VecDeque<T>,
+
+    /// The cursor of the first message in `messages`. This is the number of
+    /// messages that have been dropped from the history so far.
+    first_cursor: u32,
+
+    /// The most messages that `messages` should hold onto.
+    limit: usize,
+}
+
+impl<T> History<T> {
+    fn cursor(&self) -> u32 {
+        self.first_cursor + self.messages.len() as u32
+    }
+
+    fn trim(&mut self) {
+        let excess = self.messages.len().saturating_sub(self.limit);
+
+        self.messages.drain(..excess);
+        self.first_cursor += excess as u32;
     }
 }
 
 struct Listener<T> {
-    sender: oneshot::Sender<(u32, Vec<T>)>,
+    sender: oneshot::Sender<SubscribeResult<T>>,
     cursor: u32,
 }
 
 fn fire_listener_if_ready<T: Clone>(
-    messages: &[T],
+    history: &History<T>,
     listener: Listener<T>,
 ) -> Result<(), Listener<T>> {
-    let current_cursor = messages.len() as u32;
+    let current_cursor = history.cursor();
 
-    if listener.cursor < current_cursor {
-        let new_messages = messages[(listener.cursor as usize)..].to_vec();
-        let _ = listener.sender.send((current_cursor, new_messages));
+    if listener.cursor < history.first_cursor {
+        let _ = listener.sender.send(Err(CursorExpired {
+            cursor: listener.cursor,
+            oldest_cursor: history.first_cursor,
+        }));
+        Ok(())
+    } else if listener.cursor < current_cursor {
+        let start = (listener.cursor - history.first_cursor) as usize;
+        let new_messages = history.messages.iter().skip(start).cloned().collect();
+        let _ = listener.sender.send(Ok((current_cursor, new_messages)));
         Ok(())
     } else {
         Err(listener)
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn history_is_trimmed() {
+        let queue = MessageQueue::with_history_limit(2);
+        queue.push_messages(&[1, 2, 3]);
+
+        assert_eq!(queue.cursor(), 3);
+
+        let mut receiver = queue.subscribe(1);
+        assert_eq!(receiver.try_recv().unwrap(), Some(Ok((3, vec![2, 3]))));
+    }
+
+    #[test]
+    fn expired_cursor() {
+        let queue = MessageQueue::with_history_limit(2);
+        queue.push_messages(&[1, 2, 3]);
+
+        let mut receiver = queue.subscribe(0);
+        assert_eq!(
+            receiver.try_recv().unwrap(),
+            Some(Err(CursorExpired {
+                cursor: 0,
+                oldest_cursor: 1,
+            }))
+        );
+    }
+
+    #[test]
+    fn waiting_listeners_get_whole_push() {
+        let queue = MessageQueue::with_history_limit(2);
+        let mut receiver = queue.subscribe_any();
+
+        queue.push_messages(&[1, 2, 3, 4]);
+
+        assert_eq!(
+            receiver.try_recv().unwrap(),
+            Some(Ok((4, vec![1, 2, 3, 4])))
+        );
+        assert_eq!(queue.cursor(), 4);
+    }
+}
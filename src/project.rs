@@ -1,13 +1,18 @@
 use std::{
     collections::{BTreeMap, HashMap, HashSet},
     fs, io,
-    path::{Path, PathBuf},
+    path::{Component, Path, PathBuf},
 };
 
+use memofs::Vfs;
 use serde::{Deserialize, Serialize};
 use thiserror::Error;
 
-use crate::{glob::Glob, resolution::UnresolvedValue};
+use crate::{
+    glob::Glob,
+    resolution::UnresolvedValue,
+    snapshot::{PathIgnoreRule, SyncRule},
+};
 
 static PROJECT_FILENAME: &str = "default.project.json";
 
@@ -29,6 +34,29 @@ enum Error {
         source: serde_json::Error,
         path: PathBuf,
     },
+
+    #[error("Could not read project {}, which is extended by {}", .base_path.display(), .path.display())]
+    Extends {
+        source: io::Error,
+        path: PathBuf,
+        base_path: PathBuf,
+    },
+
+    #[error("Rojo projects cannot extend each other in a cycle: {chain}")]
+    ExtendsCycle { chain: String },
+
+    #[error("Profile '{profile}' is not defined in project {}", .path.display())]
+    UnknownProfile { profile: String, path: PathBuf },
+
+    #[error(
+        "Profile '{profile}' in project {} excludes '{node}', which is not in the project's tree",
+        .path.display()
+    )]
+    UnknownExcludedNode {
+        profile: String,
+        node: String,
+        path: PathBuf,
+    },
 }
 
 /// Contains all of the configuration for a Rojo-managed project.
@@ -40,6 +68,15 @@ pub struct Project {
     /// The name of the top-level instance described by the project.
     pub name: String,
 
+    /// If specified, the path to another project file, relative to this one,
+    /// that this project builds on. The other project's tree is merged with
+    /// this project's tree, and settings not set here are taken from it.
+    #[serde(
+        serialize_with = "crate::path_serializer::serialize_option_absolute",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub extends: Option<PathBuf>,
+
     /// The tree of instances described by this project. Projects always
     /// describe at least one instance.
     pub tree: ProjectNode,
@@ -72,11 +109,40 @@ pub struct Project {
     #[serde(default, skip_serializing_if = "Vec::is_empty")]
     pub glob_ignore_paths: Vec<Glob>,
 
+    /// A list of rules that decide how files matching a glob turn into
+    /// instances. Rules are checked in order before Rojo's built-in rules,
+    /// and apply to nested projects too.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub sync_rules: Vec<SyncRule>,
+
+    /// If specified, decides whether data modules created from JSON, TOML,
+    /// and YAML files use strict mode and export a Luau type describing their
+    /// contents. Applies to nested projects unless they set it too.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub typed_data_modules: Option<bool>,
+
+    /// Named sets of changes to the tree, like one for production builds.
+    /// Profiles are chosen with the `--profile` option, and apply to nested
+    /// projects that define a profile with the same name.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub profiles: BTreeMap<String, ProjectProfile>,
+
     /// The path to the file that this project came from. Relative paths in the
     /// project should be considered relative to the parent of this field, also
     /// given by `Project::folder_location`.
     #[serde(skip)]
     pub file_location: PathBuf,
+
+    /// Ignore rules from the projects this project extends. Their globs are
+    /// relative to the project that defined them, so they're kept separate
+    /// from `glob_ignore_paths`.
+    #[serde(skip)]
+    pub extended_ignore_rules: Vec<PathIgnoreRule>,
+
+    /// Every project file that this project extends, directly or through
+    /// another project. Changes to any of them change this project too.
+    #[serde(skip)]
+    pub extended_files: Vec<PathBuf>,
 }
 
 impl Project {
@@ -121,17 +187,159 @@ impl Project {
         contents: &[u8],
         project_file_location: &Path,
     ) -> Result<Self, ProjectError> {
-        let mut project: Self =
-            serde_json::from_slice(&contents).map_err(|source| Error::Json {
-                source,
-                path: project_file_location.to_owned(),
-            })?;
+        Ok(Self::parse(contents, project_file_location)?)
+    }
+
+    fn parse(contents: &[u8], project_file_location: &Path) -> Result<Self, Error> {
+        let mut project: Self = serde_json::from_slice(contents).map_err(|source| Error::Json {
+            source,
+            path: project_file_location.to_owned(),
+        })?;
 
         project.file_location = project_file_location.to_path_buf();
         project.check_compatibility();
         Ok(project)
     }
 
+    /// Loads the project that this project extends, if any, through the given
+    /// VFS and merges this project on top of it.
+    ///
+    /// Projects loaded with `load_from_slice` are exactly what's in the file,
+    /// which is what tools that edit project files need. Anything that builds
+    /// instances from a project should use the result of this method instead.
+    pub fn resolve_extends(self, vfs: &Vfs) -> Result<Self, ProjectError> {
+        let mut chain = vec![normalize_path(&self.file_location)];
+        Ok(self.resolve_extends_inner(vfs, &mut chain)?)
+    }
+
+    fn resolve_extends_inner(self, vfs: &Vfs, chain: &mut Vec<PathBuf>) -> Result<Self, Error> {
+        let base_path = match &self.extends {
+            Some(extends) => normalize_path(&self.folder_location().join(extends)),
+            None => return Ok(self),
+        };
+
+        if chain.contains(&base_path) {
+            chain.push(base_path);
+
+            let chain = chain
+                .iter()
+                .map(|path| path.display().to_string())
+                .collect::<Vec<_>>()
+                .join(" -> ");
+
+            return Err(Error::ExtendsCycle { chain });
+        }
+
+        let contents = vfs.read(&base_path).map_err(|source| Error::Extends {
+            source,
+            path: self.file_location.clone(),
+            base_path: base_path.clone(),
+        })?;
+
+        chain.push(base_path.clone());
+        let base = Self::parse(&contents, &base_path)?.resolve_extends_inner(vfs, chain)?;
+        chain.pop();
+
+        Ok(self.merge_onto(base))
+    }
+
+    /// Merges this project on top of a project that it extends. Settings from
+    /// this project win over settings from the base project.
+    fn merge_onto(mut self, base: Project) -> Project {
+        let base_folder = base.folder_location().to_path_buf();
+
+        // Relative paths in the base project are relative to the base
+        // project, not to this one.
+        let mut base_tree = base.tree;
+        base_tree.make_paths_absolute(&base_folder);
+        self.tree = self.tree.merge_onto(base_tree);
+
+        self.serve_port = self.serve_port.or(base.serve_port);
+        self.serve_place_ids = self.serve_place_ids.or(base.serve_place_ids);
+        self.place_id = self.place_id.or(base.place_id);
+        self.game_id = self.game_id.or(base.game_id);
+        self.typed_data_modules = self.typed_data_modules.or(base.typed_data_modules);
+
+        // Profiles are replaced whole, rather than merged, when both projects
+        // define a profile with the same name.
+        let mut profiles = base.profiles;
+        for profile in profiles.values_mut() {
+            if let Some(tree) = &mut profile.tree {
+                tree.make_paths_absolute(&base_folder);
+            }
+        }
+        profiles.extend(self.profiles);
+        self.profiles = profiles;
+
+        self.extended_ignore_rules = base.extended_ignore_rules;
+        self.extended_ignore_rules
+            .extend(
+                base.glob_ignore_paths
+                    .into_iter()
+                    .map(|glob| PathIgnoreRule {
+                        base_path: base_folder.clone(),
+                        glob,
+                    }),
+            );
+
+        // This project's sync rules are checked first so that they can
+        // override the base project's rules.
+        self.sync_rules
+            .extend(base.sync_rules.into_iter().map(|rule| {
+                if rule.base_path.as_os_str().is_empty() {
+                    SyncRule {
+                        base_path: base_folder.clone(),
+                        ..rule
+                    }
+                } else {
+                    rule
+                }
+            }));
+
+        self.extended_files = vec![base.file_location];
+        self.extended_files.extend(base.extended_files);
+
+        self
+    }
+
+    /// Tells whether this project defines a profile with the given name.
+    pub fn has_profile(&self, name: &str) -> bool {
+        self.profiles.contains_key(name)
+    }
+
+    /// Changes this project's tree the way the profile with the given name
+    /// describes.
+    pub fn apply_profile(&mut self, name: &str) -> Result<(), ProjectError> {
+        let profile = match self.profiles.get(name) {
+            Some(profile) => profile.clone(),
+            None => {
+                return Err(Error::UnknownProfile {
+                    profile: name.to_owned(),
+                    path: self.file_location.clone(),
+                }
+                .into())
+            }
+        };
+
+        if let Some(tree) = profile.tree {
+            let base = std::mem::take(&mut self.tree);
+            self.tree = tree.merge_onto(base);
+        }
+
+        for node in &profile.exclude {
+            if !self.tree.remove_descendant(node) {
+                return Err(Error::UnknownExcludedNode {
+                    profile: name.to_owned(),
+                    node: node.clone(),
+                    path: self.file_location.clone(),
+                }
+                .into());
+            }
+        }
+
+        Ok(())
+    }
+
     pub fn load_fuzzy(fuzzy_project_location: &Path) -> Result<Option<Self>, ProjectError> {
         if let Some(project_path) = Self::locate(fuzzy_project_location) {
             let project = Self::load_exact(&project_path)?;
@@ -194,6 +402,15 @@ pub struct ProjectNode {
     )]
     pub properties: HashMap<String, UnresolvedValue>,
 
+    /// The attributes that will be assigned to the resulting instance. Values
+    /// must either be strings, numbers, or bools, or explicitly typed.
+    #[serde(
+        rename = "$attributes",
+        default,
+        skip_serializing_if = "HashMap::is_empty"
+    )]
+    pub attributes: HashMap<String, UnresolvedValue>,
+
     /// Defines the behavior when Rojo encounters unknown instances in Roblox
     /// Studio during live sync. `$ignoreUnknownInstances` should be considered
     /// a large hammer and used with care.
@@ -226,7 +443,71 @@ pub struct ProjectNode {
     pub path: Option<PathBuf>,
 }
 
+/// Changes a project makes to its tree when a profile is chosen.
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+#[serde(deny_unknown_fields, rename_all = "camelCase")]
+pub struct ProjectProfile {
+    /// Merged with the project's tree when this profile is used. Nodes in it
+    /// can add `$properties` and `$attributes`, point to a different `$path`,
+    /// or add new children.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tree: Option<ProjectNode>,
+
+    /// Nodes to leave out of the tree when this profile is used, given as
+    /// paths of names separated by slashes, like `ReplicatedStorage/DevTools`.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub exclude: Vec<String>,
+}
+
 impl ProjectNode {
+    /// Merges this node on top of a node from a project that this project
+    /// extends. Anything set on this node wins, and children with the same
+    /// name are merged the same way.
+    fn merge_onto(self, mut base: ProjectNode) -> ProjectNode {
+        base.class_name = self.class_name.or(base.class_name);
+        base.path = self.path.or(base.path);
+        base.ignore_unknown_instances = self
+            .ignore_unknown_instances
+            .or(base.ignore_unknown_instances);
+        base.properties.extend(self.properties);
+        base.attributes.extend(self.attributes);
+
+        for (name, child) in self.children {
+            let merged = match base.children.remove(&name) {
+                Some(base_child) => child.merge_onto(base_child),
+                None => child,
+            };
+
+            base.children.insert(name, merged);
+        }
+
+        base
+    }
+
+    /// Removes the descendant at the given slash-separated path of names.
+    /// Returns whether there was a node to remove.
+    fn remove_descendant(&mut self, path: &str) -> bool {
+        match path.split_once('/') {
+            Some((name, rest)) => match self.children.get_mut(name) {
+                Some(child) => child.remove_descendant(rest),
+                None => false,
+            },
+            None => self.children.remove(path).is_some(),
+        }
+    }
+
+    fn make_paths_absolute(&mut self, folder: &Path) {
+        if let Some(path) = &mut self.path {
+            if path.is_relative() {
+                *path = folder.join(&path);
+            }
+        }
+
+        for child in self.children.values_mut() {
+            child.make_paths_absolute(folder);
+        }
+    }
+
     fn validate_reserved_names(&self) {
         for (name, child) in &self.children {
             if name.starts_with('$') {
@@ -243,3 +524,21 @@ impl ProjectNode {
         }
     }
 }
+
+/// Removes `.` and `..` components from a path without touching the
+/// filesystem, so that the same file is always described by the same path.
+fn normalize_path(path: &Path) -> PathBuf {
+    let mut normalized = PathBuf::new();
+
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => {
+                normalized.pop();
+            }
+            other => normalized.push(other),
+        }
+    }
+
+    normalized
+}
@@ -1,8 +1,9 @@
-use std::borrow::Borrow;
+use std::{borrow::Borrow, collections::HashMap, iter::FromIterator};
 
-use anyhow::format_err;
+use anyhow::{bail, format_err, Context};
 use rbx_dom_weak::types::{
-    CFrame, Color3, Content, Enum, Matrix3, Tags, Variant, VariantType, Vector2, Vector3,
+    Attributes, BinaryString, CFrame, Color3, Content, Enum, Matrix3, Tags, Variant, VariantType,
+    Vector2, Vector3,
 };
 use rbx_reflection::{DataType, PropertyDescriptor};
 use serde::{Deserialize, Serialize};
@@ -27,6 +28,15 @@ impl UnresolvedValue {
             UnresolvedValue::Ambiguous(partial) => partial.resolve(class_name, prop_name),
         }
     }
+
+    /// Resolves the value without a property to look at, which is the case for
+    /// attributes. Only values that can mean just one thing are accepted.
+    pub fn resolve_unambiguous(self) -> anyhow::Result<Variant> {
+        match self {
+            UnresolvedValue::FullyQualified(full) => Ok(full),
+            UnresolvedValue::Ambiguous(partial) => partial.resolve_unambiguous(),
+        }
+    }
 }
 
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
@@ -144,6 +154,19 @@ impl AmbiguousValue {
         }
     }
 
+    pub fn resolve_unambiguous(self) -> anyhow::Result<Variant> {
+        match self {
+            AmbiguousValue::Bool(value) => Ok(value.into()),
+            AmbiguousValue::Number(value) => Ok(value.into()),
+            AmbiguousValue::String(value) => Ok(value.into()),
+            unresolved => Err(format_err!(
+                "Got {}, which could be one of several types. \
+                 Specify the type explicitly, like {{\"Vector3\": [1, 2, 3]}}",
+                unresolved.describe(),
+            )),
+        }
+    }
+
     fn describe(&self) -> &'static str {
         match self {
             AmbiguousValue::Bool(_) => "a bool",
@@ -158,7 +181,7 @@ impl AmbiguousValue {
     }
 }
 
-fn find_descriptor(
+pub(crate) fn find_descriptor(
     class_name: &str,
     prop_name: &str,
 ) -> Option<&'static PropertyDescriptor<'static>> {
@@ -175,6 +198,125 @@ fn find_descriptor(
     }
 }
 
+/// Resolves the given attributes and adds them to the `Attributes` property in
+/// `properties`, replacing any existing attributes with the same name.
+pub fn apply_attributes<I>(
+    properties: &mut HashMap<String, Variant>,
+    attributes: I,
+) -> anyhow::Result<()>
+where
+    I: IntoIterator<Item = (String, UnresolvedValue)>,
+{
+    let mut attributes = attributes.into_iter().peekable();
+    if attributes.peek().is_none() {
+        return Ok(());
+    }
+
+    let mut merged = match properties.get("Attributes") {
+        Some(Variant::BinaryString(existing)) => {
+            Attributes::from_reader(AsRef::<[u8]>::as_ref(existing))
+                .context("Existing Attributes property could not be decoded")?
+        }
+        Some(Variant::Attributes(existing)) => existing.clone(),
+        _ => Attributes::new(),
+    };
+
+    for (name, unresolved) in attributes {
+        validate_attribute_name(&name)?;
+
+        let value = unresolved
+            .resolve_unambiguous()
+            .with_context(|| format!("Invalid value for attribute {}", name))?;
+
+        // Roblox stores string attributes as binary strings.
+        let value = match value {
+            Variant::String(value) => BinaryString::from(value.into_bytes()).into(),
+            value => value,
+        };
+
+        merged.insert(name, value);
+    }
+
+    let mut encoded = Vec::new();
+    merged
+        .to_writer(&mut encoded)
+        .context("Attributes could not be encoded")?;
+
+    properties.insert("Attributes".to_owned(), BinaryString::from(encoded).into());
+
+    Ok(())
+}
+
+/// Removes the `Attributes` property from `properties`, returning its contents
+/// as values that can be written to a project, model, or meta file. If the
+/// property can't be decoded, it's left in place.
+pub fn take_attributes<C>(properties: &mut HashMap<String, Variant>) -> C
+where
+    C: FromIterator<(String, UnresolvedValue)> + Default,
+{
+    let decoded = match properties.get("Attributes") {
+        Some(Variant::BinaryString(existing)) => {
+            Attributes::from_reader(AsRef::<[u8]>::as_ref(existing))
+        }
+        _ => return C::default(),
+    };
+
+    match decoded {
+        Ok(attributes) => {
+            properties.remove("Attributes");
+
+            attributes
+                .into_iter()
+                .map(|(name, value)| (name, unresolved_attribute(value)))
+                .collect()
+        }
+        Err(err) => {
+            log::warn!("Could not decode Attributes property: {}", err);
+            C::default()
+        }
+    }
+}
+
+/// Turns a decoded attribute value back into the form a user would write it in,
+/// using the unambiguous shorthands when possible.
+fn unresolved_attribute(value: Variant) -> UnresolvedValue {
+    match value {
+        Variant::Bool(value) => UnresolvedValue::Ambiguous(AmbiguousValue::Bool(value)),
+        Variant::Float64(value) => UnresolvedValue::Ambiguous(AmbiguousValue::Number(value)),
+        Variant::BinaryString(value) => match String::from_utf8(value.into()) {
+            Ok(value) => UnresolvedValue::Ambiguous(AmbiguousValue::String(value)),
+            Err(err) => {
+                UnresolvedValue::FullyQualified(BinaryString::from(err.into_bytes()).into())
+            }
+        },
+        value => UnresolvedValue::FullyQualified(value),
+    }
+}
+
+/// Attribute names are limited by Roblox to 100 letters, digits, and
+/// underscores, and can't start with RBX, which is reserved.
+fn validate_attribute_name(name: &str) -> anyhow::Result<()> {
+    if name.is_empty() || name.len() > 100 {
+        bail!("Attribute names must be between 1 and 100 characters long");
+    }
+
+    if !name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_') {
+        bail!(
+            "Invalid attribute name {}. Attribute names can only contain letters, digits, and underscores",
+            name
+        );
+    }
+
+    if name.starts_with("RBX") {
+        bail!(
+            "Invalid attribute name {}. Names starting with RBX are reserved by Roblox",
+            name
+        );
+    }
+
+    Ok(())
+}
+
 /// Outputs a string containing up to MAX_ITEMS entries from the given list. If
 /// there are more than MAX_ITEMS items, the number of remaining items will be
 /// listed.
@@ -208,6 +350,8 @@ fn nonexhaustive_list(values: &[&str]) -> String {
 mod test {
     use super::*;
 
+    use std::collections::BTreeMap;
+
     fn resolve(class: &str, prop: &str, json_value: &str) -> Variant {
         let unresolved: UnresolvedValue = serde_json::from_str(json_value).unwrap();
         unresolved.resolve(class, prop).unwrap()
@@ -291,4 +435,77 @@ mod test {
             Variant::Enum(Enum::from_u32(1)),
         );
     }
+
+    fn resolve_attributes(json_value: &str) -> anyhow::Result<HashMap<String, Variant>> {
+        let attributes: HashMap<String, UnresolvedValue> =
+            serde_json::from_str(json_value).unwrap();
+
+        let mut properties = HashMap::new();
+        apply_attributes(&mut properties, attributes)?;
+        Ok(properties)
+    }
+
+    #[test]
+    fn attributes_round_trip() {
+        let mut properties = resolve_attributes(
+            r#"{
+                "Name": "Sword",
+                "Damage": 25,
+                "Enabled": true,
+                "Offset": { "Vector3": [1, 2, 3] }
+            }"#,
+        )
+        .unwrap();
+
+        let attributes: HashMap<String, UnresolvedValue> = take_attributes(&mut properties);
+        assert!(properties.is_empty());
+
+        let expected: HashMap<String, UnresolvedValue> = serde_json::from_str(
+            r#"{
+                "Name": "Sword",
+                "Damage": 25,
+                "Enabled": true,
+                "Offset": { "Vector3": [1, 2, 3] }
+            }"#,
+        )
+        .unwrap();
+
+        assert_eq!(attributes, expected);
+    }
+
+    #[test]
+    fn attributes_merge_with_existing() {
+        let mut properties = resolve_attributes(r#"{ "A": 1, "B": 2 }"#).unwrap();
+        apply_attributes(
+            &mut properties,
+            vec![(
+                "B".to_owned(),
+                UnresolvedValue::FullyQualified(3.0f64.into()),
+            )],
+        )
+        .unwrap();
+
+        let attributes: BTreeMap<String, UnresolvedValue> = take_attributes(&mut properties);
+        let values: Vec<_> = attributes.values().cloned().collect();
+
+        assert_eq!(
+            values,
+            vec![
+                UnresolvedValue::Ambiguous(AmbiguousValue::Number(1.0)),
+                UnresolvedValue::Ambiguous(AmbiguousValue::Number(3.0)),
+            ]
+        );
+    }
+
+    #[test]
+    fn ambiguous_attributes() {
+        assert!(resolve_attributes(r#"{ "Offset": [1, 2, 3] }"#).is_err());
+    }
+
+    #[test]
+    fn invalid_attributes() {
+        assert!(resolve_attributes(r#"{ "Has Space": 1 }"#).is_err());
+        assert!(resolve_attributes(r#"{ "RBXThing": 1 }"#).is_err());
+        assert!(resolve_attributes(r#"{ "Cost": { "Int32": 5 } }"#).is_err());
+    }
 }
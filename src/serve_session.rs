@@ -1,6 +1,6 @@
 use std::{
     borrow::Cow,
-    collections::HashSet,
+    collections::{HashMap, HashSet},
     io,
     path::{Path, PathBuf},
     sync::{Arc, Mutex, MutexGuard},
@@ -10,10 +10,11 @@ use std::{
 use crossbeam_channel::Sender;
 use memofs::IoResultExt;
 use memofs::Vfs;
+use rbx_dom_weak::types::Ref;
 use thiserror::Error;
 
 use crate::{
-    change_processor::ChangeProcessor,
+    change_processor::{ChangeProcessor, SnapshotError},
     message_queue::MessageQueue,
     project::{Project, ProjectError},
     session_id::SessionId,
@@ -83,6 +84,10 @@ pub struct ServeSession {
     /// to be applied.
     message_queue: Arc<MessageQueue<AppliedPatchSet>>,
 
+    /// Errors that are currently keeping instances from updating, keyed by the
+    /// instance that could not be updated. Written by the ChangeProcessor.
+    snapshot_errors: Arc<Mutex<HashMap<Ref, SnapshotError>>>,
+
     /// A channel to send mutation requests on. These will be handled by the
     /// ChangeProcessor and trigger changes in the tree.
     tree_mutation_sender: Sender<PatchSet>,
@@ -96,6 +101,16 @@ impl ServeSession {
     /// currently loaded from the filesystem directly instead of through the
     /// in-memory filesystem layer.
     pub fn new<P: AsRef<Path>>(vfs: Vfs, start_path: P) -> Result<Self, ServeSessionError> {
+        Self::with_profile(vfs, start_path, None)
+    }
+
+    /// Start a new serve session like `new`, building the project with the
+    /// given profile. The root project must define the profile.
+    pub fn with_profile<P: AsRef<Path>>(
+        vfs: Vfs,
+        start_path: P,
+        profile: Option<&str>,
+    ) -> Result<Self, ServeSessionError> {
         let start_path = start_path.as_ref();
         let start_time = Instant::now();
 
@@ -110,8 +125,10 @@ impl ServeSession {
 
         log::debug!("Loading project file from {}", project_path.display());
 
-        let root_project = match vfs.read(&project_path).with_not_found()? {
-            Some(contents) => Project::load_from_slice(&contents, &project_path)?,
+        let mut root_project = match vfs.read(&project_path).with_not_found()? {
+            Some(contents) => {
+                Project::load_from_slice(&contents, &project_path)?.resolve_extends(&vfs)?
+            }
             None => {
                 return Err(ServeSessionError::NoProjectFound {
                     path: project_path.to_path_buf(),
@@ -123,7 +140,15 @@ impl ServeSession {
 
         let root_id = tree.get_root_id();
 
-        let instance_context = InstanceContext::default();
+        if let Some(profile) = profile {
+            log::debug!("Using project profile '{}'", profile);
+            root_project.apply_profile(profile)?;
+        }
+
+        let instance_context = InstanceContext {
+            profile: profile.map(Into::into),
+            ..Default::default()
+        };
 
         log::trace!("Generating snapshot of instances from VFS");
         let snapshot = snapshot_from_vfs(&instance_context, &vfs, &start_path)?
@@ -141,6 +166,7 @@ impl ServeSession {
         let tree = Arc::new(Mutex::new(tree));
         let message_queue = Arc::new(message_queue);
         let vfs = Arc::new(vfs);
+        let snapshot_errors = Arc::new(Mutex::new(HashMap::new()));
 
         let (tree_mutation_sender, tree_mutation_receiver) = crossbeam_channel::unbounded();
 
@@ -149,6 +175,7 @@ impl ServeSession {
             Arc::clone(&tree),
             Arc::clone(&vfs),
             Arc::clone(&message_queue),
+            Arc::clone(&snapshot_errors),
             tree_mutation_receiver,
         );
 
@@ -159,6 +186,7 @@ impl ServeSession {
             root_project,
             tree,
             message_queue,
+            snapshot_errors,
             tree_mutation_sender,
             vfs,
         })
@@ -176,7 +204,6 @@ impl ServeSession {
         self.tree_mutation_sender.clone()
     }
 
-    #[allow(unused)]
     pub fn vfs(&self) -> &Vfs {
         &self.vfs
     }
@@ -185,10 +212,28 @@ impl ServeSession {
         &self.message_queue
     }
 
+    /// Returns the errors that are currently keeping instances from updating,
+    /// sorted by path.
+    pub fn snapshot_errors(&self) -> Vec<(Ref, SnapshotError)> {
+        let snapshot_errors = self.snapshot_errors.lock().unwrap();
+
+        let mut errors: Vec<_> = snapshot_errors
+            .iter()
+            .map(|(&id, error)| (id, error.clone()))
+            .collect();
+
+        errors.sort_by(|a, b| a.1.path.cmp(&b.1.path));
+        errors
+    }
+
     pub fn session_id(&self) -> SessionId {
         self.session_id
     }
 
+    pub fn root_project(&self) -> &Project {
+        &self.root_project
+    }
+
     pub fn project_name(&self) -> &str {
         &self.root_project.name
     }
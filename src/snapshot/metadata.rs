@@ -4,9 +4,10 @@ use std::{
     sync::Arc,
 };
 
+use anyhow::Context;
 use serde::{Deserialize, Serialize};
 
-use crate::{glob::Glob, path_serializer, project::ProjectNode};
+use crate::{glob::Glob, path_serializer, project::ProjectNode, snapshot_middleware::Middleware};
 
 /// Rojo-specific metadata that can be associated with an instance or a snapshot
 /// of an instance.
@@ -37,6 +38,9 @@ pub struct InstanceMetadata {
     /// - bar/init.lua
     /// - bar/init.server.lua
     /// - bar/init.client.lua
+    /// - bar/init.luau
+    /// - bar/init.server.luau
+    /// - bar/init.client.luau
     /// - bar/default.project.json
     ///
     /// This path is used to make sure that file changes update all instances
@@ -103,6 +107,19 @@ impl Default for InstanceMetadata {
 pub struct InstanceContext {
     #[serde(skip_serializing_if = "Vec::is_empty")]
     pub path_ignore_rules: Arc<Vec<PathIgnoreRule>>,
+
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub sync_rules: Arc<Vec<SyncRule>>,
+
+    /// Whether data modules, like the ones created from JSON files, should be
+    /// generated with Luau type annotations.
+    #[serde(default, skip_serializing_if = "is_false")]
+    pub typed_data_modules: bool,
+
+    /// The project profile chosen with `--profile`, if any. Projects that
+    /// define a profile with this name apply it to their tree.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub profile: Option<Arc<str>>,
 }
 
 impl InstanceContext {
@@ -123,16 +140,46 @@ impl InstanceContext {
         let rules = Arc::make_mut(&mut self.path_ignore_rules);
         rules.extend(new_rules);
     }
+
+    /// Add the given sync rules to the context. New rules are checked before
+    /// the rules already in the context, which lets nested projects override
+    /// the rules of the projects that contain them.
+    pub fn add_sync_rules<I>(&mut self, new_rules: I)
+    where
+        I: IntoIterator<Item = SyncRule>,
+        I::IntoIter: ExactSizeIterator,
+    {
+        let new_rules = new_rules.into_iter();
+
+        if new_rules.len() == 0 {
+            return;
+        }
+
+        let rules = Arc::make_mut(&mut self.sync_rules);
+        rules.splice(0..0, new_rules);
+    }
+
+    /// Returns the first sync rule that applies to the given path, if any.
+    pub fn get_sync_rule(&self, path: &Path) -> Option<&SyncRule> {
+        self.sync_rules.iter().find(|rule| rule.matches(path))
+    }
 }
 
 impl Default for InstanceContext {
     fn default() -> Self {
         InstanceContext {
             path_ignore_rules: Arc::new(Vec::new()),
+            sync_rules: Arc::new(Vec::new()),
+            typed_data_modules: false,
+            profile: None,
         }
     }
 }
 
+fn is_false(value: &bool) -> bool {
+    !value
+}
+
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct PathIgnoreRule {
     /// The path that this glob is relative to. Since ignore globs are defined
@@ -156,13 +203,65 @@ impl PathIgnoreRule {
     }
 }
 
+/// Decides how files matching a glob turn into instances, taking priority over
+/// Rojo's built-in rules. Sync rules are defined in project files.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SyncRule {
+    /// The glob that paths are matched against, relative to `base_path`.
+    pub pattern: Glob,
+
+    /// The middleware that matching files are snapshotted with.
+    #[serde(rename = "use")]
+    pub middleware: Middleware,
+
+    /// The suffix removed from the file name to get the name of the instance.
+    /// If unset, only the file's extension is removed.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub suffix: Option<String>,
+
+    /// The path that `pattern` is relative to. This is the folder containing
+    /// the project file that defined the rule, and is filled in when the
+    /// project is loaded.
+    #[serde(skip)]
+    pub base_path: PathBuf,
+}
+
+impl SyncRule {
+    pub fn matches<P: AsRef<Path>>(&self, path: P) -> bool {
+        match path.as_ref().strip_prefix(&self.base_path) {
+            Ok(suffix) => self.pattern.is_match(suffix),
+            Err(_) => false,
+        }
+    }
+
+    /// Returns the name of the instance that a file matching this rule should
+    /// turn into.
+    pub fn file_name_for_path<'a>(&self, path: &'a Path) -> anyhow::Result<&'a str> {
+        let file_name = path
+            .file_name()
+            .and_then(|name| name.to_str())
+            .with_context(|| format!("Path did not have a file name: {}", path.display()))?;
+
+        match &self.suffix {
+            Some(suffix) => file_name
+                .strip_suffix(suffix.as_str())
+                .with_context(|| format!("Path did not end in {}: {}", suffix, path.display())),
+            None => Ok(file_name
+                .rfind('.')
+                .map(|index| &file_name[..index])
+                .unwrap_or(file_name)),
+        }
+    }
+}
+
 #[derive(Clone, PartialEq, Serialize, Deserialize)]
 pub enum InstigatingSource {
     Path(#[serde(serialize_with = "path_serializer::serialize_absolute")] PathBuf),
     ProjectNode(
         #[serde(serialize_with = "path_serializer::serialize_absolute")] PathBuf,
         String,
-        ProjectNode,
+        Box<ProjectNode>,
         Option<String>,
     ),
 }
@@ -1,21 +1,26 @@
-use std::{collections::BTreeMap, path::Path};
+use std::{
+    collections::{BTreeMap, BTreeSet, HashMap},
+    path::Path,
+};
 
 use anyhow::Context;
 use maplit::hashmap;
 use memofs::{IoResultExt, Vfs};
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
 
 use crate::snapshot::{InstanceContext, InstanceMetadata, InstanceSnapshot};
 
-use super::{meta_file::AdjacentMetadata, util::PathExt};
+use super::{
+    meta_file::{AdjacentMetadata, DirectoryMetadata},
+    snapshot_from_vfs,
+};
 
 pub fn snapshot_csv(
     _context: &InstanceContext,
     vfs: &Vfs,
     path: &Path,
+    name: &str,
 ) -> anyhow::Result<Option<InstanceSnapshot>> {
-    let name = path.file_name_trim_end(".csv")?;
-
     let meta_path = path.with_file_name(format!("{}.meta.json", name));
     let contents = vfs.read(path)?;
 
@@ -26,6 +31,8 @@ pub fn snapshot_csv(
         )
     })?;
 
+    warn_about_issues(path, &contents, |_| true);
+
     let mut snapshot = InstanceSnapshot::new()
         .name(name)
         .class_name("LocalizationTable")
@@ -46,27 +53,158 @@ pub fn snapshot_csv(
     Ok(Some(snapshot))
 }
 
+/// Snapshots a directory whose `init.meta.json` sets its `className` to
+/// `LocalizationTable`. Every CSV file in the directory, usually one per
+/// locale, is merged into the table's contents.
+pub fn snapshot_csv_dir(
+    context: &InstanceContext,
+    vfs: &Vfs,
+    path: &Path,
+    name: &str,
+    mut metadata: DirectoryMetadata,
+) -> anyhow::Result<Option<InstanceSnapshot>> {
+    let mut csv_paths = Vec::new();
+    let mut children = Vec::new();
+
+    for entry in vfs.read_dir(path)? {
+        let entry = entry?;
+        let entry_path = entry.path();
+
+        let passes_filter_rules = context
+            .path_ignore_rules
+            .iter()
+            .all(|rule| rule.passes(entry_path));
+
+        if !passes_filter_rules || entry_path == metadata.path {
+            continue;
+        }
+
+        // Everything else in the directory turns into children, just like it
+        // would in any other directory.
+        if entry_path.extension().and_then(|ext| ext.to_str()) == Some("csv") {
+            csv_paths.push(entry_path.to_path_buf());
+        } else if let Some(child) = snapshot_from_vfs(context, vfs, entry_path)? {
+            children.push(child);
+        }
+    }
+
+    // Directories aren't read in any particular order, but the merged table
+    // should always come out the same.
+    csv_paths.sort();
+
+    let mut tables = Vec::with_capacity(csv_paths.len());
+    for csv_path in &csv_paths {
+        let contents = vfs.read(csv_path)?;
+        let entries = read_localization_csv(&contents).with_context(|| {
+            format!(
+                "File was not a valid LocalizationTable CSV file: {}",
+                csv_path.display()
+            )
+        })?;
+
+        // Each file usually only has some of the table's locales, so missing
+        // translations are checked after the files are merged instead.
+        warn_about_issues(csv_path, &contents, |kind| {
+            kind != LocalizationIssueKind::MissingTranslation
+        });
+
+        tables.push((csv_path.as_path(), entries));
+    }
+
+    let entries = merge_localization_tables(&tables);
+
+    for issue in check_merged_localization(&entries, None) {
+        log::warn!("{}: {}", path.display(), issue.message);
+    }
+
+    let mut relevant_paths = vec![path.to_path_buf(), metadata.path.clone()];
+    relevant_paths.extend(csv_paths.iter().cloned());
+    let table_contents =
+        serde_json::to_string(&entries).expect("Could not encode JSON for localization table");
+
+    let mut snapshot = InstanceSnapshot::new()
+        .name(name)
+        .class_name("LocalizationTable")
+        .properties(hashmap! {
+            "Contents".to_owned() => table_contents.into(),
+        })
+        .children(children)
+        .metadata(
+            InstanceMetadata::new()
+                .instigating_source(path)
+                .relevant_paths(relevant_paths)
+                .context(context),
+        );
+
+    // The className is what made this directory a LocalizationTable, so
+    // there's nothing left to apply.
+    metadata.class_name = None;
+    metadata.apply_all(&mut snapshot)?;
+
+    Ok(Some(snapshot))
+}
+
 /// Struct that holds any valid row from a Roblox CSV translation table.
 ///
 /// We manually deserialize into this table from CSV, but let serde_json handle
 /// serialization.
-#[derive(Debug, Default, Serialize)]
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
-struct LocalizationEntry<'a> {
-    #[serde(skip_serializing_if = "Option::is_none")]
-    key: Option<&'a str>,
+pub struct LocalizationEntry {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub key: Option<String>,
 
-    #[serde(skip_serializing_if = "Option::is_none")]
-    context: Option<&'a str>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub context: Option<String>,
 
-    #[serde(skip_serializing_if = "Option::is_none")]
-    example: Option<&'a str>,
+    // Roblox calls this field `examples` in the tables it writes.
+    #[serde(default, alias = "examples", skip_serializing_if = "Option::is_none")]
+    pub example: Option<String>,
 
-    #[serde(skip_serializing_if = "Option::is_none")]
-    source: Option<&'a str>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub source: Option<String>,
 
     // We use a BTreeMap here to get deterministic output order.
-    values: BTreeMap<&'a str, &'a str>,
+    #[serde(default)]
+    pub values: BTreeMap<String, String>,
+}
+
+impl LocalizationEntry {
+    /// Describes this entry in warnings, by its key if it has one, or by its
+    /// source text otherwise.
+    pub fn describe(&self) -> String {
+        match (&self.key, &self.source, &self.context) {
+            (Some(key), _, _) => format!("key '{}'", key),
+            (None, Some(source), Some(context)) => {
+                format!("source '{}' in context '{}'", source, context)
+            }
+            (None, Some(source), None) => format!("source '{}'", source),
+            (None, None, _) => "entry with no key or source".to_owned(),
+        }
+    }
+
+    /// Entries are the same entry if they have the same key. Entries without
+    /// keys are matched by their source text and context instead, just like
+    /// Roblox does.
+    fn identity(&self) -> (Option<String>, Option<String>, Option<String>) {
+        match &self.key {
+            Some(key) => (Some(key.clone()), None, None),
+            None => (None, self.source.clone(), self.context.clone()),
+        }
+    }
+
+    /// Turns empty strings into missing values. Tables written by Roblox use
+    /// empty strings for fields that aren't set.
+    fn without_empty_fields(mut self) -> Self {
+        let not_empty = |value: &String| !value.is_empty();
+
+        self.key = self.key.filter(not_empty);
+        self.context = self.context.filter(not_empty);
+        self.example = self.example.filter(not_empty);
+        self.source = self.source.filter(not_empty);
+        self.values.retain(|_, value| !value.is_empty());
+        self
+    }
 }
 
 /// Normally, we'd be able to let the csv crate construct our struct for us.
@@ -76,53 +214,435 @@ struct LocalizationEntry<'a> {
 /// csv+Serde.
 ///
 /// https://github.com/BurntSushi/rust-csv/issues/151
-///
-/// This function operates in one step in order to minimize data-copying.
-fn convert_localization_csv(contents: &[u8]) -> Result<String, csv::Error> {
-    let mut reader = csv::Reader::from_reader(contents);
+pub fn read_localization_csv(contents: &[u8]) -> Result<Vec<LocalizationEntry>, csv::Error> {
+    read_entries(csv::Reader::from_reader(contents), false)
+}
+
+/// Reads the entries of a localization CSV file the way
+/// `check_localization_csv` sees them: rows with the wrong number of fields
+/// are kept, and rows that can't be read at all are skipped.
+pub fn read_localization_csv_flexible(
+    contents: &[u8],
+) -> Result<Vec<LocalizationEntry>, csv::Error> {
+    let reader = csv::ReaderBuilder::new()
+        .flexible(true)
+        .from_reader(contents);
+
+    read_entries(reader, true)
+}
 
+fn read_entries(
+    mut reader: csv::Reader<&[u8]>,
+    skip_bad_rows: bool,
+) -> Result<Vec<LocalizationEntry>, csv::Error> {
     let headers = reader.headers()?.clone();
 
-    let mut records = Vec::new();
+    let mut entries = Vec::new();
 
     for record in reader.into_records() {
-        records.push(record?);
+        let record = match record {
+            Ok(record) => record,
+            Err(err) if skip_bad_rows && err.position().is_some() => continue,
+            Err(err) => return Err(err),
+        };
+        let entry = entry_from_record(&headers, &record);
+
+        if entry.key.is_none() && entry.source.is_none() {
+            continue;
+        }
+
+        entries.push(entry);
     }
 
-    let mut entries = Vec::new();
+    Ok(entries)
+}
+
+/// The language codes that Roblox supports. Locale columns in CSV files can be
+/// a language code alone, like `es`, or a language code and a region, like
+/// `es-es` or `pt-br`.
+const LANGUAGE_CODES: &[&str] = &[
+    "af", "am", "ar", "az", "be", "bg", "bn", "bs", "ca", "cs", "da", "de", "el", "en", "es", "et",
+    "fa", "fi", "fil", "fr", "he", "hi", "hr", "hu", "hy", "id", "is", "it", "ja", "ka", "kk",
+    "km", "ko", "lo", "lt", "lv", "ms", "my", "nb", "nl", "pl", "pt", "ro", "ru", "si", "sk", "sl",
+    "sq", "sr", "sv", "sw", "th", "tr", "uk", "uz", "vi", "zh", "zu",
+];
+
+/// The columns of a localization CSV file that aren't locales.
+const KNOWN_COLUMNS: &[&str] = &["Key", "Source", "Context", "Example"];
+
+fn is_locale_code(header: &str) -> bool {
+    let header = header.to_ascii_lowercase();
+    let mut parts = header.splitn(2, '-');
+
+    let language = parts.next().unwrap_or_default();
+    if !LANGUAGE_CODES.contains(&language) {
+        return false;
+    }
 
-    for record in &records {
-        let mut entry = LocalizationEntry::default();
+    match parts.next() {
+        None => true,
+        Some(region) => {
+            let is_letters = region.len() == 2 && region.chars().all(|c| c.is_ascii_lowercase());
+            let is_numbers = region.len() == 3 && region.chars().all(|c| c.is_ascii_digit());
 
-        for (header, value) in headers.iter().zip(record.into_iter()) {
-            if header.is_empty() || value.is_empty() {
-                continue;
-            }
+            is_letters || is_numbers || region == "cjv"
+        }
+    }
+}
+
+/// A problem found in a localization CSV file.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LocalizationIssue {
+    /// The line of the file the problem is on. Problems with tables merged
+    /// from several files don't have one.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub line: Option<u64>,
+    pub kind: LocalizationIssueKind,
+    pub message: String,
+}
+
+impl LocalizationIssue {
+    /// Describes where the issue is, like `strings.csv:4`.
+    pub fn location(&self, path: &Path) -> String {
+        match self.line {
+            Some(line) => format!("{}:{}", path.display(), line),
+            None => path.display().to_string(),
+        }
+    }
+}
 
-            match header {
-                "Key" => entry.key = Some(value),
-                "Source" => entry.source = Some(value),
-                "Context" => entry.context = Some(value),
-                "Example" => entry.example = Some(value),
-                _ => {
-                    entry.values.insert(header, value);
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum LocalizationIssueKind {
+    /// An entry with the same key, or the same source and context, as an
+    /// earlier row.
+    DuplicateKey,
+
+    /// A column header that isn't one of the known columns or a locale code
+    /// that Roblox supports.
+    UnknownLocale,
+
+    /// An entry that has no translation for one of the target locales.
+    MissingTranslation,
+
+    /// A row that Roblox can't read the way it was probably meant, like one
+    /// with the wrong number of fields or translations without a key or
+    /// source.
+    MalformedRow,
+}
+
+/// Finds problems in a localization CSV file that Rojo would otherwise skip
+/// over quietly.
+///
+/// Entries are checked for translations in each of `target_locales`, or in
+/// every locale the file has a column for if no target locales are given.
+pub fn check_localization_csv(
+    contents: &[u8],
+    target_locales: Option<&[String]>,
+) -> Result<Vec<LocalizationIssue>, csv::Error> {
+    let mut reader = csv::ReaderBuilder::new()
+        .flexible(true)
+        .from_reader(contents);
+
+    let headers = reader.headers()?.clone();
+    let header_line = headers
+        .position()
+        .map(|position| position.line())
+        .unwrap_or(1);
+
+    let mut issues = Vec::new();
+
+    let mut locales = Vec::new();
+    for header in headers.iter() {
+        if header.is_empty() || KNOWN_COLUMNS.contains(&header) {
+            continue;
+        }
+
+        if is_locale_code(header) {
+            locales.push(header.to_owned());
+        } else {
+            issues.push(LocalizationIssue {
+                line: Some(header_line),
+                kind: LocalizationIssueKind::UnknownLocale,
+                message: format!(
+                    "Column '{}' is not a locale code that Roblox supports",
+                    header
+                ),
+            });
+        }
+    }
+
+    let target_locales = target_locales.unwrap_or(&locales);
+    let mut first_lines = HashMap::new();
+
+    for record in reader.records() {
+        let record = match record {
+            Ok(record) => record,
+            Err(err) => match err.position() {
+                Some(position) => {
+                    issues.push(LocalizationIssue {
+                        line: Some(position.line()),
+                        kind: LocalizationIssueKind::MalformedRow,
+                        message: err.to_string(),
+                    });
+                    continue;
                 }
-            }
+                None => return Err(err),
+            },
+        };
+
+        let line = record
+            .position()
+            .map(|position| position.line())
+            .unwrap_or_default();
+
+        if record.len() != headers.len() {
+            issues.push(LocalizationIssue {
+                line: Some(line),
+                kind: LocalizationIssueKind::MalformedRow,
+                message: format!(
+                    "Row has {} fields, but the header has {}",
+                    record.len(),
+                    headers.len()
+                ),
+            });
         }
 
+        let entry = entry_from_record(&headers, &record);
+
         if entry.key.is_none() && entry.source.is_none() {
+            // Rows with nothing in them are often used to space out groups
+            // of entries.
+            if record.iter().any(|value| !value.is_empty()) {
+                issues.push(LocalizationIssue {
+                    line: Some(line),
+                    kind: LocalizationIssueKind::MalformedRow,
+                    message: "Row has no Key or Source, so Roblox will ignore it".to_owned(),
+                });
+            }
+
             continue;
         }
 
-        entries.push(entry);
+        if let Some(first_line) = first_lines.insert(entry.identity(), line) {
+            issues.push(LocalizationIssue {
+                line: Some(line),
+                kind: LocalizationIssueKind::DuplicateKey,
+                message: format!(
+                    "Localization {} was already defined on line {}",
+                    entry.describe(),
+                    first_line
+                ),
+            });
+        }
+
+        issues.extend(missing_translation_issue(
+            &entry,
+            target_locales,
+            Some(line),
+        ));
+    }
+
+    Ok(issues)
+}
+
+/// Logs a warning for each issue in a CSV file that's being synced, if the
+/// issue's kind passes the given filter.
+fn warn_about_issues(path: &Path, contents: &[u8], filter: impl Fn(LocalizationIssueKind) -> bool) {
+    // Files that can't be read at all have already been reported as errors.
+    let issues = match check_localization_csv(contents, None) {
+        Ok(issues) => issues,
+        Err(_) => return,
+    };
+
+    for issue in issues.iter().filter(|issue| filter(issue.kind)) {
+        log::warn!("{}: {}", issue.location(path), issue.message);
+    }
+}
+
+fn entry_from_record(headers: &csv::StringRecord, record: &csv::StringRecord) -> LocalizationEntry {
+    let mut entry = LocalizationEntry::default();
+
+    for (header, value) in headers.iter().zip(record) {
+        if header.is_empty() || value.is_empty() {
+            continue;
+        }
+
+        let value = value.to_owned();
+
+        match header {
+            "Key" => entry.key = Some(value),
+            "Source" => entry.source = Some(value),
+            "Context" => entry.context = Some(value),
+            "Example" => entry.example = Some(value),
+            _ => {
+                entry.values.insert(header.to_owned(), value);
+            }
+        }
     }
 
+    entry
+}
+
+fn convert_localization_csv(contents: &[u8]) -> Result<String, csv::Error> {
+    let entries = read_localization_csv(contents)?;
+
     let encoded =
         serde_json::to_string(&entries).expect("Could not encode JSON for localization table");
 
     Ok(encoded)
 }
 
+/// Reads the JSON stored in a LocalizationTable's `Contents` property.
+pub fn read_localization_contents(contents: &str) -> serde_json::Result<Vec<LocalizationEntry>> {
+    let entries: Vec<LocalizationEntry> = serde_json::from_str(contents)?;
+
+    Ok(entries
+        .into_iter()
+        .map(LocalizationEntry::without_empty_fields)
+        .collect())
+}
+
+/// Writes entries as a CSV file in the layout that Roblox and Rojo both read:
+/// `Key`, `Source`, `Context`, and `Example` columns followed by one column for
+/// each locale, in alphabetical order.
+pub fn write_localization_csv(entries: &[LocalizationEntry]) -> Result<Vec<u8>, csv::Error> {
+    let locales: BTreeSet<&str> = entries
+        .iter()
+        .flat_map(|entry| entry.values.keys().map(String::as_str))
+        .collect();
+
+    let mut writer = csv::Writer::from_writer(Vec::new());
+
+    let mut headers = vec!["Key", "Source", "Context", "Example"];
+    headers.extend(locales.iter().copied());
+    writer.write_record(&headers)?;
+
+    for entry in entries {
+        let mut record = vec![
+            entry.key.as_deref().unwrap_or_default(),
+            entry.source.as_deref().unwrap_or_default(),
+            entry.context.as_deref().unwrap_or_default(),
+            entry.example.as_deref().unwrap_or_default(),
+        ];
+
+        for locale in &locales {
+            record.push(
+                entry
+                    .values
+                    .get(*locale)
+                    .map(String::as_str)
+                    .unwrap_or_default(),
+            );
+        }
+
+        writer.write_record(&record)?;
+    }
+
+    // Writing into a Vec can't fail.
+    Ok(writer.into_inner().expect("Could not write CSV"))
+}
+
+/// Merges the entries from several CSV files into one table. Entries that
+/// appear in more than one file have their translations combined.
+///
+/// Warns about translations that disagree between files.
+pub fn merge_localization_tables(
+    tables: &[(&Path, Vec<LocalizationEntry>)],
+) -> Vec<LocalizationEntry> {
+    let mut merged: Vec<LocalizationEntry> = Vec::new();
+    let mut indices = HashMap::new();
+
+    for (path, entries) in tables {
+        for entry in entries {
+            let existing = match indices.get(&entry.identity()) {
+                Some(&index) => &mut merged[index],
+                None => {
+                    indices.insert(entry.identity(), merged.len());
+                    merged.push(entry.clone());
+                    continue;
+                }
+            };
+
+            if existing.source.is_none() {
+                existing.source = entry.source.clone();
+            }
+            if existing.context.is_none() {
+                existing.context = entry.context.clone();
+            }
+            if existing.example.is_none() {
+                existing.example = entry.example.clone();
+            }
+
+            for (locale, value) in &entry.values {
+                match existing.values.get(locale) {
+                    Some(existing_value) if existing_value != value => log::warn!(
+                        "Localization {} has more than one '{}' translation, using the one before {}",
+                        entry.describe(),
+                        locale,
+                        path.display()
+                    ),
+                    Some(_) => {}
+                    None => {
+                        existing.values.insert(locale.clone(), value.clone());
+                    }
+                }
+            }
+        }
+    }
+
+    merged
+}
+
+/// Finds entries in a merged table that are missing translations for any of
+/// `target_locales`, or for any locale that other entries have if no target
+/// locales are given.
+pub fn check_merged_localization(
+    entries: &[LocalizationEntry],
+    target_locales: Option<&[String]>,
+) -> Vec<LocalizationIssue> {
+    let all_locales: Vec<String> = entries
+        .iter()
+        .flat_map(|entry| entry.values.keys().cloned())
+        .collect::<BTreeSet<_>>()
+        .into_iter()
+        .collect();
+
+    let target_locales = target_locales.unwrap_or(&all_locales);
+
+    entries
+        .iter()
+        .filter_map(|entry| missing_translation_issue(entry, target_locales, None))
+        .collect()
+}
+
+fn missing_translation_issue(
+    entry: &LocalizationEntry,
+    target_locales: &[String],
+    line: Option<u64>,
+) -> Option<LocalizationIssue> {
+    let missing: Vec<&str> = target_locales
+        .iter()
+        .filter(|locale| !entry.values.contains_key(*locale))
+        .map(String::as_str)
+        .collect();
+
+    if missing.is_empty() {
+        return None;
+    }
+
+    Some(LocalizationIssue {
+        line,
+        kind: LocalizationIssueKind::MissingTranslation,
+        message: format!(
+            "Localization {} is missing translations for: {}",
+            entry.describe(),
+            missing.join(", ")
+        ),
+    })
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
@@ -144,10 +664,14 @@ Ack,Ack!,,An exclamation of despair,¡Ay!"#,
 
         let mut vfs = Vfs::new(imfs);
 
-        let instance_snapshot =
-            snapshot_csv(&InstanceContext::default(), &mut vfs, Path::new("/foo.csv"))
-                .unwrap()
-                .unwrap();
+        let instance_snapshot = snapshot_csv(
+            &InstanceContext::default(),
+            &mut vfs,
+            Path::new("/foo.csv"),
+            "foo",
+        )
+        .unwrap()
+        .unwrap();
 
         insta::assert_yaml_snapshot!(instance_snapshot);
     }
@@ -172,11 +696,190 @@ Ack,Ack!,,An exclamation of despair,¡Ay!"#,
 
         let mut vfs = Vfs::new(imfs);
 
+        let instance_snapshot = snapshot_csv(
+            &InstanceContext::default(),
+            &mut vfs,
+            Path::new("/foo.csv"),
+            "foo",
+        )
+        .unwrap()
+        .unwrap();
+
+        insta::assert_yaml_snapshot!(instance_snapshot);
+    }
+
+    #[test]
+    fn csv_dir_merges_locales() {
+        let mut imfs = InMemoryFs::new();
+        imfs.load_snapshot(
+            "/foo",
+            VfsSnapshot::dir(hashmap! {
+                "init.meta.json" => VfsSnapshot::file(r#"{ "className": "LocalizationTable" }"#),
+                "es.csv" => VfsSnapshot::file(
+                    "Key,Source,Context,Example,es\n\
+                     Ack,Ack!,,An exclamation of despair,¡Ay!\n\
+                     Bye,Goodbye!,,,¡Adiós!\n",
+                ),
+                "fr.csv" => VfsSnapshot::file(
+                    "Key,Source,fr\n\
+                     Ack,Ack!,Zut !\n",
+                ),
+            }),
+        )
+        .unwrap();
+
+        let vfs = Vfs::new(imfs);
+
         let instance_snapshot =
-            snapshot_csv(&InstanceContext::default(), &mut vfs, Path::new("/foo.csv"))
+            snapshot_from_vfs(&InstanceContext::default(), &vfs, Path::new("/foo"))
                 .unwrap()
                 .unwrap();
 
         insta::assert_yaml_snapshot!(instance_snapshot);
     }
+
+    #[test]
+    fn csv_dir_keeps_other_children() {
+        let mut imfs = InMemoryFs::new();
+        imfs.load_snapshot(
+            "/foo",
+            VfsSnapshot::dir(hashmap! {
+                "init.meta.json" => VfsSnapshot::file(r#"{ "className": "LocalizationTable" }"#),
+                "es.csv" => VfsSnapshot::file("Key,Source,es\nBye,Goodbye!,¡Adiós!\n"),
+                "Helper.lua" => VfsSnapshot::file("return nil"),
+                "Nested" => VfsSnapshot::empty_dir(),
+            }),
+        )
+        .unwrap();
+
+        let vfs = Vfs::new(imfs);
+
+        let instance_snapshot =
+            snapshot_from_vfs(&InstanceContext::default(), &vfs, Path::new("/foo"))
+                .unwrap()
+                .unwrap();
+
+        let mut children: Vec<_> = instance_snapshot
+            .children
+            .iter()
+            .map(|child| (child.name.as_ref(), child.class_name.as_ref()))
+            .collect();
+        children.sort();
+
+        assert_eq!(instance_snapshot.class_name, "LocalizationTable");
+        assert_eq!(children, [("Helper", "ModuleScript"), ("Nested", "Folder")]);
+    }
+
+    #[test]
+    fn merge_keeps_first_translation() {
+        let entry = |file_value: &str| LocalizationEntry {
+            key: Some("Ack".to_owned()),
+            values: maplit::btreemap! {
+                "es".to_owned() => file_value.to_owned(),
+            },
+            ..Default::default()
+        };
+
+        let tables = vec![
+            (Path::new("/a.csv"), vec![entry("¡Ay!")]),
+            (Path::new("/b.csv"), vec![entry("¡Oh!")]),
+        ];
+
+        assert_eq!(merge_localization_tables(&tables), vec![entry("¡Ay!")]);
+    }
+
+    #[test]
+    fn csv_round_trip() {
+        let contents = "Key,Source,Context,Example,de,es\n\
+                        Ack,Ack!,,An exclamation of despair,,¡Ay!\n\
+                        ,\"Hello, world!\",Greeting,,\"Hallo, Welt!\",\n";
+
+        let entries = read_localization_csv(contents.as_bytes()).unwrap();
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[1].source.as_deref(), Some("Hello, world!"));
+
+        let written = write_localization_csv(&entries).unwrap();
+        assert_eq!(String::from_utf8(written).unwrap(), contents);
+    }
+
+    #[test]
+    fn contents_from_roblox() {
+        let entries = read_localization_contents(
+            r#"[{"key":"Ack","context":"","examples":"An exclamation of despair","source":"Ack!","values":{"es":"¡Ay!","fr":""}}]"#,
+        )
+        .unwrap();
+
+        assert_eq!(
+            entries,
+            vec![LocalizationEntry {
+                key: Some("Ack".to_owned()),
+                context: None,
+                example: Some("An exclamation of despair".to_owned()),
+                source: Some("Ack!".to_owned()),
+                values: maplit::btreemap! {
+                    "es".to_owned() => "¡Ay!".to_owned(),
+                },
+            }]
+        );
+    }
+
+    #[test]
+    fn check_csv() {
+        let contents = "Key,Source,Context,Example,es,fr,Spanish\n\
+                        Ack,Ack!,,,¡Ay!,Zut !,\n\
+                        Bye,Goodbye!,,,¡Adiós!,,\n\
+                        ,,,,,,\n\
+                        ,,,,Hola,,\n\
+                        Ack,Ack again!,,,¡Ay!,Zut !,\n\
+                        Short,Short row\n";
+
+        let issues: Vec<_> = check_localization_csv(contents.as_bytes(), None)
+            .unwrap()
+            .into_iter()
+            .map(|issue| (issue.line, issue.kind))
+            .collect();
+
+        assert_eq!(
+            issues,
+            vec![
+                (Some(1), LocalizationIssueKind::UnknownLocale),
+                (Some(3), LocalizationIssueKind::MissingTranslation),
+                (Some(5), LocalizationIssueKind::MalformedRow),
+                (Some(6), LocalizationIssueKind::DuplicateKey),
+                (Some(7), LocalizationIssueKind::MalformedRow),
+                (Some(7), LocalizationIssueKind::MissingTranslation),
+            ]
+        );
+
+        let target_locales = ["de".to_owned()];
+        let issues = check_localization_csv(
+            b"Key,Source,es\nAck,Ack!,\xc2\xa1Ay!\n",
+            Some(&target_locales),
+        )
+        .unwrap();
+
+        assert_eq!(
+            issues,
+            vec![LocalizationIssue {
+                line: Some(2),
+                kind: LocalizationIssueKind::MissingTranslation,
+                message: "Localization key 'Ack' is missing translations for: de".to_owned(),
+            }]
+        );
+    }
+
+    #[test]
+    fn locale_codes() {
+        for code in &["es", "es-es", "pt-BR", "ar-001", "zh-cjv", "fil"] {
+            assert!(is_locale_code(code), "{} should be a locale code", code);
+        }
+
+        for code in &["Spanish", "es_es", "xx", "es-", "es-espana", ""] {
+            assert!(
+                !is_locale_code(code),
+                "{} should not be a locale code",
+                code
+            );
+        }
+    }
 }
@@ -4,7 +4,7 @@ use memofs::{DirEntry, IoResultExt, Vfs};
 
 use crate::snapshot::{InstanceContext, InstanceMetadata, InstanceSnapshot};
 
-use super::{meta_file::DirectoryMetadata, snapshot_from_vfs};
+use super::{csv::snapshot_csv_dir, meta_file::DirectoryMetadata, snapshot_from_vfs};
 
 pub fn snapshot_dir(
     context: &InstanceContext,
@@ -18,6 +18,33 @@ pub fn snapshot_dir(
             .all(|rule| rule.passes(child.path()))
     };
 
+    let instance_name = path
+        .file_name()
+        .expect("Could not extract file name")
+        .to_str()
+        .ok_or_else(|| anyhow::anyhow!("File name was not valid UTF-8: {}", path.display()))?
+        .to_string();
+
+    let meta_path = path.join("init.meta.json");
+
+    let metadata = match vfs.read(&meta_path).with_not_found()? {
+        Some(meta_contents) => Some(DirectoryMetadata::from_slice(
+            &meta_contents,
+            meta_path.clone(),
+        )?),
+        None => None,
+    };
+
+    // Directories that are LocalizationTables are made from the CSV files in
+    // them instead of having children.
+    let class_name = metadata
+        .as_ref()
+        .and_then(|metadata| metadata.class_name.as_deref());
+
+    if class_name == Some("LocalizationTable") {
+        return snapshot_csv_dir(context, vfs, path, &instance_name, metadata.unwrap());
+    }
+
     let mut snapshot_children = Vec::new();
 
     for entry in vfs.read_dir(path)? {
@@ -32,15 +59,6 @@ pub fn snapshot_dir(
         }
     }
 
-    let instance_name = path
-        .file_name()
-        .expect("Could not extract file name")
-        .to_str()
-        .ok_or_else(|| anyhow::anyhow!("File name was not valid UTF-8: {}", path.display()))?
-        .to_string();
-
-    let meta_path = path.join("init.meta.json");
-
     let relevant_paths = vec![
         path.to_path_buf(),
         meta_path.clone(),
@@ -50,6 +68,9 @@ pub fn snapshot_dir(
         path.join("init.lua"),
         path.join("init.server.lua"),
         path.join("init.client.lua"),
+        path.join("init.luau"),
+        path.join("init.server.luau"),
+        path.join("init.client.luau"),
     ];
 
     let mut snapshot = InstanceSnapshot::new()
@@ -63,8 +84,7 @@ pub fn snapshot_dir(
                 .context(context),
         );
 
-    if let Some(meta_contents) = vfs.read(&meta_path).with_not_found()? {
-        let mut metadata = DirectoryMetadata::from_slice(&meta_contents, meta_path)?;
+    if let Some(mut metadata) = metadata {
         metadata.apply_all(&mut snapshot)?;
     }
 
@@ -5,24 +5,24 @@ use maplit::hashmap;
 use memofs::{IoResultExt, Vfs};
 
 use crate::{
-    lua_ast::{Expression, Statement},
+    lua_ast::{data_module_source, Expression},
     snapshot::{InstanceContext, InstanceMetadata, InstanceSnapshot},
 };
 
-use super::{meta_file::AdjacentMetadata, util::PathExt};
+use super::meta_file::AdjacentMetadata;
 
 pub fn snapshot_json(
     context: &InstanceContext,
     vfs: &Vfs,
     path: &Path,
+    name: &str,
 ) -> anyhow::Result<Option<InstanceSnapshot>> {
-    let name = path.file_name_trim_end(".json")?;
     let contents = vfs.read(path)?;
 
     let value: serde_json::Value = serde_json::from_slice(&contents)
         .with_context(|| format!("File contains malformed JSON: {}", path.display()))?;
 
-    let as_lua = json_to_lua(value).to_string();
+    let as_lua = data_module_source(json_to_lua_value(value), context.typed_data_modules);
 
     let properties = hashmap! {
         "Source".to_owned() => as_lua.into(),
@@ -49,10 +49,6 @@ pub fn snapshot_json(
     Ok(Some(snapshot))
 }
 
-fn json_to_lua(value: serde_json::Value) -> Statement {
-    Statement::Return(json_to_lua_value(value))
-}
-
 fn json_to_lua_value(value: serde_json::Value) -> Expression {
     use serde_json::Value;
 
@@ -107,10 +103,34 @@ mod test {
             &InstanceContext::default(),
             &mut vfs,
             Path::new("/foo.json"),
+            "foo",
         )
         .unwrap()
         .unwrap();
 
         insta::assert_yaml_snapshot!(instance_snapshot);
     }
+
+    #[test]
+    fn typed_instance_from_vfs() {
+        let mut imfs = InMemoryFs::new();
+        imfs.load_snapshot(
+            "/foo.json",
+            VfsSnapshot::file(r#"{ "speed": 16, "names": ["a", "b"] }"#),
+        )
+        .unwrap();
+
+        let mut vfs = Vfs::new(imfs.clone());
+
+        let context = InstanceContext {
+            typed_data_modules: true,
+            ..Default::default()
+        };
+
+        let instance_snapshot = snapshot_json(&context, &mut vfs, Path::new("/foo.json"), "foo")
+            .unwrap()
+            .unwrap();
+
+        insta::assert_yaml_snapshot!(instance_snapshot);
+    }
 }
@@ -1,23 +1,25 @@
-use std::{borrow::Cow, collections::HashMap, path::Path, str};
+use std::{
+    borrow::Cow,
+    collections::{BTreeMap, HashMap},
+    path::Path,
+    str,
+};
 
 use anyhow::Context;
 use memofs::Vfs;
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
 
 use crate::{
-    resolution::UnresolvedValue,
+    resolution::{apply_attributes, take_attributes, UnresolvedValue},
     snapshot::{InstanceContext, InstanceSnapshot},
 };
 
-use super::util::PathExt;
-
 pub fn snapshot_json_model(
     context: &InstanceContext,
     vfs: &Vfs,
     path: &Path,
+    name: &str,
 ) -> anyhow::Result<Option<InstanceSnapshot>> {
-    let name = path.file_name_trim_end(".model.json")?;
-
     let contents = vfs.read(path)?;
     let contents_str = str::from_utf8(&contents)
         .with_context(|| format!("File was not valid UTF-8: {}", path.display()))?;
@@ -43,16 +45,31 @@ pub fn snapshot_json_model(
     Ok(Some(snapshot))
 }
 
-#[derive(Debug, Deserialize)]
+/// The contents of a `.model.json` file.
+#[derive(Debug, Serialize, Deserialize)]
 #[serde(rename_all = "PascalCase")]
-struct JsonModel {
+pub struct JsonModel {
+    #[serde(skip_serializing_if = "Option::is_none")]
     name: Option<String>,
 
     #[serde(flatten)]
     core: JsonModelCore,
 }
 
-#[derive(Debug, Deserialize)]
+impl JsonModel {
+    /// Creates a JSON model describing the given snapshot and its descendants.
+    ///
+    /// The name of the model comes from its file name, so the snapshot's name
+    /// is not included.
+    pub fn from_snapshot(snapshot: &InstanceSnapshot) -> Self {
+        Self {
+            name: None,
+            core: JsonModelCore::from_snapshot(snapshot),
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
 #[serde(rename_all = "PascalCase")]
 struct JsonModelInstance {
     name: String,
@@ -61,7 +78,7 @@ struct JsonModelInstance {
     core: JsonModelCore,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Serialize, Deserialize)]
 #[serde(rename_all = "PascalCase")]
 struct JsonModelCore {
     class_name: String,
@@ -69,11 +86,40 @@ struct JsonModelCore {
     #[serde(default = "Vec::new", skip_serializing_if = "Vec::is_empty")]
     children: Vec<JsonModelInstance>,
 
-    #[serde(default = "HashMap::new", skip_serializing_if = "HashMap::is_empty")]
-    properties: HashMap<String, UnresolvedValue>,
+    #[serde(default = "BTreeMap::new", skip_serializing_if = "BTreeMap::is_empty")]
+    properties: BTreeMap<String, UnresolvedValue>,
+
+    #[serde(default = "BTreeMap::new", skip_serializing_if = "BTreeMap::is_empty")]
+    attributes: BTreeMap<String, UnresolvedValue>,
 }
 
 impl JsonModelCore {
+    fn from_snapshot(snapshot: &InstanceSnapshot) -> Self {
+        let children = snapshot
+            .children
+            .iter()
+            .map(|child| JsonModelInstance {
+                name: child.name.to_string(),
+                core: JsonModelCore::from_snapshot(child),
+            })
+            .collect();
+
+        let mut properties = snapshot.properties.clone();
+        let attributes = take_attributes(&mut properties);
+
+        let properties = properties
+            .into_iter()
+            .map(|(key, value)| (key, UnresolvedValue::FullyQualified(value)))
+            .collect();
+
+        Self {
+            class_name: snapshot.class_name.to_string(),
+            children,
+            properties,
+            attributes,
+        }
+    }
+
     fn into_snapshot(self, name: String) -> anyhow::Result<InstanceSnapshot> {
         let class_name = self.class_name;
 
@@ -88,6 +134,8 @@ impl JsonModelCore {
             properties.insert(key, value);
         }
 
+        apply_attributes(&mut properties, self.attributes)?;
+
         Ok(InstanceSnapshot {
             snapshot_id: None,
             metadata: Default::default(),
@@ -136,6 +184,7 @@ mod test {
             &InstanceContext::default(),
             &mut vfs,
             Path::new("/foo.model.json"),
+            "foo",
         )
         .unwrap()
         .unwrap();
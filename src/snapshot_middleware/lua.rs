@@ -9,6 +9,8 @@ use crate::snapshot::{InstanceContext, InstanceMetadata, InstanceSnapshot};
 use super::{dir::snapshot_dir, meta_file::AdjacentMetadata, util::match_trailing};
 
 /// Core routine for turning Lua files into snapshots.
+///
+/// Files ending in `.lua` and `.luau` are treated identically.
 pub fn snapshot_lua(
     context: &InstanceContext,
     vfs: &Vfs,
@@ -16,17 +18,48 @@ pub fn snapshot_lua(
 ) -> anyhow::Result<Option<InstanceSnapshot>> {
     let file_name = path.file_name().unwrap().to_string_lossy();
 
-    let (class_name, instance_name) = if let Some(name) = match_trailing(&file_name, ".server.lua")
+    let (stem, extension, other_extension) = if let Some(stem) = match_trailing(&file_name, ".lua")
     {
-        ("Script", name)
-    } else if let Some(name) = match_trailing(&file_name, ".client.lua") {
-        ("LocalScript", name)
-    } else if let Some(name) = match_trailing(&file_name, ".lua") {
-        ("ModuleScript", name)
+        (stem, "lua", "luau")
+    } else if let Some(stem) = match_trailing(&file_name, ".luau") {
+        (stem, "luau", "lua")
     } else {
         return Ok(None);
     };
 
+    let (class_name, instance_name) = script_class_and_name(stem);
+
+    // foo.lua and foo.luau would both turn into the same instance, so we
+    // refuse to guess which one the user meant.
+    let other_path = path.with_file_name(format!("{}.{}", stem, other_extension));
+    if vfs.metadata(&other_path).with_not_found()?.is_some() {
+        anyhow::bail!(
+            "Both {stem}.{extension} and {stem}.{other_extension} exist, and would both \
+             turn into the same instance.\n\
+             Rename or remove one of them.\n\
+             \n\
+             Conflicting files: {} and {}",
+            path.display(),
+            other_path.display(),
+            stem = stem,
+            extension = extension,
+            other_extension = other_extension,
+        );
+    }
+
+    snapshot_lua_script(context, vfs, path, instance_name, class_name)
+}
+
+/// Turns a Lua file into a script with the given name and ClassName, without
+/// looking at the file's name. Sync rules use this to decide on both
+/// themselves.
+pub fn snapshot_lua_script(
+    context: &InstanceContext,
+    vfs: &Vfs,
+    path: &Path,
+    instance_name: &str,
+    class_name: &str,
+) -> anyhow::Result<Option<InstanceSnapshot>> {
     let contents = vfs.read(path)?;
     let contents_str = str::from_utf8(&contents)
         .with_context(|| format!("File was not valid UTF-8: {}", path.display()))?
@@ -55,11 +88,27 @@ pub fn snapshot_lua(
     Ok(Some(snapshot))
 }
 
+/// Picks the ClassName of a script from the name of its file with the extension
+/// removed, returning it along with the name of the instance.
+///
+/// `foo.server` becomes a Script named `foo`, `foo.client` becomes a
+/// LocalScript, and anything else becomes a ModuleScript.
+pub fn script_class_and_name(stem: &str) -> (&'static str, &str) {
+    if let Some(name) = match_trailing(stem, ".server") {
+        ("Script", name)
+    } else if let Some(name) = match_trailing(stem, ".client") {
+        ("LocalScript", name)
+    } else {
+        ("ModuleScript", stem)
+    }
+}
+
 /// Attempts to snapshot an 'init' Lua script contained inside of a folder with
 /// the given name.
 ///
-/// Scripts named `init.lua`, `init.server.lua`, or `init.client.lua` usurp
-/// their parents, which acts similarly to `__init__.py` from the Python world.
+/// Scripts named `init.lua`, `init.server.lua`, or `init.client.lua` (or their
+/// `.luau` equivalents) usurp their parents, which acts similarly to
+/// `__init__.py` from the Python world.
 pub fn snapshot_lua_init(
     context: &InstanceContext,
     vfs: &Vfs,
@@ -70,9 +119,9 @@ pub fn snapshot_lua_init(
 
     if dir_snapshot.class_name != "Folder" {
         anyhow::bail!(
-            "init.lua, init.server.lua, and init.client.lua can \
-             only be used if the instance produced by the containing \
-             directory would be a Folder.\n\
+            "init.lua, init.server.lua, and init.client.lua (and their \
+             .luau equivalents) can only be used if the instance produced by \
+             the containing directory would be a Folder.\n\
              \n\
              The directory {} turned into an instance of class {}.",
             folder_path.display(),
@@ -149,6 +198,89 @@ mod test {
         insta::assert_yaml_snapshot!(instance_snapshot);
     }
 
+    #[test]
+    fn module_luau_from_vfs() {
+        let mut imfs = InMemoryFs::new();
+        imfs.load_snapshot("/foo.luau", VfsSnapshot::file("Hello there!"))
+            .unwrap();
+
+        let mut vfs = Vfs::new(imfs);
+
+        let instance_snapshot = snapshot_lua(
+            &InstanceContext::default(),
+            &mut vfs,
+            Path::new("/foo.luau"),
+        )
+        .unwrap()
+        .unwrap();
+
+        insta::assert_yaml_snapshot!(instance_snapshot);
+    }
+
+    #[test]
+    fn server_luau_from_vfs() {
+        let mut imfs = InMemoryFs::new();
+        imfs.load_snapshot("/foo.server.luau", VfsSnapshot::file("Hello there!"))
+            .unwrap();
+
+        let mut vfs = Vfs::new(imfs);
+
+        let instance_snapshot = snapshot_lua(
+            &InstanceContext::default(),
+            &mut vfs,
+            Path::new("/foo.server.luau"),
+        )
+        .unwrap()
+        .unwrap();
+
+        insta::assert_yaml_snapshot!(instance_snapshot);
+    }
+
+    #[test]
+    fn client_luau_from_vfs() {
+        let mut imfs = InMemoryFs::new();
+        imfs.load_snapshot("/foo.client.luau", VfsSnapshot::file("Hello there!"))
+            .unwrap();
+
+        let mut vfs = Vfs::new(imfs);
+
+        let instance_snapshot = snapshot_lua(
+            &InstanceContext::default(),
+            &mut vfs,
+            Path::new("/foo.client.luau"),
+        )
+        .unwrap()
+        .unwrap();
+
+        insta::assert_yaml_snapshot!(instance_snapshot);
+    }
+
+    #[test]
+    fn lua_and_luau_conflict() {
+        let mut imfs = InMemoryFs::new();
+        imfs.load_snapshot(
+            "/root",
+            VfsSnapshot::dir(hashmap! {
+                "foo.server.lua" => VfsSnapshot::file("Hello there!"),
+                "foo.server.luau" => VfsSnapshot::file("Hello there!"),
+            }),
+        )
+        .unwrap();
+
+        let mut vfs = Vfs::new(imfs);
+
+        let error = snapshot_lua(
+            &InstanceContext::default(),
+            &mut vfs,
+            Path::new("/root/foo.server.luau"),
+        )
+        .unwrap_err();
+
+        assert!(error
+            .to_string()
+            .contains("Both foo.server.luau and foo.server.lua exist"));
+    }
+
     #[ignore = "init.lua functionality has moved to the root snapshot function"]
     #[test]
     fn init_module_from_vfs() {
@@ -261,4 +393,25 @@ mod test {
             insta::assert_yaml_snapshot!(instance_snapshot);
         });
     }
+
+    #[test]
+    fn class_name_in_meta_is_ignored() {
+        let mut imfs = InMemoryFs::new();
+        imfs.load_snapshot("/foo.lua", VfsSnapshot::file("Hello there!"))
+            .unwrap();
+        imfs.load_snapshot(
+            "/foo.meta.json",
+            VfsSnapshot::file(r#"{"className": "Script"}"#),
+        )
+        .unwrap();
+
+        let mut vfs = Vfs::new(imfs);
+
+        let instance_snapshot =
+            snapshot_lua(&InstanceContext::default(), &mut vfs, Path::new("/foo.lua"))
+                .unwrap()
+                .unwrap();
+
+        assert_eq!(instance_snapshot.class_name, "ModuleScript");
+    }
 }
@@ -3,13 +3,16 @@ use std::{borrow::Cow, collections::HashMap, path::PathBuf};
 use anyhow::{format_err, Context};
 use serde::{Deserialize, Serialize};
 
-use crate::{resolution::UnresolvedValue, snapshot::InstanceSnapshot};
+use crate::{
+    resolution::{apply_attributes, UnresolvedValue},
+    snapshot::InstanceSnapshot,
+};
 
 /// Represents metadata in a sibling file with the same basename.
 ///
 /// As an example, hello.meta.json next to hello.lua would allow assigning
 /// additional metadata to the instance resulting from hello.lua.
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Default, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct AdjacentMetadata {
     #[serde(skip_serializing_if = "Option::is_none")]
@@ -18,6 +21,15 @@ pub struct AdjacentMetadata {
     #[serde(default, skip_serializing_if = "HashMap::is_empty")]
     pub properties: HashMap<String, UnresolvedValue>,
 
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub attributes: HashMap<String, UnresolvedValue>,
+
+    /// The class of the instance that holds the top-level instances of a model
+    /// file that has more than one. Model files take this out before applying
+    /// the rest of the metadata.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub class_name: Option<String>,
+
     #[serde(skip)]
     pub path: PathBuf,
 }
@@ -52,10 +64,23 @@ impl AdjacentMetadata {
             snapshot.properties.insert(key, value);
         }
 
+        apply_attributes(&mut snapshot.properties, self.attributes.drain())
+            .with_context(|| format!("error applying meta file {}", path.display()))?;
+
         Ok(())
     }
 
     pub fn apply_all(&mut self, snapshot: &mut InstanceSnapshot) -> anyhow::Result<()> {
+        // Model files use className before this, and other files have always
+        // ignored it.
+        if self.class_name.take().is_some() {
+            log::warn!(
+                "className in .meta.json files is ignored unless the file is a model \
+                 file with more than one top-level instance: {}",
+                self.path.display()
+            );
+        }
+
         self.apply_ignore_unknown_instances(snapshot);
         self.apply_properties(snapshot)?;
         Ok(())
@@ -69,7 +94,7 @@ impl AdjacentMetadata {
 /// folder.
 ///
 /// This is always sourced from a file named init.meta.json.
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Default, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct DirectoryMetadata {
     #[serde(skip_serializing_if = "Option::is_none")]
@@ -78,6 +103,9 @@ pub struct DirectoryMetadata {
     #[serde(default, skip_serializing_if = "HashMap::is_empty")]
     pub properties: HashMap<String, UnresolvedValue>,
 
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub attributes: HashMap<String, UnresolvedValue>,
+
     #[serde(skip_serializing_if = "Option::is_none")]
     pub class_name: Option<String>,
 
@@ -139,6 +167,9 @@ impl DirectoryMetadata {
             snapshot.properties.insert(key, value);
         }
 
+        apply_attributes(&mut snapshot.properties, self.attributes.drain())
+            .with_context(|| format!("error applying meta file {}", path.display()))?;
+
         Ok(())
     }
 }
@@ -14,12 +14,15 @@ mod meta_file;
 mod project;
 mod rbxm;
 mod rbxmx;
+mod toml;
 mod txt;
 mod util;
+mod yaml;
 
 use std::path::Path;
 
 use memofs::{IoResultExt, Vfs};
+use serde::{Deserialize, Serialize};
 
 use crate::snapshot::{InstanceContext, InstanceSnapshot};
 
@@ -28,15 +31,37 @@ use self::{
     dir::snapshot_dir,
     json::snapshot_json,
     json_model::snapshot_json_model,
-    lua::{snapshot_lua, snapshot_lua_init},
+    lua::{script_class_and_name, snapshot_lua, snapshot_lua_init, snapshot_lua_script},
     project::snapshot_project,
     rbxm::snapshot_rbxm,
     rbxmx::snapshot_rbxmx,
+    toml::snapshot_toml,
     txt::snapshot_txt,
     util::PathExt,
+    yaml::snapshot_yaml,
 };
 
-pub use self::project::snapshot_project_node;
+pub use self::{
+    csv::{
+        check_localization_csv, check_merged_localization, merge_localization_tables,
+        read_localization_contents, read_localization_csv_flexible, write_localization_csv,
+        LocalizationIssue,
+    },
+    json_model::JsonModel,
+    meta_file::{AdjacentMetadata, DirectoryMetadata},
+    project::snapshot_project_node,
+};
+
+/// The names of scripts that, when placed inside a directory, turn that
+/// directory into a script. Checked in order.
+pub const INIT_SCRIPT_NAMES: &[&str] = &[
+    "init.lua",
+    "init.server.lua",
+    "init.client.lua",
+    "init.luau",
+    "init.server.luau",
+    "init.client.luau",
+];
 
 /// The main entrypoint to the snapshot function. This function can be pointed
 /// at any path and will return something if Rojo knows how to deal with it.
@@ -56,50 +81,119 @@ pub fn snapshot_from_vfs(
             return snapshot_project(context, vfs, &project_path);
         }
 
-        let init_path = path.join("init.lua");
-        if vfs.metadata(&init_path).with_not_found()?.is_some() {
-            return snapshot_lua_init(context, vfs, &init_path);
+        for init_name in INIT_SCRIPT_NAMES {
+            let init_path = path.join(init_name);
+            if vfs.metadata(&init_path).with_not_found()?.is_some() {
+                return snapshot_lua_init(context, vfs, &init_path);
+            }
         }
 
-        let init_path = path.join("init.server.lua");
-        if vfs.metadata(&init_path).with_not_found()?.is_some() {
-            return snapshot_lua_init(context, vfs, &init_path);
-        }
+        snapshot_dir(context, vfs, path)
+    } else {
+        let file_name = path
+            .file_name()
+            .and_then(|name| name.to_str())
+            .unwrap_or_default();
 
-        let init_path = path.join("init.client.lua");
-        if vfs.metadata(&init_path).with_not_found()?.is_some() {
-            return snapshot_lua_init(context, vfs, &init_path);
+        // .meta.json files and init scripts do not turn into their own
+        // instances. They're picked up by the instances they describe.
+        if file_name.ends_with(".meta.json") || INIT_SCRIPT_NAMES.contains(&file_name) {
+            return Ok(None);
         }
 
-        snapshot_dir(context, vfs, path)
-    } else {
-        if let Ok(name) = path.file_name_trim_end(".lua") {
-            match name {
-                // init scripts are handled elsewhere and should not turn into
-                // their own children.
-                "init" | "init.client" | "init.server" => return Ok(None),
+        if let Some(rule) = context.get_sync_rule(path) {
+            let name = rule.file_name_for_path(path)?;
+            return rule.middleware.snapshot(context, vfs, path, name);
+        }
 
-                _ => return snapshot_lua(context, vfs, path),
-            }
+        if path.file_name_ends_with(".lua") || path.file_name_ends_with(".luau") {
+            return snapshot_lua(context, vfs, path);
         } else if path.file_name_ends_with(".project.json") {
             return snapshot_project(context, vfs, path);
         } else if path.file_name_ends_with(".model.json") {
-            return snapshot_json_model(context, vfs, path);
-        } else if path.file_name_ends_with(".meta.json") {
-            // .meta.json files do not turn into their own instances.
-            return Ok(None);
+            let name = path.file_name_trim_end(".model.json")?;
+            return snapshot_json_model(context, vfs, path, name);
         } else if path.file_name_ends_with(".json") {
-            return snapshot_json(context, vfs, path);
+            let name = path.file_name_trim_end(".json")?;
+            return snapshot_json(context, vfs, path, name);
+        } else if path.file_name_ends_with(".toml") {
+            let name = path.file_name_trim_end(".toml")?;
+            return snapshot_toml(context, vfs, path, name);
+        } else if path.file_name_ends_with(".yaml") {
+            let name = path.file_name_trim_end(".yaml")?;
+            return snapshot_yaml(context, vfs, path, name);
+        } else if path.file_name_ends_with(".yml") {
+            let name = path.file_name_trim_end(".yml")?;
+            return snapshot_yaml(context, vfs, path, name);
         } else if path.file_name_ends_with(".csv") {
-            return snapshot_csv(context, vfs, path);
+            let name = path.file_name_trim_end(".csv")?;
+            return snapshot_csv(context, vfs, path, name);
         } else if path.file_name_ends_with(".txt") {
-            return snapshot_txt(context, vfs, path);
+            let name = path.file_name_trim_end(".txt")?;
+            return snapshot_txt(context, vfs, path, name);
         } else if path.file_name_ends_with(".rbxmx") {
-            return snapshot_rbxmx(context, vfs, path);
+            let name = path.file_name_trim_end(".rbxmx")?;
+            return snapshot_rbxmx(context, vfs, path, name);
         } else if path.file_name_ends_with(".rbxm") {
-            return snapshot_rbxm(context, vfs, path);
+            let name = path.file_name_trim_end(".rbxm")?;
+            return snapshot_rbxm(context, vfs, path, name);
         }
 
         Ok(None)
     }
 }
+
+/// The ways of turning a file into an instance that sync rules can choose
+/// between.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum Middleware {
+    /// A script whose ClassName depends on whether its name ends in `.server`
+    /// or `.client`, like `.lua` files.
+    Lua,
+    ModuleScript,
+    ServerScript,
+    ClientScript,
+    Json,
+    Toml,
+    Yaml,
+    JsonModel,
+    Text,
+    Csv,
+    Rbxm,
+    Rbxmx,
+}
+
+impl Middleware {
+    /// Snapshots the file at the given path into an instance with the given
+    /// name using this middleware.
+    pub fn snapshot(
+        self,
+        context: &InstanceContext,
+        vfs: &Vfs,
+        path: &Path,
+        name: &str,
+    ) -> anyhow::Result<Option<InstanceSnapshot>> {
+        match self {
+            Middleware::Lua => {
+                let (class_name, name) = script_class_and_name(name);
+                snapshot_lua_script(context, vfs, path, name, class_name)
+            }
+            Middleware::ModuleScript => {
+                snapshot_lua_script(context, vfs, path, name, "ModuleScript")
+            }
+            Middleware::ServerScript => snapshot_lua_script(context, vfs, path, name, "Script"),
+            Middleware::ClientScript => {
+                snapshot_lua_script(context, vfs, path, name, "LocalScript")
+            }
+            Middleware::Json => snapshot_json(context, vfs, path, name),
+            Middleware::Toml => snapshot_toml(context, vfs, path, name),
+            Middleware::Yaml => snapshot_yaml(context, vfs, path, name),
+            Middleware::JsonModel => snapshot_json_model(context, vfs, path, name),
+            Middleware::Text => snapshot_txt(context, vfs, path, name),
+            Middleware::Csv => snapshot_csv(context, vfs, path, name),
+            Middleware::Rbxm => snapshot_rbxm(context, vfs, path, name),
+            Middleware::Rbxmx => snapshot_rbxmx(context, vfs, path, name),
+        }
+    }
+}
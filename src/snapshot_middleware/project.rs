@@ -1,13 +1,16 @@
-use std::{borrow::Cow, collections::HashMap, path::Path};
+use std::{borrow::Cow, collections::HashMap, env, path::Path};
 
 use anyhow::{bail, Context};
 use memofs::Vfs;
+use rbx_dom_weak::types::Variant;
 use rbx_reflection::ClassTag;
 
 use crate::{
     project::{Project, ProjectNode},
+    resolution::{apply_attributes, AmbiguousValue, UnresolvedValue},
     snapshot::{
         InstanceContext, InstanceMetadata, InstanceSnapshot, InstigatingSource, PathIgnoreRule,
+        SyncRule,
     },
 };
 
@@ -18,22 +21,60 @@ pub fn snapshot_project(
     vfs: &Vfs,
     path: &Path,
 ) -> anyhow::Result<Option<InstanceSnapshot>> {
-    let project = Project::load_from_slice(&vfs.read(path)?, path)
+    let mut project = Project::load_from_slice(&vfs.read(path)?, path)
+        .and_then(|project| project.resolve_extends(vfs))
         .with_context(|| format!("File was not a valid Rojo project: {}", path.display()))?;
 
+    // Nested projects don't have to define every profile that the root
+    // project does.
+    if let Some(profile) = &context.profile {
+        if project.has_profile(profile) {
+            project.apply_profile(profile)?;
+        }
+    }
+
     let mut context = context.clone();
 
-    let rules = project.glob_ignore_paths.iter().map(|glob| PathIgnoreRule {
-        glob: glob.clone(),
-        base_path: project.folder_location().to_path_buf(),
-    });
+    let rules = project
+        .glob_ignore_paths
+        .iter()
+        .map(|glob| PathIgnoreRule {
+            glob: glob.clone(),
+            base_path: project.folder_location().to_path_buf(),
+        })
+        .chain(project.extended_ignore_rules.iter().cloned())
+        .collect::<Vec<_>>();
 
     context.add_path_ignore_rules(rules);
 
-    // TODO: If this project node is a path to an instance that Rojo doesn't
-    // understand, this may panic!
+    // Rules inherited from extended projects already know which folder
+    // they're relative to.
+    let sync_rules = project.sync_rules.iter().map(|rule| {
+        if rule.base_path.as_os_str().is_empty() {
+            SyncRule {
+                base_path: project.folder_location().to_path_buf(),
+                ..rule.clone()
+            }
+        } else {
+            rule.clone()
+        }
+    });
+
+    context.add_sync_rules(sync_rules);
+
+    if let Some(typed) = project.typed_data_modules {
+        context.typed_data_modules = typed;
+    }
+
     let mut snapshot =
-        snapshot_project_node(&context, path, &project.name, &project.tree, vfs, None)?.unwrap();
+        snapshot_project_node(&context, path, &project.name, &project.tree, vfs, None)?
+            .with_context(|| {
+                format!(
+                    "The root of project {} did not turn into an instance. \
+                     Its $path must point to a file or folder that Rojo can sync.",
+                    path.display()
+                )
+            })?;
 
     // Setting the instigating source to the project file path is a little
     // coarse.
@@ -53,6 +94,13 @@ pub fn snapshot_project(
     // file being updated.
     snapshot.metadata.relevant_paths.push(path.to_path_buf());
 
+    // Projects that this project extends are part of the root node too, so
+    // changing any of them should also cause the project to be reloaded.
+    snapshot
+        .metadata
+        .relevant_paths
+        .extend(project.extended_files.iter().cloned());
+
     Ok(Some(snapshot))
 }
 
@@ -77,6 +125,10 @@ pub fn snapshot_project_node(
     let mut children = Vec::new();
     let mut metadata = InstanceMetadata::default();
 
+    // Project nodes can be snapshotted again on their own, which needs to
+    // happen with the same profile.
+    metadata.context.profile = context.profile.clone();
+
     if let Some(path) = &node.path {
         // If the path specified in the project is relative, we assume it's
         // relative to the folder that the project is in, project_folder.
@@ -194,12 +246,13 @@ pub fn snapshot_project_node(
     }
 
     for (key, unresolved) in &node.properties {
-        let value = unresolved
-            .clone()
-            .resolve(&class_name, key)
+        let value = interpolate_env(unresolved.clone())
+            .and_then(|value| value.resolve(&class_name, key))
             .with_context(|| {
                 format!(
-                    "Unresolvable property in project at path {}",
+                    "Unresolvable property {}.{} in project at path {}",
+                    instance_name,
+                    key,
                     project_path.display()
                 )
             })?;
@@ -221,6 +274,29 @@ pub fn snapshot_project_node(
         properties.insert(key.clone(), value);
     }
 
+    let attributes = node
+        .attributes
+        .iter()
+        .map(|(key, unresolved)| {
+            let value = interpolate_env(unresolved.clone()).with_context(|| {
+                format!(
+                    "Unresolvable attribute {} in project at path {}",
+                    key,
+                    project_path.display()
+                )
+            })?;
+
+            Ok((key.clone(), value))
+        })
+        .collect::<anyhow::Result<Vec<_>>>()?;
+
+    apply_attributes(&mut properties, attributes).with_context(|| {
+        format!(
+            "Unresolvable attribute in project at path {}",
+            project_path.display()
+        )
+    })?;
+
     // If the user specified $ignoreUnknownInstances, overwrite the existing
     // value.
     //
@@ -238,7 +314,7 @@ pub fn snapshot_project_node(
     metadata.instigating_source = Some(InstigatingSource::ProjectNode(
         project_path.to_path_buf(),
         instance_name.to_string(),
-        node.clone(),
+        Box::new(node.clone()),
         parent_class.map(|name| name.to_owned()),
     ));
 
@@ -252,6 +328,68 @@ pub fn snapshot_project_node(
     }))
 }
 
+/// Replaces every `${env:VAR}` in the string parts of a property or attribute
+/// value with the value of the environment variable `VAR`.
+fn interpolate_env(value: UnresolvedValue) -> anyhow::Result<UnresolvedValue> {
+    Ok(match value {
+        UnresolvedValue::FullyQualified(Variant::String(value)) => {
+            UnresolvedValue::FullyQualified(Variant::String(interpolate_env_str(&value)?))
+        }
+        UnresolvedValue::Ambiguous(AmbiguousValue::String(value)) => {
+            UnresolvedValue::Ambiguous(AmbiguousValue::String(interpolate_env_str(&value)?))
+        }
+        UnresolvedValue::Ambiguous(AmbiguousValue::StringArray(values)) => {
+            let values = values
+                .iter()
+                .map(|value| interpolate_env_str(value))
+                .collect::<anyhow::Result<_>>()?;
+
+            UnresolvedValue::Ambiguous(AmbiguousValue::StringArray(values))
+        }
+        other => other,
+    })
+}
+
+fn interpolate_env_str(source: &str) -> anyhow::Result<String> {
+    const PREFIX: &str = "${env:";
+
+    let mut output = String::with_capacity(source.len());
+    let mut rest = source;
+
+    while let Some(start) = rest.find(PREFIX) {
+        output.push_str(&rest[..start]);
+
+        let after_prefix = &rest[start + PREFIX.len()..];
+        let end = match after_prefix.find('}') {
+            Some(end) => end,
+            None => bail!(
+                "Unterminated environment variable reference in {:?}",
+                source
+            ),
+        };
+
+        let name = &after_prefix[..end];
+        if name.is_empty() {
+            bail!("Empty environment variable reference in {:?}", source);
+        }
+
+        match env::var(name) {
+            Ok(value) => output.push_str(&value),
+            Err(env::VarError::NotPresent) => {
+                bail!("Environment variable {} is not set", name)
+            }
+            Err(env::VarError::NotUnicode(_)) => {
+                bail!("Environment variable {} is not valid UTF-8", name)
+            }
+        }
+
+        rest = &after_prefix[end + 1..];
+    }
+
+    output.push_str(rest);
+    Ok(output)
+}
+
 fn infer_class_name(name: &str, parent_class: Option<&str>) -> Option<Cow<'static, str>> {
     // If className wasn't defined from another source, we may be able
     // to infer one.
@@ -285,6 +423,7 @@ mod test {
 
     use maplit::hashmap;
     use memofs::{InMemoryFs, VfsSnapshot};
+    use rbx_dom_weak::types::Attributes;
 
     #[ignore = "Functionality moved to root snapshot middleware"]
     #[test]
@@ -350,6 +489,66 @@ mod test {
         insta::assert_yaml_snapshot!(instance_snapshot);
     }
 
+    #[test]
+    fn project_with_nested_sync_rules() {
+        let _ = env_logger::try_init();
+
+        let mut imfs = InMemoryFs::new();
+        imfs.load_snapshot(
+            "/foo",
+            VfsSnapshot::dir(hashmap! {
+                "default.project.json" => VfsSnapshot::file(r#"
+                    {
+                        "name": "outer",
+                        "tree": {
+                            "$className": "Folder",
+                            "README": {
+                                "$path": "README.md"
+                            },
+                            "nested": {
+                                "$path": "nested"
+                            }
+                        },
+                        "syncRules": [
+                            { "pattern": "*.md", "use": "text" }
+                        ]
+                    }
+                "#),
+                "README.md" => VfsSnapshot::file("Outer readme"),
+                "nested" => VfsSnapshot::dir(hashmap! {
+                    "default.project.json" => VfsSnapshot::file(r#"
+                        {
+                            "name": "inner",
+                            "tree": {
+                                "$className": "Folder",
+                                "notes": {
+                                    "$path": "notes.md"
+                                }
+                            },
+                            "syncRules": [
+                                { "pattern": "*.md", "use": "moduleScript" }
+                            ]
+                        }
+                    "#),
+                    "notes.md" => VfsSnapshot::file("return 'inner'"),
+                }),
+            }),
+        )
+        .unwrap();
+
+        let mut vfs = Vfs::new(imfs);
+
+        let instance_snapshot = snapshot_project(
+            &InstanceContext::default(),
+            &mut vfs,
+            Path::new("/foo/default.project.json"),
+        )
+        .expect("snapshot error")
+        .expect("snapshot returned no instances");
+
+        insta::assert_yaml_snapshot!(instance_snapshot);
+    }
+
     #[test]
     fn project_with_resolved_properties() {
         let _ = env_logger::try_init();
@@ -630,4 +829,234 @@ mod test {
 
         insta::assert_yaml_snapshot!(instance_snapshot);
     }
+
+    #[test]
+    fn project_extends_cycle() {
+        let _ = env_logger::try_init();
+
+        let mut imfs = InMemoryFs::new();
+        imfs.load_snapshot(
+            "/foo",
+            VfsSnapshot::dir(hashmap! {
+                "a.project.json" => VfsSnapshot::file(r#"
+                    {
+                        "name": "a",
+                        "extends": "nested/b.project.json",
+                        "tree": {
+                            "$className": "Folder"
+                        }
+                    }
+                "#),
+                "nested" => VfsSnapshot::dir(hashmap! {
+                    "b.project.json" => VfsSnapshot::file(r#"
+                        {
+                            "name": "b",
+                            "extends": "../a.project.json",
+                            "tree": {
+                                "$className": "Folder"
+                            }
+                        }
+                    "#),
+                }),
+            }),
+        )
+        .unwrap();
+
+        let vfs = Vfs::new(imfs);
+
+        let err = snapshot_project(
+            &InstanceContext::default(),
+            &vfs,
+            Path::new("/foo/a.project.json"),
+        )
+        .unwrap_err();
+
+        assert_eq!(
+            err.root_cause().to_string(),
+            "Rojo projects cannot extend each other in a cycle: \
+             /foo/a.project.json -> /foo/nested/b.project.json -> /foo/a.project.json"
+        );
+    }
+
+    #[test]
+    fn project_with_profile() {
+        let _ = env_logger::try_init();
+
+        let mut imfs = InMemoryFs::new();
+        imfs.load_snapshot(
+            "/foo",
+            VfsSnapshot::dir(hashmap! {
+                "default.project.json" => VfsSnapshot::file(r#"
+                    {
+                        "name": "profiles",
+                        "tree": {
+                            "$className": "Folder",
+                            "Config": {
+                                "$className": "StringValue",
+                                "$properties": {
+                                    "Value": "production"
+                                }
+                            },
+                            "Message": {
+                                "$path": "production.txt"
+                            },
+                            "DevTools": {
+                                "$className": "Folder"
+                            }
+                        },
+                        "profiles": {
+                            "dev": {
+                                "tree": {
+                                    "Config": {
+                                        "$properties": {
+                                            "Value": "dev"
+                                        }
+                                    },
+                                    "Message": {
+                                        "$path": "dev.txt"
+                                    }
+                                }
+                            },
+                            "release": {
+                                "exclude": ["DevTools"]
+                            }
+                        }
+                    }
+                "#),
+                "production.txt" => VfsSnapshot::file("Hello from production"),
+                "dev.txt" => VfsSnapshot::file("Hello from dev"),
+            }),
+        )
+        .unwrap();
+
+        let vfs = Vfs::new(imfs);
+
+        for profile in &["dev", "release"] {
+            let context = InstanceContext {
+                profile: Some((*profile).into()),
+                ..Default::default()
+            };
+
+            let instance_snapshot =
+                snapshot_project(&context, &vfs, Path::new("/foo/default.project.json"))
+                    .expect("snapshot error")
+                    .expect("snapshot returned no instances");
+
+            insta::assert_yaml_snapshot!(
+                format!("project_with_profile_{}", profile),
+                instance_snapshot
+            );
+        }
+    }
+
+    #[test]
+    fn project_with_env_interpolation() {
+        let _ = env_logger::try_init();
+
+        std::env::set_var("ROJO_TEST_PROJECT_GREETING", "Hello");
+
+        let mut imfs = InMemoryFs::new();
+        imfs.load_snapshot(
+            "/foo.project.json",
+            VfsSnapshot::file(
+                r#"
+                    {
+                        "name": "env-interpolation",
+                        "tree": {
+                            "$className": "StringValue",
+                            "$properties": {
+                                "Value": "${env:ROJO_TEST_PROJECT_GREETING}, world!"
+                            },
+                            "$attributes": {
+                                "Greeting": "${env:ROJO_TEST_PROJECT_GREETING}"
+                            }
+                        }
+                    }
+                "#,
+            ),
+        )
+        .unwrap();
+
+        let vfs = Vfs::new(imfs);
+
+        let instance_snapshot = snapshot_project(
+            &InstanceContext::default(),
+            &vfs,
+            Path::new("/foo.project.json"),
+        )
+        .expect("snapshot error")
+        .expect("snapshot returned no instances");
+
+        assert_eq!(
+            instance_snapshot.properties.get("Value"),
+            Some(&Variant::String("Hello, world!".to_owned()))
+        );
+
+        let attributes = match instance_snapshot.properties.get("Attributes") {
+            Some(Variant::BinaryString(encoded)) => {
+                Attributes::from_reader(AsRef::<[u8]>::as_ref(encoded)).unwrap()
+            }
+            other => panic!("expected encoded attributes, got {:?}", other),
+        };
+
+        assert_eq!(
+            attributes.get("Greeting"),
+            Some(&Variant::BinaryString(b"Hello".to_vec().into()))
+        );
+    }
+
+    #[test]
+    fn project_with_unset_env_var() {
+        let _ = env_logger::try_init();
+
+        let mut imfs = InMemoryFs::new();
+        imfs.load_snapshot(
+            "/foo.project.json",
+            VfsSnapshot::file(
+                r#"
+                    {
+                        "name": "unset-env",
+                        "tree": {
+                            "$className": "StringValue",
+                            "$properties": {
+                                "Value": "${env:ROJO_TEST_PROJECT_UNSET_VAR}"
+                            }
+                        }
+                    }
+                "#,
+            ),
+        )
+        .unwrap();
+
+        let vfs = Vfs::new(imfs);
+
+        let err = snapshot_project(
+            &InstanceContext::default(),
+            &vfs,
+            Path::new("/foo.project.json"),
+        )
+        .unwrap_err();
+
+        assert_eq!(
+            format!("{:#}", err),
+            "Unresolvable property unset-env.Value in project at path /foo.project.json: \
+             Environment variable ROJO_TEST_PROJECT_UNSET_VAR is not set"
+        );
+    }
+
+    #[test]
+    fn interpolate_env_strings() {
+        std::env::set_var("ROJO_TEST_INTERPOLATE_A", "a");
+        std::env::set_var("ROJO_TEST_INTERPOLATE_B", "b");
+
+        assert_eq!(
+            interpolate_env_str("${env:ROJO_TEST_INTERPOLATE_A}/${env:ROJO_TEST_INTERPOLATE_B}!")
+                .unwrap(),
+            "a/b!"
+        );
+        assert_eq!(interpolate_env_str("no variables").unwrap(), "no variables");
+        assert_eq!(interpolate_env_str("$5 {env:}").unwrap(), "$5 {env:}");
+        assert!(interpolate_env_str("${env:ROJO_TEST_INTERPOLATE_A").is_err());
+        assert!(interpolate_env_str("${env:}").is_err());
+    }
 }
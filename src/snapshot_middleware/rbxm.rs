@@ -1,43 +1,88 @@
 use std::path::Path;
 
-use anyhow::Context;
-use memofs::Vfs;
+use anyhow::{bail, Context};
+use memofs::{IoResultExt, Vfs};
+use rbx_dom_weak::WeakDom;
 
 use crate::snapshot::{InstanceContext, InstanceMetadata, InstanceSnapshot};
 
-use super::util::PathExt;
+use super::meta_file::AdjacentMetadata;
 
 pub fn snapshot_rbxm(
     context: &InstanceContext,
     vfs: &Vfs,
     path: &Path,
+    name: &str,
 ) -> anyhow::Result<Option<InstanceSnapshot>> {
-    let name = path.file_name_trim_end(".rbxm")?;
-
     let temp_tree = rbx_binary::from_reader(vfs.read(path)?.as_slice())
         .with_context(|| format!("Malformed rbxm file: {}", path.display()))?;
 
-    let root_instance = temp_tree.root();
-    let children = root_instance.children();
-
-    if children.len() == 1 {
-        let snapshot = InstanceSnapshot::from_tree(&temp_tree, children[0])
-            .name(name)
-            .metadata(
-                InstanceMetadata::new()
-                    .instigating_source(path)
-                    .relevant_paths(vec![path.to_path_buf()])
-                    .context(context),
-            );
-
-        Ok(Some(snapshot))
-    } else {
-        anyhow::bail!(
-            "Rojo currently only supports model files with one top-level instance.\n\n \
-             Check the model file at path {}",
+    snapshot_model(context, vfs, path, name, &temp_tree)
+}
+
+/// Turns the instances in a decoded model file into a snapshot.
+///
+/// Model files with one top-level instance turn into that instance. Model
+/// files with several top-level instances turn into a Folder that contains
+/// them, or an instance of the class given by `className` in the model's
+/// `.meta.json` file.
+pub fn snapshot_model(
+    context: &InstanceContext,
+    vfs: &Vfs,
+    path: &Path,
+    name: &str,
+    tree: &WeakDom,
+) -> anyhow::Result<Option<InstanceSnapshot>> {
+    let meta_path = path.with_file_name(format!("{}.meta.json", name));
+
+    let mut metadata = match vfs.read(&meta_path).with_not_found()? {
+        Some(meta_contents) => AdjacentMetadata::from_slice(&meta_contents, meta_path.clone())?,
+        None => AdjacentMetadata::default(),
+    };
+
+    let snapshot = match tree.root().children() {
+        [] => bail!(
+            "Model file at path {} does not contain any instances",
             path.display()
-        );
-    }
+        ),
+        [root] => {
+            if metadata.class_name.is_some() {
+                bail!(
+                    "className in .meta.json files can only be specified for model \
+                     files with more than one top-level instance: {}",
+                    meta_path.display()
+                );
+            }
+
+            InstanceSnapshot::from_tree(tree, *root)
+        }
+        roots => {
+            let class_name = metadata
+                .class_name
+                .take()
+                .unwrap_or_else(|| "Folder".to_owned());
+
+            let children: Vec<_> = roots
+                .iter()
+                .map(|&root| InstanceSnapshot::from_tree(tree, root))
+                .collect();
+
+            InstanceSnapshot::new()
+                .class_name(class_name)
+                .children(children)
+        }
+    };
+
+    let mut snapshot = snapshot.name(name).metadata(
+        InstanceMetadata::new()
+            .instigating_source(path)
+            .relevant_paths(vec![path.to_path_buf(), meta_path])
+            .context(context),
+    );
+
+    metadata.apply_all(&mut snapshot)?;
+
+    Ok(Some(snapshot))
 }
 
 #[cfg(test)]
@@ -61,6 +106,7 @@ mod test {
             &InstanceContext::default(),
             &mut vfs,
             Path::new("/foo.rbxm"),
+            "foo",
         )
         .unwrap()
         .unwrap();
@@ -3,50 +3,30 @@ use std::path::Path;
 use anyhow::Context;
 use memofs::Vfs;
 
-use crate::snapshot::{InstanceContext, InstanceMetadata, InstanceSnapshot};
+use crate::snapshot::{InstanceContext, InstanceSnapshot};
 
-use super::util::PathExt;
+use super::rbxm::snapshot_model;
 
 pub fn snapshot_rbxmx(
     context: &InstanceContext,
     vfs: &Vfs,
     path: &Path,
+    name: &str,
 ) -> anyhow::Result<Option<InstanceSnapshot>> {
-    let name = path.file_name_trim_end(".rbxmx")?;
-
     let options = rbx_xml::DecodeOptions::new()
         .property_behavior(rbx_xml::DecodePropertyBehavior::ReadUnknown);
 
     let temp_tree = rbx_xml::from_reader(vfs.read(path)?.as_slice(), options)
         .with_context(|| format!("Malformed rbxm file: {}", path.display()))?;
 
-    let root_instance = temp_tree.root();
-    let children = root_instance.children();
-
-    if children.len() == 1 {
-        let snapshot = InstanceSnapshot::from_tree(&temp_tree, children[0])
-            .name(name)
-            .metadata(
-                InstanceMetadata::new()
-                    .instigating_source(path)
-                    .relevant_paths(vec![path.to_path_buf()])
-                    .context(context),
-            );
-
-        Ok(Some(snapshot))
-    } else {
-        anyhow::bail!(
-            "Rojo currently only supports model files with one top-level instance.\n\n \
-             Check the model file at path {}",
-            path.display()
-        );
-    }
+    snapshot_model(context, vfs, path, name, &temp_tree)
 }
 
 #[cfg(test)]
 mod test {
     use super::*;
 
+    use maplit::hashmap;
     use memofs::{InMemoryFs, VfsSnapshot};
 
     #[test]
@@ -74,6 +54,7 @@ mod test {
             &InstanceContext::default(),
             &mut vfs,
             Path::new("/foo.rbxmx"),
+            "foo",
         )
         .unwrap()
         .unwrap();
@@ -83,4 +64,112 @@ mod test {
         assert_eq!(instance_snapshot.properties, Default::default());
         assert_eq!(instance_snapshot.children, Vec::new());
     }
+
+    const TWO_ROOTS: &str = r#"
+        <roblox version="4">
+            <Item class="Part" referent="0">
+                <Properties>
+                    <string name="Name">Left</string>
+                </Properties>
+            </Item>
+            <Item class="Part" referent="1">
+                <Properties>
+                    <string name="Name">Right</string>
+                </Properties>
+            </Item>
+        </roblox>
+    "#;
+
+    #[test]
+    fn multiple_roots() {
+        let mut imfs = InMemoryFs::new();
+        imfs.load_snapshot("/foo.rbxmx", VfsSnapshot::file(TWO_ROOTS))
+            .unwrap();
+
+        let vfs = Vfs::new(imfs);
+
+        let instance_snapshot = snapshot_rbxmx(
+            &InstanceContext::default(),
+            &vfs,
+            Path::new("/foo.rbxmx"),
+            "foo",
+        )
+        .unwrap()
+        .unwrap();
+
+        assert_eq!(instance_snapshot.name, "foo");
+        assert_eq!(instance_snapshot.class_name, "Folder");
+
+        let children: Vec<_> = instance_snapshot
+            .children
+            .iter()
+            .map(|child| (child.name.as_ref(), child.class_name.as_ref()))
+            .collect();
+        assert_eq!(children, vec![("Left", "Part"), ("Right", "Part")]);
+    }
+
+    #[test]
+    fn multiple_roots_with_class_name() {
+        let mut imfs = InMemoryFs::new();
+        imfs.load_snapshot(
+            "/root",
+            VfsSnapshot::dir(hashmap! {
+                "foo.rbxmx" => VfsSnapshot::file(TWO_ROOTS),
+                "foo.meta.json" => VfsSnapshot::file(r#"{"className": "Model"}"#),
+            }),
+        )
+        .unwrap();
+
+        let vfs = Vfs::new(imfs);
+
+        let instance_snapshot = snapshot_rbxmx(
+            &InstanceContext::default(),
+            &vfs,
+            Path::new("/root/foo.rbxmx"),
+            "foo",
+        )
+        .unwrap()
+        .unwrap();
+
+        assert_eq!(instance_snapshot.name, "foo");
+        assert_eq!(instance_snapshot.class_name, "Model");
+        assert_eq!(instance_snapshot.children.len(), 2);
+    }
+
+    #[test]
+    fn one_root_with_class_name() {
+        let mut imfs = InMemoryFs::new();
+        imfs.load_snapshot(
+            "/root",
+            VfsSnapshot::dir(hashmap! {
+                "foo.rbxmx" => VfsSnapshot::file(r#"
+                    <roblox version="4">
+                        <Item class="Folder" referent="0">
+                            <Properties>
+                                <string name="Name">Folder</string>
+                            </Properties>
+                        </Item>
+                    </roblox>
+                "#),
+                "foo.meta.json" => VfsSnapshot::file(r#"{"className": "Model"}"#),
+            }),
+        )
+        .unwrap();
+
+        let vfs = Vfs::new(imfs);
+
+        let err = snapshot_rbxmx(
+            &InstanceContext::default(),
+            &vfs,
+            Path::new("/root/foo.rbxmx"),
+            "foo",
+        )
+        .unwrap_err();
+
+        assert_eq!(
+            err.to_string(),
+            "className in .meta.json files can only be specified for model files \
+             with more than one top-level instance: /root/foo.meta.json"
+        );
+    }
 }
@@ -0,0 +1,139 @@
+use std::{path::Path, str};
+
+use anyhow::Context;
+use maplit::hashmap;
+use memofs::{IoResultExt, Vfs};
+
+use crate::{
+    lua_ast::{data_module_source, Expression},
+    snapshot::{InstanceContext, InstanceMetadata, InstanceSnapshot},
+};
+
+use super::meta_file::AdjacentMetadata;
+
+pub fn snapshot_toml(
+    context: &InstanceContext,
+    vfs: &Vfs,
+    path: &Path,
+    name: &str,
+) -> anyhow::Result<Option<InstanceSnapshot>> {
+    let contents = vfs.read(path)?;
+    let contents_str = str::from_utf8(&contents)
+        .with_context(|| format!("File was not valid UTF-8: {}", path.display()))?;
+
+    let value: toml::Value = toml::from_str(contents_str)
+        .with_context(|| format!("File contains malformed TOML: {}", path.display()))?;
+
+    let as_lua = data_module_source(toml_to_lua_value(value), context.typed_data_modules);
+
+    let properties = hashmap! {
+        "Source".to_owned() => as_lua.into(),
+    };
+
+    let meta_path = path.with_file_name(format!("{}.meta.json", name));
+
+    let mut snapshot = InstanceSnapshot::new()
+        .name(name)
+        .class_name("ModuleScript")
+        .properties(properties)
+        .metadata(
+            InstanceMetadata::new()
+                .instigating_source(path)
+                .relevant_paths(vec![path.to_path_buf(), meta_path.clone()])
+                .context(context),
+        );
+
+    if let Some(meta_contents) = vfs.read(&meta_path).with_not_found()? {
+        let mut metadata = AdjacentMetadata::from_slice(&meta_contents, meta_path)?;
+        metadata.apply_all(&mut snapshot)?;
+    }
+
+    Ok(Some(snapshot))
+}
+
+fn toml_to_lua_value(value: toml::Value) -> Expression {
+    use toml::Value;
+
+    match value {
+        Value::Boolean(value) => Expression::Bool(value),
+        Value::Integer(value) => Expression::Number(value as f64),
+        Value::Float(value) => Expression::Number(value),
+        Value::String(value) => Expression::String(value),
+        // Lua has no date type, so dates are kept in the same format they
+        // were written in.
+        Value::Datetime(value) => Expression::String(value.to_string()),
+        Value::Array(values) => {
+            Expression::Array(values.into_iter().map(toml_to_lua_value).collect())
+        }
+        Value::Table(values) => Expression::table(
+            values
+                .into_iter()
+                .map(|(key, value)| (key.into(), toml_to_lua_value(value)))
+                .collect(),
+        ),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use memofs::{InMemoryFs, VfsSnapshot};
+
+    #[test]
+    fn instance_from_vfs() {
+        let mut imfs = InMemoryFs::new();
+        imfs.load_snapshot(
+            "/foo.toml",
+            VfsSnapshot::file(
+                r#"
+                    array = [1, 2, 3]
+                    true = true
+                    false = false
+                    int = 1234
+                    float = 1234.5452
+                    date = 1979-05-27T07:32:00Z
+                    "1invalidident" = "nice"
+
+                    [object]
+                    hello = "world"
+                "#,
+            ),
+        )
+        .unwrap();
+
+        let mut vfs = Vfs::new(imfs.clone());
+
+        let instance_snapshot = snapshot_toml(
+            &InstanceContext::default(),
+            &mut vfs,
+            Path::new("/foo.toml"),
+            "foo",
+        )
+        .unwrap()
+        .unwrap();
+
+        insta::assert_yaml_snapshot!(instance_snapshot);
+    }
+
+    #[test]
+    fn error_includes_line() {
+        let mut imfs = InMemoryFs::new();
+        imfs.load_snapshot("/foo.toml", VfsSnapshot::file("a = 1\nb = \n"))
+            .unwrap();
+
+        let mut vfs = Vfs::new(imfs.clone());
+
+        let error = snapshot_toml(
+            &InstanceContext::default(),
+            &mut vfs,
+            Path::new("/foo.toml"),
+            "foo",
+        )
+        .unwrap_err();
+
+        let message = format!("{:#}", error);
+        assert!(message.contains("/foo.toml"), "{}", message);
+        assert!(message.contains("line 2"), "{}", message);
+    }
+}
@@ -0,0 +1,150 @@
+use std::{path::Path, str};
+
+use anyhow::Context;
+use maplit::hashmap;
+use memofs::{IoResultExt, Vfs};
+
+use crate::{
+    lua_ast::{data_module_source, Expression},
+    snapshot::{InstanceContext, InstanceMetadata, InstanceSnapshot},
+};
+
+use super::meta_file::AdjacentMetadata;
+
+pub fn snapshot_yaml(
+    context: &InstanceContext,
+    vfs: &Vfs,
+    path: &Path,
+    name: &str,
+) -> anyhow::Result<Option<InstanceSnapshot>> {
+    let contents = vfs.read(path)?;
+    let contents_str = str::from_utf8(&contents)
+        .with_context(|| format!("File was not valid UTF-8: {}", path.display()))?;
+
+    let value: serde_yaml::Value = serde_yaml::from_str(contents_str)
+        .with_context(|| format!("File contains malformed YAML: {}", path.display()))?;
+
+    let as_lua = yaml_to_lua_value(value)
+        .with_context(|| format!("Could not convert YAML file: {}", path.display()))?;
+    let as_lua = data_module_source(as_lua, context.typed_data_modules);
+
+    let properties = hashmap! {
+        "Source".to_owned() => as_lua.into(),
+    };
+
+    let meta_path = path.with_file_name(format!("{}.meta.json", name));
+
+    let mut snapshot = InstanceSnapshot::new()
+        .name(name)
+        .class_name("ModuleScript")
+        .properties(properties)
+        .metadata(
+            InstanceMetadata::new()
+                .instigating_source(path)
+                .relevant_paths(vec![path.to_path_buf(), meta_path.clone()])
+                .context(context),
+        );
+
+    if let Some(meta_contents) = vfs.read(&meta_path).with_not_found()? {
+        let mut metadata = AdjacentMetadata::from_slice(&meta_contents, meta_path)?;
+        metadata.apply_all(&mut snapshot)?;
+    }
+
+    Ok(Some(snapshot))
+}
+
+fn yaml_to_lua_value(value: serde_yaml::Value) -> anyhow::Result<Expression> {
+    use serde_yaml::Value;
+
+    Ok(match value {
+        Value::Null => Expression::Nil,
+        Value::Bool(value) => Expression::Bool(value),
+        Value::Number(value) => Expression::Number(value.as_f64().unwrap()),
+        Value::String(value) => Expression::String(value),
+        Value::Sequence(values) => Expression::Array(
+            values
+                .into_iter()
+                .map(yaml_to_lua_value)
+                .collect::<anyhow::Result<_>>()?,
+        ),
+        Value::Mapping(values) => {
+            let mut entries = Vec::with_capacity(values.len());
+
+            for (key, value) in values {
+                // Unlike JSON and TOML, YAML allows keys that aren't strings.
+                // Lua allows most of them too, except for nil.
+                if key.is_null() {
+                    anyhow::bail!("Mappings with null keys cannot be converted to Lua");
+                }
+
+                entries.push((yaml_to_lua_value(key)?, yaml_to_lua_value(value)?));
+            }
+
+            Expression::table(entries)
+        }
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use memofs::{InMemoryFs, VfsSnapshot};
+
+    #[test]
+    fn instance_from_vfs() {
+        let mut imfs = InMemoryFs::new();
+        imfs.load_snapshot(
+            "/foo.yaml",
+            VfsSnapshot::file(
+                r#"
+array: [1, 2, 3]
+object:
+  hello: world
+"true": true
+"false": false
+"null": null
+int: 1234
+float: 1234.5452
+1invalidident: nice
+5: number key
+"#,
+            ),
+        )
+        .unwrap();
+
+        let mut vfs = Vfs::new(imfs.clone());
+
+        let instance_snapshot = snapshot_yaml(
+            &InstanceContext::default(),
+            &mut vfs,
+            Path::new("/foo.yaml"),
+            "foo",
+        )
+        .unwrap()
+        .unwrap();
+
+        insta::assert_yaml_snapshot!(instance_snapshot);
+    }
+
+    #[test]
+    fn error_includes_line() {
+        let mut imfs = InMemoryFs::new();
+        imfs.load_snapshot("/foo.yaml", VfsSnapshot::file("a: 1\nb: c: d\n"))
+            .unwrap();
+
+        let mut vfs = Vfs::new(imfs.clone());
+
+        let error = snapshot_yaml(
+            &InstanceContext::default(),
+            &mut vfs,
+            Path::new("/foo.yaml"),
+            "foo",
+        )
+        .unwrap_err();
+
+        let message = format!("{:#}", error);
+        assert!(message.contains("/foo.yaml"), "{}", message);
+        assert!(message.contains("line 2"), "{}", message);
+    }
+}
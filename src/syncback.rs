@@ -0,0 +1,548 @@
+//! Defines how instances are turned back into files on the filesystem.
+//!
+//! These rules are the inverse of the ones in `snapshot_middleware`: anything
+//! written here should turn back into the same instance when Rojo reads it.
+
+use std::{
+    collections::{HashMap, HashSet},
+    path::{Path, PathBuf},
+};
+
+use anyhow::{bail, Context};
+use memofs::{IoResultExt, Vfs};
+use rbx_dom_weak::{
+    types::{Ref, Variant, VariantType},
+    InstanceBuilder, WeakDom,
+};
+
+use crate::{
+    resolution::{find_descriptor, take_attributes, UnresolvedValue},
+    snapshot::InstanceSnapshot,
+    snapshot_middleware::{AdjacentMetadata, DirectoryMetadata, JsonModel},
+};
+
+/// The ways that an instance can be represented on the filesystem.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Representation {
+    /// A Lua file with the given suffix, like `foo.server.lua`.
+    Script(&'static str),
+
+    /// A directory containing an init script with the given suffix, like
+    /// `foo/init.server.lua`.
+    ScriptDirectory(&'static str),
+
+    /// A directory. Instances that aren't Folders have their ClassName written
+    /// into `init.meta.json`.
+    Directory,
+
+    /// A `.model.json` file containing the whole subtree.
+    JsonModel,
+
+    /// A `.rbxmx` file containing the whole subtree.
+    XmlModel,
+}
+
+fn representation(snapshot: &InstanceSnapshot) -> Representation {
+    let suffix = script_suffix(&snapshot.class_name);
+
+    // Meta files can't describe references or SharedString values, and the
+    // children of directories need to be able to become distinct files.
+    let fits_in_files = !snapshot.properties.values().any(is_unencodable);
+    let fits_in_directory = fits_in_files && children_fit_in_directory(snapshot);
+
+    match suffix {
+        Some(suffix) if fits_in_files && snapshot.children.is_empty() => {
+            return Representation::Script(suffix)
+        }
+        Some(suffix) if fits_in_directory => return Representation::ScriptDirectory(suffix),
+        _ => {}
+    }
+
+    // Directories are only worth it for non-Folder instances if there are
+    // scripts inside them that someone would want to edit.
+    if fits_in_directory
+        && (snapshot.class_name == "Folder" || snapshot.children.iter().any(contains_scripts))
+    {
+        return Representation::Directory;
+    }
+
+    if needs_xml_model(snapshot) {
+        Representation::XmlModel
+    } else {
+        Representation::JsonModel
+    }
+}
+
+/// Writes the given instance and its descendants into the directory at
+/// `parent_path`.
+///
+/// Returns every file and directory that was created, starting with the one
+/// that represents the instance itself.
+pub fn write_instance(
+    vfs: &Vfs,
+    parent_path: &Path,
+    snapshot: &InstanceSnapshot,
+) -> anyhow::Result<Vec<PathBuf>> {
+    let name = snapshot.name.as_ref();
+    validate_file_name(name)?;
+
+    let mut written = Vec::new();
+
+    match representation(snapshot) {
+        Representation::Script(suffix) => {
+            if !is_valid_script_name(name) {
+                bail!(
+                    "Cannot create a script named {}, it would not turn back into the same instance.\n\
+                     Directory: {}",
+                    name,
+                    parent_path.display()
+                );
+            }
+
+            let script_path = parent_path.join(format!("{}{}.lua", name, suffix));
+            write_new_file(vfs, &script_path, script_source(snapshot)?)?;
+            written.push(script_path);
+
+            let meta_path = parent_path.join(format!("{}.meta.json", name));
+            if write_adjacent_meta(vfs, &meta_path, non_source_properties(snapshot))? {
+                written.push(meta_path);
+            }
+        }
+        Representation::ScriptDirectory(suffix) => {
+            let dir_path = create_dir(vfs, parent_path, name)?;
+            written.push(dir_path.clone());
+
+            let init_path = dir_path.join(format!("init{}.lua", suffix));
+            vfs.write(&init_path, script_source(snapshot)?)?;
+            written.push(init_path);
+
+            let meta_path = dir_path.join("init.meta.json");
+            if write_directory_meta(vfs, &meta_path, None, non_source_properties(snapshot))? {
+                written.push(meta_path);
+            }
+
+            write_children(vfs, &dir_path, snapshot, &mut written)?;
+        }
+        Representation::Directory => {
+            let dir_path = create_dir(vfs, parent_path, name)?;
+            written.push(dir_path.clone());
+
+            let class_name = if snapshot.class_name == "Folder" {
+                None
+            } else {
+                Some(snapshot.class_name.to_string())
+            };
+
+            let meta_path = dir_path.join("init.meta.json");
+            if write_directory_meta(vfs, &meta_path, class_name, snapshot.properties.iter())? {
+                written.push(meta_path);
+            }
+
+            write_children(vfs, &dir_path, snapshot, &mut written)?;
+        }
+        Representation::JsonModel => {
+            let model_path = parent_path.join(format!("{}.model.json", name));
+            let model = serde_json::to_string_pretty(&JsonModel::from_snapshot(snapshot))
+                .with_context(|| format!("Could not encode instance {} as JSON", name))?;
+            write_new_file(vfs, &model_path, model)?;
+            written.push(model_path);
+        }
+        Representation::XmlModel => {
+            let model_path = parent_path.join(format!("{}.rbxmx", name));
+            write_new_file(vfs, &model_path, encode_xml_model(snapshot)?)?;
+            written.push(model_path);
+        }
+    }
+
+    Ok(written)
+}
+
+/// Removes properties that don't need to be written to the filesystem from the
+/// given instance and its descendants. This includes properties that have
+/// their default value and properties that Rojo doesn't know about.
+pub fn remove_default_properties(snapshot: &mut InstanceSnapshot) {
+    let class_name = snapshot.class_name.clone();
+
+    snapshot.properties.retain(|key, value| {
+        if find_descriptor(&class_name, key).is_none() {
+            return false;
+        }
+
+        find_default_property(&class_name, key) != Some(value)
+    });
+
+    for child in &mut snapshot.children {
+        remove_default_properties(child);
+    }
+}
+
+fn find_default_property(class_name: &str, prop_name: &str) -> Option<&'static Variant> {
+    let database = rbx_reflection_database::get();
+    let mut current_class_name = class_name;
+
+    loop {
+        let class = database.classes.get(current_class_name)?;
+        if let Some(value) = class.default_properties.get(prop_name) {
+            return Some(value);
+        }
+
+        current_class_name = class.superclass.as_deref()?;
+    }
+}
+
+fn write_children(
+    vfs: &Vfs,
+    dir_path: &Path,
+    snapshot: &InstanceSnapshot,
+    written: &mut Vec<PathBuf>,
+) -> anyhow::Result<()> {
+    for child in &snapshot.children {
+        written.extend(write_instance(vfs, dir_path, child)?);
+    }
+
+    Ok(())
+}
+
+/// Returns the suffix that a Lua file needs to turn into a script of the given
+/// class, or `None` if the class is not a script.
+pub fn script_suffix(class_name: &str) -> Option<&'static str> {
+    match class_name {
+        "ModuleScript" => Some(""),
+        "Script" => Some(".server"),
+        "LocalScript" => Some(".client"),
+        _ => None,
+    }
+}
+
+/// Tells whether all of the children of this instance can be written into the
+/// same directory and still turn back into the same instances.
+pub fn children_fit_in_directory(snapshot: &InstanceSnapshot) -> bool {
+    let mut seen_names = HashSet::new();
+
+    snapshot.children.iter().all(|child| {
+        let name = child.name.as_ref();
+
+        // Names are compared case-insensitively since many filesystems can't
+        // tell them apart.
+        let is_unique = seen_names.insert(name.to_lowercase());
+        let is_valid_name = validate_file_name(name).is_ok()
+            && (script_suffix(&child.class_name).is_none() || is_valid_script_name(name));
+
+        is_unique && is_valid_name
+    })
+}
+
+fn contains_scripts(snapshot: &InstanceSnapshot) -> bool {
+    script_suffix(&snapshot.class_name).is_some() || snapshot.children.iter().any(contains_scripts)
+}
+
+/// Scripts named `init` would turn their parent into a script, and names that
+/// look like script suffixes would be read back with a different name.
+fn is_valid_script_name(name: &str) -> bool {
+    name != "init" && !name.ends_with(".server") && !name.ends_with(".client")
+}
+
+fn validate_file_name(name: &str) -> anyhow::Result<()> {
+    let is_reserved = name == "." || name == "..";
+    let has_invalid_chars = name.chars().any(|c| {
+        matches!(c, '/' | '\\' | ':' | '*' | '?' | '"' | '<' | '>' | '|') || c.is_control()
+    });
+
+    if name.is_empty() || is_reserved || has_invalid_chars {
+        bail!("Instance name {:?} cannot be used as a file name.", name);
+    }
+
+    Ok(())
+}
+
+fn script_source(snapshot: &InstanceSnapshot) -> anyhow::Result<&str> {
+    match snapshot.properties.get("Source") {
+        Some(Variant::String(source)) => Ok(source.as_str()),
+        Some(_) => bail!("Source of script {} was not a string", snapshot.name),
+        None => Ok(""),
+    }
+}
+
+fn non_source_properties(snapshot: &InstanceSnapshot) -> impl Iterator<Item = (&String, &Variant)> {
+    snapshot
+        .properties
+        .iter()
+        .filter(|(key, _)| key.as_str() != "Source")
+}
+
+fn create_dir(vfs: &Vfs, parent_path: &Path, name: &str) -> anyhow::Result<PathBuf> {
+    let dir_path = parent_path.join(name);
+    ensure_free(vfs, &dir_path)?;
+    vfs.create_dir(&dir_path)?;
+
+    Ok(dir_path)
+}
+
+fn write_new_file<C: AsRef<[u8]>>(vfs: &Vfs, path: &Path, contents: C) -> anyhow::Result<()> {
+    ensure_free(vfs, path)?;
+    vfs.write(path, contents)?;
+
+    Ok(())
+}
+
+fn ensure_free(vfs: &Vfs, path: &Path) -> anyhow::Result<()> {
+    if vfs.metadata(path).with_not_found()?.is_some() {
+        bail!("Cannot create {}, it already exists.", path.display());
+    }
+
+    Ok(())
+}
+
+/// Writes an adjacent `.meta.json` file containing the given properties, if
+/// there are any. Returns whether a file was written.
+fn write_adjacent_meta<'a>(
+    vfs: &Vfs,
+    path: &Path,
+    properties: impl Iterator<Item = (&'a String, &'a Variant)>,
+) -> anyhow::Result<bool> {
+    let (properties, attributes) = unresolved_properties(properties);
+    let meta = AdjacentMetadata {
+        properties,
+        attributes,
+        ..Default::default()
+    };
+
+    if meta.properties.is_empty() && meta.attributes.is_empty() {
+        return Ok(false);
+    }
+
+    write_new_file(vfs, path, serde_json::to_string_pretty(&meta)?)?;
+    Ok(true)
+}
+
+/// Writes an `init.meta.json` file containing the given ClassName and
+/// properties, if there are any. Returns whether a file was written.
+fn write_directory_meta<'a>(
+    vfs: &Vfs,
+    path: &Path,
+    class_name: Option<String>,
+    properties: impl Iterator<Item = (&'a String, &'a Variant)>,
+) -> anyhow::Result<bool> {
+    let (properties, attributes) = unresolved_properties(properties);
+    let meta = DirectoryMetadata {
+        properties,
+        attributes,
+        class_name,
+        ..Default::default()
+    };
+
+    if meta.properties.is_empty() && meta.attributes.is_empty() && meta.class_name.is_none() {
+        return Ok(false);
+    }
+
+    write_new_file(vfs, path, serde_json::to_string_pretty(&meta)?)?;
+    Ok(true)
+}
+
+/// Converts properties into the values written to meta files, splitting out
+/// attributes so that they're written as readable values instead of as an
+/// encoded `Attributes` property.
+fn unresolved_properties<'a>(
+    properties: impl Iterator<Item = (&'a String, &'a Variant)>,
+) -> (
+    HashMap<String, UnresolvedValue>,
+    HashMap<String, UnresolvedValue>,
+) {
+    let mut properties: HashMap<String, Variant> = properties
+        .map(|(key, value)| (key.clone(), value.clone()))
+        .collect();
+    let attributes = take_attributes(&mut properties);
+
+    let properties = properties
+        .into_iter()
+        .map(|(key, value)| (key, UnresolvedValue::FullyQualified(value)))
+        .collect();
+
+    (properties, attributes)
+}
+
+/// JSON can't describe references between instances or SharedString values,
+/// but XML models can.
+fn is_unencodable(value: &Variant) -> bool {
+    matches!(value.ty(), VariantType::Ref | VariantType::SharedString)
+}
+
+fn needs_xml_model(snapshot: &InstanceSnapshot) -> bool {
+    snapshot.properties.values().any(is_unencodable)
+        || snapshot.children.iter().any(needs_xml_model)
+}
+
+fn encode_xml_model(snapshot: &InstanceSnapshot) -> anyhow::Result<Vec<u8>> {
+    let mut referents = HashMap::new();
+    let builder = instance_builder(snapshot, &mut referents);
+    let mut dom = WeakDom::new(builder);
+
+    // References are only kept if they point to instances inside the model.
+    let mut to_visit = vec![dom.root_ref()];
+    while let Some(id) = to_visit.pop() {
+        let instance = dom.get_by_ref_mut(id).unwrap();
+        to_visit.extend_from_slice(instance.children());
+
+        for value in instance.properties.values_mut() {
+            if let Variant::Ref(target) = value {
+                *target = referents.get(target).copied().unwrap_or_else(Ref::none);
+            }
+        }
+    }
+
+    let mut output = Vec::new();
+    rbx_xml::to_writer_default(&mut output, &dom, &[dom.root_ref()])
+        .context("Could not encode instance as an XML model")?;
+
+    Ok(output)
+}
+
+/// Creates an `InstanceBuilder` mirroring the given snapshot, recording which
+/// snapshot IDs turned into which referents.
+fn instance_builder(
+    snapshot: &InstanceSnapshot,
+    referents: &mut HashMap<Ref, Ref>,
+) -> InstanceBuilder {
+    let builder = InstanceBuilder::new(snapshot.class_name.as_ref())
+        .with_name(snapshot.name.as_ref())
+        .with_properties(snapshot.properties.clone());
+
+    if let Some(snapshot_id) = snapshot.snapshot_id {
+        referents.insert(snapshot_id, builder.referent());
+    }
+
+    let children: Vec<_> = snapshot
+        .children
+        .iter()
+        .map(|child| instance_builder(child, referents))
+        .collect();
+
+    builder.with_children(children)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use memofs::{InMemoryFs, VfsSnapshot};
+
+    use crate::{snapshot::InstanceContext, snapshot_middleware::snapshot_from_vfs};
+
+    fn empty_dir_vfs() -> Vfs {
+        let mut imfs = InMemoryFs::new();
+        imfs.load_snapshot("/root", VfsSnapshot::empty_dir())
+            .unwrap();
+
+        Vfs::new(imfs)
+    }
+
+    #[test]
+    fn script_with_children() {
+        let vfs = empty_dir_vfs();
+
+        let snapshot = InstanceSnapshot::new()
+            .name("Main")
+            .class_name("Script")
+            .property("Source", "print('hello')")
+            .property("Disabled", true)
+            .children(vec![
+                InstanceSnapshot::new()
+                    .name("Util")
+                    .class_name("ModuleScript"),
+                InstanceSnapshot::new()
+                    .name("Speed")
+                    .class_name("NumberValue")
+                    .property("Value", 16.0),
+            ]);
+
+        let written = write_instance(&vfs, Path::new("/root"), &snapshot).unwrap();
+
+        assert_eq!(
+            written,
+            vec![
+                PathBuf::from("/root/Main"),
+                PathBuf::from("/root/Main/init.server.lua"),
+                PathBuf::from("/root/Main/init.meta.json"),
+                PathBuf::from("/root/Main/Util.lua"),
+                PathBuf::from("/root/Main/Speed.model.json"),
+            ]
+        );
+
+        let read_back = snapshot_from_vfs(&InstanceContext::default(), &vfs, &written[0])
+            .unwrap()
+            .unwrap();
+
+        assert_eq!(read_back.name, "Main");
+        assert_eq!(read_back.class_name, "Script");
+        assert_eq!(
+            read_back.properties.get("Source"),
+            Some(&Variant::String("print('hello')".to_owned()))
+        );
+        assert_eq!(
+            read_back.properties.get("Disabled"),
+            Some(&Variant::Bool(true))
+        );
+
+        let mut children: Vec<_> = read_back
+            .children
+            .iter()
+            .map(|child| (child.name.as_ref(), child.class_name.as_ref()))
+            .collect();
+        children.sort();
+
+        assert_eq!(
+            children,
+            vec![("Speed", "NumberValue"), ("Util", "ModuleScript")]
+        );
+    }
+
+    #[test]
+    fn xml_model_for_refs() {
+        let vfs = empty_dir_vfs();
+
+        let part_id = Ref::new();
+        let snapshot = InstanceSnapshot::new()
+            .name("Car")
+            .class_name("Model")
+            .property("PrimaryPart", part_id)
+            .children(vec![InstanceSnapshot::new()
+                .snapshot_id(Some(part_id))
+                .name("Body")
+                .class_name("Part")]);
+
+        let written = write_instance(&vfs, Path::new("/root"), &snapshot).unwrap();
+        assert_eq!(written, vec![PathBuf::from("/root/Car.rbxmx")]);
+
+        let read_back = snapshot_from_vfs(&InstanceContext::default(), &vfs, &written[0])
+            .unwrap()
+            .unwrap();
+
+        assert_eq!(read_back.class_name, "Model");
+        assert_eq!(read_back.children.len(), 1);
+        assert!(matches!(
+            read_back.properties.get("PrimaryPart"),
+            Some(Variant::Ref(id)) if id.is_some()
+        ));
+    }
+
+    #[test]
+    fn existing_files_are_not_overwritten() {
+        let mut imfs = InMemoryFs::new();
+        imfs.load_snapshot(
+            "/root",
+            VfsSnapshot::dir(maplit::hashmap! {
+                "Util.lua" => VfsSnapshot::file("return {}"),
+            }),
+        )
+        .unwrap();
+
+        let vfs = Vfs::new(imfs);
+
+        let snapshot = InstanceSnapshot::new()
+            .name("Util")
+            .class_name("ModuleScript");
+
+        assert!(write_instance(&vfs, Path::new("/root"), &snapshot).is_err());
+        assert_eq!(vfs.read("/root/Util.lua").unwrap().as_slice(), b"return {}");
+    }
+}
@@ -1,19 +1,25 @@
 //! Defines Rojo's HTTP API, all under /api. These endpoints generally return
 //! JSON.
 
-use std::{collections::HashMap, fs, path::PathBuf, str::FromStr, sync::Arc};
+use std::{
+    collections::{HashMap, HashSet},
+    fs,
+    path::PathBuf,
+    str::FromStr,
+    sync::Arc,
+};
 
 use hyper::{body, Body, Method, Request, Response, StatusCode};
 use rbx_dom_weak::types::Ref;
 
 use crate::{
     serve_session::ServeSession,
-    snapshot::{InstanceWithMeta, PatchSet, PatchUpdate},
+    snapshot::{InstanceSnapshot, InstanceWithMeta, PatchAdd, PatchSet, PatchUpdate},
     web::{
         interface::{
-            ErrorResponse, Instance, OpenResponse, ReadResponse, ServerInfoResponse,
-            SubscribeMessage, SubscribeResponse, WriteRequest, WriteResponse, PROTOCOL_VERSION,
-            SERVER_VERSION,
+            AddedInstance, ErrorResponse, ErrorsResponse, Instance, InstanceError, OpenResponse,
+            ReadResponse, ServerInfoResponse, SubscribeMessage, SubscribeResponse, WriteRequest,
+            WriteResponse, PROTOCOL_VERSION, SERVER_VERSION,
         },
         util::{json, json_ok},
     },
@@ -24,6 +30,7 @@ pub async fn call(serve_session: Arc<ServeSession>, request: Request<Body>) -> R
 
     match (request.method(), request.uri().path()) {
         (&Method::GET, "/api/rojo") => service.handle_api_rojo().await,
+        (&Method::GET, "/api/errors") => service.handle_api_errors().await,
         (&Method::GET, path) if path.starts_with("/api/read/") => {
             service.handle_api_read(request).await
         }
@@ -69,6 +76,25 @@ impl ApiService {
         })
     }
 
+    /// List the errors that are currently keeping instances from updating
+    async fn handle_api_errors(&self) -> Response<Body> {
+        let errors = self
+            .serve_session
+            .snapshot_errors()
+            .into_iter()
+            .map(|(id, error)| InstanceError {
+                id,
+                path: error.path,
+                message: error.message,
+            })
+            .collect();
+
+        json_ok(ErrorsResponse {
+            session_id: self.serve_session.session_id(),
+            errors,
+        })
+    }
+
     /// Retrieve any messages past the given cursor index, and if
     /// there weren't any, subscribe to receive any new messages.
     async fn handle_api_subscribe(&self, request: Request<Body>) -> Response<Body> {
@@ -94,7 +120,7 @@ impl ApiService {
         let tree_handle = self.serve_session.tree_handle();
 
         match result {
-            Ok((message_cursor, messages)) => {
+            Ok(Ok((message_cursor, messages))) => {
                 let tree = tree_handle.lock().unwrap();
 
                 let api_messages = messages
@@ -108,6 +134,10 @@ impl ApiService {
                     messages: api_messages,
                 })
             }
+            Ok(Err(err)) => json(
+                ErrorResponse::resync_required(err.to_string()),
+                StatusCode::GONE,
+            ),
             Err(_) => json(
                 ErrorResponse::internal_error("Message queue disconnected sender"),
                 StatusCode::INTERNAL_SERVER_ERROR,
@@ -138,6 +168,13 @@ impl ApiService {
             );
         }
 
+        let added_instances = match patch_adds(&request.added) {
+            Ok(added_instances) => added_instances,
+            Err(message) => {
+                return json(ErrorResponse::bad_request(message), StatusCode::BAD_REQUEST);
+            }
+        };
+
         let updated_instances = request
             .updated
             .into_iter()
@@ -153,7 +190,7 @@ impl ApiService {
         tree_mutation_sender
             .send(PatchSet {
                 removed_instances: Vec::new(),
-                added_instances: Vec::new(),
+                added_instances,
                 updated_instances,
             })
             .unwrap();
@@ -244,16 +281,61 @@ impl ApiService {
     }
 }
 
+/// Turns the flat map of instances added by a client into snapshots, each
+/// rooted at an instance that already exists on the server.
+fn patch_adds(added: &HashMap<Ref, AddedInstance>) -> Result<Vec<PatchAdd>, String> {
+    let mut visited = HashSet::new();
+    let mut patch_adds = Vec::new();
+
+    for (&id, instance) in added {
+        if !added.contains_key(&instance.parent) {
+            patch_adds.push(PatchAdd {
+                parent_id: instance.parent,
+                instance: added_snapshot(id, added, &mut visited)?,
+            });
+        }
+    }
+
+    Ok(patch_adds)
+}
+
+fn added_snapshot(
+    id: Ref,
+    added: &HashMap<Ref, AddedInstance>,
+    visited: &mut HashSet<Ref>,
+) -> Result<InstanceSnapshot, String> {
+    if !visited.insert(id) {
+        return Err(format!("Added instance {} appears more than once", id));
+    }
+
+    let instance = added
+        .get(&id)
+        .ok_or_else(|| format!("Added instance {} is missing", id))?;
+
+    let children = instance
+        .children
+        .iter()
+        .map(|&child_id| added_snapshot(child_id, added, visited))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    Ok(InstanceSnapshot::new()
+        .snapshot_id(Some(id))
+        .name(instance.name.clone())
+        .class_name(instance.class_name.clone())
+        .properties(instance.properties.clone())
+        .children(children))
+}
+
 /// If this instance is represented by a script, try to find the correct .lua
-/// file to open to edit it.
+/// or .luau file to open to edit it.
 fn pick_script_path(instance: InstanceWithMeta<'_>) -> Option<PathBuf> {
     match instance.class_name() {
         "Script" | "LocalScript" | "ModuleScript" => {}
         _ => return None,
     }
 
-    // Pick the first listed relevant path that has an extension of .lua that
-    // exists.
+    // Pick the first listed relevant path that has an extension of .lua or
+    // .luau that exists.
     instance
         .metadata()
         .relevant_paths
@@ -261,7 +343,7 @@ fn pick_script_path(instance: InstanceWithMeta<'_>) -> Option<PathBuf> {
         .find(|path| {
             // We should only ever open Lua files to be safe.
             match path.extension().and_then(|ext| ext.to_str()) {
-                Some("lua") => {}
+                Some("lua") | Some("luau") => {}
                 _ => return false,
             }
 
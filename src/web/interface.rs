@@ -5,6 +5,7 @@
 use std::{
     borrow::Cow,
     collections::{HashMap, HashSet},
+    path::PathBuf,
 };
 
 use rbx_dom_weak::types::{Ref, Variant, VariantType};
@@ -182,10 +183,29 @@ pub struct WriteRequest {
     pub removed: Vec<Ref>,
 
     #[serde(default)]
-    pub added: HashMap<Ref, ()>,
+    pub added: HashMap<Ref, AddedInstance>,
     pub updated: Vec<InstanceUpdate>,
 }
 
+/// An instance created by a client that should be written to the filesystem.
+///
+/// Added instances are keyed by IDs chosen by the client. `Parent` refers
+/// either to an instance that already exists on the server or to another added
+/// instance.
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct AddedInstance {
+    pub parent: Ref,
+    pub name: String,
+    pub class_name: String,
+
+    #[serde(default)]
+    pub properties: HashMap<String, Variant>,
+
+    #[serde(default)]
+    pub children: Vec<Ref>,
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct WriteResponse {
@@ -208,12 +228,30 @@ pub struct OpenResponse {
     pub session_id: SessionId,
 }
 
+/// Response body from /api/errors
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ErrorsResponse {
+    pub session_id: SessionId,
+    pub errors: Vec<InstanceError>,
+}
+
+/// An error that is keeping an instance from updating. The instance keeps its
+/// last good state until the files it comes from are fixed.
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct InstanceError {
+    pub id: Ref,
+    pub path: PathBuf,
+    pub message: String,
+}
+
 /// General response type returned from all Rojo routes
 #[derive(Debug, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct ErrorResponse {
-    kind: ErrorResponseKind,
-    details: String,
+    pub kind: ErrorResponseKind,
+    pub details: String,
 }
 
 impl ErrorResponse {
@@ -231,6 +269,13 @@ impl ErrorResponse {
         }
     }
 
+    pub fn resync_required<S: Into<String>>(details: S) -> Self {
+        Self {
+            kind: ErrorResponseKind::ResyncRequired,
+            details: details.into(),
+        }
+    }
+
     pub fn internal_error<S: Into<String>>(details: S) -> Self {
         Self {
             kind: ErrorResponseKind::InternalError,
@@ -244,4 +289,8 @@ pub enum ErrorResponseKind {
     NotFound,
     BadRequest,
     InternalError,
+
+    /// The client's message cursor is older than any message the server still
+    /// has, so it has missed changes and must read the whole tree again.
+    ResyncRequired,
 }
@@ -5,6 +5,7 @@
 mod api;
 mod assets;
 pub mod interface;
+mod socket;
 mod ui;
 mod util;
 
@@ -41,7 +42,9 @@ impl LiveServer {
                     let serve_session = Arc::clone(&serve_session);
 
                     async move {
-                        if req.uri().path().starts_with("/api") {
+                        if req.uri().path().starts_with("/api/socket/") {
+                            Ok::<_, Infallible>(socket::call(serve_session, req).await)
+                        } else if req.uri().path().starts_with("/api") {
                             Ok::<_, Infallible>(api::call(serve_session, req).await)
                         } else {
                             Ok::<_, Infallible>(ui::call(serve_session, req).await)
@@ -0,0 +1,143 @@
+//! Defines the WebSocket version of /api/subscribe, under /api/socket.
+//!
+//! Instead of making a new request for every batch of changes, clients open one
+//! connection and receive every change in order as a stream of
+//! `SubscribeResponse` messages. Each message contains the cursor to reconnect
+//! with if the connection drops, so no changes are missed. If the server no
+//! longer has the changes after a client's cursor, it sends an `ErrorResponse`
+//! instead and closes the connection.
+
+use std::sync::Arc;
+
+use futures::{
+    future::{self, Either},
+    SinkExt, StreamExt,
+};
+use hyper::{
+    header::{self, HeaderValue},
+    upgrade::{self, Upgraded},
+    Body, Request, Response, StatusCode,
+};
+use tokio_tungstenite::{
+    tungstenite::{handshake::derive_accept_key, protocol::Role, Message},
+    WebSocketStream,
+};
+
+use crate::{
+    serve_session::ServeSession,
+    web::{
+        interface::{ErrorResponse, SubscribeMessage, SubscribeResponse},
+        util::json,
+    },
+};
+
+pub async fn call(serve_session: Arc<ServeSession>, request: Request<Body>) -> Response<Body> {
+    let argument = &request.uri().path()["/api/socket/".len()..];
+    let cursor: u32 = match argument.parse() {
+        Ok(v) => v,
+        Err(err) => {
+            return json(
+                ErrorResponse::bad_request(format!("Malformed message cursor: {}", err)),
+                StatusCode::BAD_REQUEST,
+            );
+        }
+    };
+
+    let is_upgrade = request
+        .headers()
+        .get(header::UPGRADE)
+        .and_then(|value| value.to_str().ok())
+        .map(|value| value.eq_ignore_ascii_case("websocket"))
+        .unwrap_or(false);
+
+    let key = match request.headers().get(header::SEC_WEBSOCKET_KEY) {
+        Some(key) if is_upgrade => derive_accept_key(key.as_bytes()),
+        _ => {
+            return json(
+                ErrorResponse::bad_request("Expected a WebSocket upgrade request"),
+                StatusCode::BAD_REQUEST,
+            );
+        }
+    };
+
+    tokio::spawn(async move {
+        match upgrade::on(request).await {
+            Ok(upgraded) => {
+                let socket = WebSocketStream::from_raw_socket(upgraded, Role::Server, None).await;
+
+                if let Err(err) = stream_messages(&serve_session, socket, cursor).await {
+                    log::debug!("WebSocket connection closed with error: {}", err);
+                }
+            }
+            Err(err) => log::error!("Could not upgrade connection to WebSocket: {}", err),
+        }
+    });
+
+    Response::builder()
+        .status(StatusCode::SWITCHING_PROTOCOLS)
+        .header(header::CONNECTION, HeaderValue::from_static("Upgrade"))
+        .header(header::UPGRADE, HeaderValue::from_static("websocket"))
+        .header(header::SEC_WEBSOCKET_ACCEPT, key)
+        .body(Body::empty())
+        .unwrap()
+}
+
+/// Sends every message after the given cursor to the client until either side
+/// closes the connection.
+async fn stream_messages(
+    serve_session: &ServeSession,
+    socket: WebSocketStream<Upgraded>,
+    mut cursor: u32,
+) -> anyhow::Result<()> {
+    let session_id = serve_session.session_id();
+    let (mut sender, mut receiver) = socket.split();
+
+    loop {
+        let subscription = serve_session.message_queue().subscribe(cursor);
+
+        let (message_cursor, messages) = match future::select(subscription, receiver.next()).await {
+            Either::Left((Ok(Ok(result)), _)) => result,
+
+            // The client has missed messages, so the best we can do is tell
+            // it to start over.
+            Either::Left((Ok(Err(err)), _)) => {
+                let response =
+                    serde_json::to_string(&ErrorResponse::resync_required(err.to_string()))?;
+
+                sender.send(Message::Text(response)).await?;
+                break;
+            }
+
+            // The message queue went away, so the session is shutting down.
+            Either::Left((Err(_), _)) => break,
+
+            // We don't expect clients to send us anything, but we still need to
+            // read from the socket to notice when it closes. Pings are answered
+            // for us.
+            Either::Right((Some(Ok(Message::Close(_))), _)) | Either::Right((None, _)) => break,
+            Either::Right((Some(Ok(_)), _)) => continue,
+            Either::Right((Some(Err(err)), _)) => return Err(err.into()),
+        };
+
+        let serialized = {
+            let tree = serve_session.tree();
+
+            let messages = messages
+                .into_iter()
+                .map(|patch| SubscribeMessage::from_patch_update(&tree, patch))
+                .collect();
+
+            serde_json::to_string(&SubscribeResponse {
+                session_id,
+                message_cursor,
+                messages,
+            })?
+        };
+
+        sender.send(Message::Text(serialized)).await?;
+        cursor = message_cursor;
+    }
+
+    let _ = sender.send(Message::Close(None)).await;
+    Ok(())
+}
@@ -61,10 +61,13 @@ impl UiService {
 
     fn handle_home(&self) -> Response<Body> {
         let page = self.normal_page(html! {
-            <div class="button-list">
-                { Self::button("Rojo Documentation", "https://rojo.space/docs") }
-                { Self::button("View instance tree state", "/show-instances") }
-            </div>
+            <>
+                { self.snapshot_errors() }
+                <div class="button-list">
+                    { Self::button("Rojo Documentation", "https://rojo.space/docs") }
+                    { Self::button("View instance tree state", "/show-instances") }
+                </div>
+            </>
         });
 
         Response::builder()
@@ -87,6 +90,35 @@ impl UiService {
             .unwrap()
     }
 
+    /// Lists the errors that are keeping instances from updating, if there
+    /// are any.
+    fn snapshot_errors(&self) -> HtmlContent<'_> {
+        let errors = self.serve_session.snapshot_errors();
+
+        if errors.is_empty() {
+            return HtmlContent::None;
+        }
+
+        let error_list: Vec<_> = errors
+            .into_iter()
+            .map(|(_id, error)| {
+                html! {
+                    <div class="snapshot-error">
+                        <div class="snapshot-error-path">{ format!("{}", error.path.display()) }</div>
+                        <pre class="snapshot-error-message">{ error.message }</pre>
+                    </div>
+                }
+            })
+            .collect();
+
+        html! {
+            <section class="main-section">
+                <h1 class="section-title">"Sync Errors"</h1>
+                { Fragment::new(error_list) }
+            </section>
+        }
+    }
+
     fn instance(tree: &RojoTree, id: Ref) -> HtmlContent<'_> {
         let instance = tree.get_instance(id).unwrap();
         let children_list: Vec<_> = instance
@@ -1,5 +1,6 @@
 use std::{
     fs,
+    net::TcpStream,
     path::{Path, PathBuf},
     process::Command,
     sync::atomic::{AtomicUsize, Ordering},
@@ -8,10 +9,15 @@ use std::{
 };
 
 use rbx_dom_weak::types::Ref;
+use reqwest::StatusCode;
 
 use tempfile::{tempdir, TempDir};
+use tungstenite::{stream::MaybeTlsStream, Message, WebSocket};
 
-use librojo::web_api::{ReadResponse, ServerInfoResponse, SubscribeResponse};
+use librojo::web_api::{
+    ErrorResponse, ErrorsResponse, ReadResponse, ServerInfoResponse, SubscribeResponse,
+    WriteRequest, WriteResponse,
+};
 use rojo_insta_ext::RedactionMap;
 
 use crate::rojo_test::io_util::{
@@ -26,11 +32,20 @@ use crate::rojo_test::io_util::{
 /// The passed in callback is where the actual test body should go. Setup and
 /// cleanup happens automatically.
 pub fn run_serve_test(test_name: &str, callback: impl FnOnce(TestServeSession, RedactionMap)) {
+    run_serve_test_with_args(test_name, &[], callback)
+}
+
+/// Like `run_serve_test`, but passes extra arguments to `rojo serve`.
+pub fn run_serve_test_with_args(
+    test_name: &str,
+    args: &[&str],
+    callback: impl FnOnce(TestServeSession, RedactionMap),
+) {
     let _ = env_logger::try_init();
 
     let mut redactions = RedactionMap::new();
 
-    let mut session = TestServeSession::new(test_name);
+    let mut session = TestServeSession::new(test_name, args);
     let info = session.wait_to_come_online();
 
     redactions.intern(info.session_id);
@@ -61,7 +76,7 @@ pub struct TestServeSession {
 }
 
 impl TestServeSession {
-    pub fn new(name: &str) -> Self {
+    pub fn new(name: &str, args: &[&str]) -> Self {
         let working_dir = get_working_dir_path();
 
         let source_path = Path::new(SERVE_TESTS_PATH).join(name);
@@ -89,6 +104,7 @@ impl TestServeSession {
                 "--port",
                 port_string.as_str(),
             ])
+            .args(args)
             .current_dir(working_dir)
             .spawn()
             .expect("Couldn't start Rojo");
@@ -153,6 +169,13 @@ impl TestServeSession {
         Ok(serde_json::from_str(&body).expect("Server returned malformed response"))
     }
 
+    pub fn get_api_errors(&self) -> Result<ErrorsResponse, reqwest::Error> {
+        let url = format!("http://localhost:{}/api/errors", self.port);
+        let body = reqwest::get(&url)?.text()?;
+
+        Ok(serde_json::from_str(&body).expect("Server returned malformed response"))
+    }
+
     pub fn get_api_subscribe(
         &self,
         cursor: u32,
@@ -161,6 +184,54 @@ impl TestServeSession {
 
         reqwest::get(&url)?.json()
     }
+
+    /// Subscribes with a cursor that the server is expected to reject,
+    /// returning the status code and error it responded with.
+    pub fn get_api_subscribe_error(
+        &self,
+        cursor: u32,
+    ) -> Result<(StatusCode, ErrorResponse), reqwest::Error> {
+        let url = format!("http://localhost:{}/api/subscribe/{}", self.port, cursor);
+        let mut response = reqwest::get(&url)?;
+
+        Ok((response.status(), response.json()?))
+    }
+
+    pub fn post_api_write(&self, request: &WriteRequest) -> Result<WriteResponse, reqwest::Error> {
+        let url = format!("http://localhost:{}/api/write", self.port);
+
+        reqwest::Client::new()
+            .post(&url)
+            .json(request)
+            .send()?
+            .json()
+    }
+
+    pub fn connect_socket(&self, cursor: u32) -> TestSocket {
+        let url = format!("ws://localhost:{}/api/socket/{}", self.port, cursor);
+        let (socket, _response) = tungstenite::connect(url).expect("Could not connect to socket");
+
+        TestSocket(socket)
+    }
+}
+
+/// A connection to /api/socket, which streams the same responses as
+/// /api/subscribe.
+pub struct TestSocket(WebSocket<MaybeTlsStream<TcpStream>>);
+
+impl TestSocket {
+    /// Blocks until the server sends the next batch of messages.
+    pub fn read_response(&mut self) -> SubscribeResponse<'static> {
+        loop {
+            match self.0.read_message().expect("Could not read from socket") {
+                Message::Text(text) => {
+                    return serde_json::from_str(&text).expect("Server returned malformed response")
+                }
+                Message::Close(_) => panic!("Server closed the socket"),
+                _ => continue,
+            }
+        }
+    }
 }
 
 /// Probably-okay way to generate random enough port numbers for running the
@@ -21,10 +21,12 @@ macro_rules! gen_build_tests {
 }
 
 gen_build_tests! {
+    attributes,
     client_in_folder,
     client_init,
     csv_bug_145,
     csv_bug_147,
+    csv_dir,
     csv_in_folder,
     deep_nesting,
     gitkeep,
@@ -39,17 +41,21 @@ gen_build_tests! {
     json_as_lua,
     json_model_in_folder,
     json_model_legacy_name,
+    luau_in_folder,
     module_in_folder,
     module_init,
     project_composed_default,
     project_composed_file,
+    project_extends,
     project_root_name,
     rbxm_in_folder,
     rbxmx_in_folder,
+    rbxmx_multiple_roots,
     rbxmx_ref,
     script_meta_disabled,
     server_in_folder,
     server_init,
+    sync_rules,
     txt,
     txt_in_folder,
     unresolved_values,
@@ -1,9 +1,16 @@
-use std::fs;
+use std::{collections::HashMap, fs, thread, time::Duration};
 
 use insta::assert_yaml_snapshot;
+use rbx_dom_weak::types::{Ref, Variant};
 use tempfile::tempdir;
 
-use crate::rojo_test::{internable::InternAndRedact, serve_util::run_serve_test};
+use librojo::web_api::{AddedInstance, ErrorResponseKind, WriteRequest};
+use reqwest::StatusCode;
+
+use crate::rojo_test::{
+    internable::InternAndRedact,
+    serve_util::{run_serve_test, run_serve_test_with_args},
+};
 
 #[test]
 fn empty() {
@@ -51,6 +58,33 @@ fn scripts() {
     });
 }
 
+#[test]
+fn scripts_socket() {
+    run_serve_test("scripts", |session, mut redactions| {
+        let mut socket = session.connect_socket(0);
+
+        fs::write(session.path().join("src/foo.lua"), "Updated foo!").unwrap();
+
+        let socket_response = socket.read_response();
+        assert_yaml_snapshot!(
+            "scripts_socket_subscribe",
+            socket_response.intern_and_redact(&mut redactions, ())
+        );
+
+        // Reconnecting with an old cursor sends everything after that cursor,
+        // so clients don't miss changes made while they were disconnected.
+        drop(socket);
+
+        let mut socket = session.connect_socket(0);
+        let resumed_response = socket.read_response();
+        assert!(resumed_response.message_cursor >= socket_response.message_cursor);
+        assert_eq!(
+            resumed_response.messages.len(),
+            resumed_response.message_cursor as usize
+        );
+    });
+}
+
 #[test]
 fn add_folder() {
     run_serve_test("add_folder", |session, mut redactions| {
@@ -220,3 +254,187 @@ fn empty_json_model() {
         );
     });
 }
+
+#[test]
+fn snapshot_errors() {
+    run_serve_test("snapshot_errors", |session, mut redactions| {
+        let info = session.get_api_rojo().unwrap();
+        let root_id = info.root_instance_id;
+
+        assert_yaml_snapshot!("snapshot_errors_info", redactions.redacted_yaml(info));
+
+        let read_response = session.get_api_read(root_id).unwrap();
+        assert_yaml_snapshot!(
+            "snapshot_errors_all",
+            read_response.intern_and_redact(&mut redactions, root_id)
+        );
+
+        assert!(session.get_api_errors().unwrap().errors.is_empty());
+
+        let data_path = session.path().join("src/data.json");
+        fs::write(&data_path, "{ not json").unwrap();
+
+        // Failed snapshots don't produce any messages, so we poll for the
+        // error to show up instead.
+        let mut errors = Vec::new();
+        for _ in 0..20 {
+            errors = session.get_api_errors().unwrap().errors;
+            if !errors.is_empty() {
+                break;
+            }
+
+            thread::sleep(Duration::from_millis(100));
+        }
+
+        assert_eq!(errors.len(), 1);
+        assert!(errors[0].path.ends_with("src/data.json"));
+        assert!(errors[0].message.contains("malformed JSON"));
+
+        // The instance keeps its last good state while the file is broken.
+        let read_response = session.get_api_read(root_id).unwrap();
+        assert_yaml_snapshot!(
+            "snapshot_errors_all",
+            read_response.intern_and_redact(&mut redactions, root_id)
+        );
+
+        fs::write(&data_path, r#"{ "value": 2 }"#).unwrap();
+
+        let subscribe_response = session.get_api_subscribe(0).unwrap();
+        assert_yaml_snapshot!(
+            "snapshot_errors_subscribe",
+            subscribe_response.intern_and_redact(&mut redactions, ())
+        );
+
+        assert!(session.get_api_errors().unwrap().errors.is_empty());
+    });
+}
+
+#[test]
+fn project_extends() {
+    run_serve_test("project_extends", |session, mut redactions| {
+        let info = session.get_api_rojo().unwrap();
+        let root_id = info.root_instance_id;
+
+        assert_yaml_snapshot!("project_extends_info", redactions.redacted_yaml(info));
+
+        let read_response = session.get_api_read(root_id).unwrap();
+        assert_yaml_snapshot!(
+            "project_extends_all",
+            read_response.intern_and_redact(&mut redactions, root_id)
+        );
+
+        // Changing a project that's extended reloads the project extending it.
+        let base_path = session.path().join("base.project.json");
+        let base = fs::read_to_string(&base_path).unwrap();
+        fs::write(&base_path, base.replace("\"base\"", "\"updated\"")).unwrap();
+
+        let subscribe_response = session.get_api_subscribe(0).unwrap();
+        assert_yaml_snapshot!(
+            "project_extends_subscribe",
+            subscribe_response.intern_and_redact(&mut redactions, ())
+        );
+
+        let read_response = session.get_api_read(root_id).unwrap();
+        assert_yaml_snapshot!(
+            "project_extends_all-2",
+            read_response.intern_and_redact(&mut redactions, root_id)
+        );
+    });
+}
+
+#[test]
+fn edit_after_write() {
+    run_serve_test("scripts", |session, _redactions| {
+        let info = session.get_api_rojo().unwrap();
+
+        let main_id = Ref::new();
+        let helper_id = Ref::new();
+
+        let mut added = HashMap::new();
+        added.insert(
+            main_id,
+            AddedInstance {
+                parent: info.root_instance_id,
+                name: "Main".to_owned(),
+                class_name: "Script".to_owned(),
+                properties: vec![("Source".to_owned(), Variant::String("print(1)".into()))]
+                    .into_iter()
+                    .collect(),
+                children: vec![helper_id],
+            },
+        );
+        added.insert(
+            helper_id,
+            AddedInstance {
+                parent: main_id,
+                name: "Helper".to_owned(),
+                class_name: "ModuleScript".to_owned(),
+                properties: HashMap::new(),
+                children: Vec::new(),
+            },
+        );
+
+        session
+            .post_api_write(&WriteRequest {
+                session_id: info.session_id,
+                removed: Vec::new(),
+                added,
+                updated: Vec::new(),
+            })
+            .unwrap();
+
+        let subscribe_response = session.get_api_subscribe(0).unwrap();
+        let mut cursor = subscribe_response.message_cursor;
+        let main = subscribe_response
+            .messages
+            .iter()
+            .flat_map(|message| message.added.values())
+            .find(|instance| instance.name == "Main")
+            .expect("Main was not added");
+        let main_id = main.id;
+
+        // The init script is written inside a directory that was just created,
+        // so the watcher may never report it being written.
+        let init_path = session.path().join("src/Main/init.server.lua");
+        assert_eq!(fs::read_to_string(&init_path).unwrap(), "print(1)");
+
+        fs::write(&init_path, "print(2)").unwrap();
+
+        loop {
+            let subscribe_response = session.get_api_subscribe(cursor).unwrap();
+            cursor = subscribe_response.message_cursor;
+
+            let source = subscribe_response
+                .messages
+                .iter()
+                .flat_map(|message| &message.updated)
+                .filter(|update| update.id == main_id)
+                .find_map(|update| update.changed_properties.get("Source"));
+
+            if let Some(source) = source {
+                assert_eq!(source, &Some(Variant::String("print(2)".into())));
+                break;
+            }
+        }
+    });
+}
+
+#[test]
+fn subscribe_expired_cursor() {
+    run_serve_test_with_args(
+        "scripts",
+        &["--history-limit", "1"],
+        |session, _redactions| {
+            // Each change is pushed on its own, so only the second one is kept.
+            fs::write(session.path().join("src/foo.lua"), "Updated foo!").unwrap();
+            let cursor = session.get_api_subscribe(0).unwrap().message_cursor;
+
+            fs::write(session.path().join("src/foo.lua"), "Updated foo again!").unwrap();
+            session.get_api_subscribe(cursor).unwrap();
+
+            let (status, error) = session.get_api_subscribe_error(0).unwrap();
+            assert_eq!(status, StatusCode::GONE);
+            assert!(matches!(error.kind, ErrorResponseKind::ResyncRequired));
+        },
+    );
+}